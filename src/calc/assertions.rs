@@ -0,0 +1,94 @@
+use std::fmt::{Display, Formatter};
+use crate::calc::Locale;
+use crate::calc::engine::{CellRef, CSVEngine, Engine, Expression};
+use crate::calc::operators::holds;
+
+/// An `ASSERT` call of a sheet and whether its condition held.
+pub struct Assertion {
+    pub cell: CellRef,
+    pub message: String,
+    pub passed: bool,
+}
+
+/// Every assertion of a sheet, in reading order.
+pub struct TestReport {
+    pub assertions: Vec<Assertion>,
+}
+
+impl TestReport {
+    /// Evaluates every `ASSERT` call of every formula, including nested ones, from the cell
+    /// holding it.
+    pub fn run(engine: &CSVEngine, locale: Locale) -> Self {
+        let mut assertions = Vec::new();
+        for cell in engine.source() {
+            if let Some(formula) = engine.formula(&cell.position) {
+                collect(engine, locale, cell.position, &formula, &mut assertions);
+            }
+        }
+        TestReport { assertions }
+    }
+
+    pub fn failed(&self) -> usize {
+        self.assertions.iter().filter(|a| !a.passed).count()
+    }
+}
+
+fn collect(engine: &CSVEngine, locale: Locale, cell: CellRef, expression: &Expression, assertions: &mut Vec<Assertion>) {
+    match expression {
+        Expression::Call(name, args) => {
+            if let ("ASSERT", [condition, rest @ ..]) = (name.as_str(), args.as_slice()) {
+                let described = locale.localize_formula(&condition.to_string());
+                let (passed, detail) = match holds(engine.within(cell, || engine.eval(condition))) {
+                    Ok(passed) => (passed, String::new()),
+                    Err(e) => (false, format!(" ({})", e)),
+                };
+                let message = match rest.first().map(|m| engine.within(cell, || engine.eval(m)).as_text()) {
+                    Some(Ok(message)) => message,
+                    _ => described,
+                };
+                assertions.push(Assertion { cell, message: message + &detail, passed });
+            }
+            for arg in args {
+                collect(engine, locale, cell, arg, assertions);
+            }
+        },
+        Expression::Negate(e) => collect(engine, locale, cell, e, assertions),
+        Expression::Binary(_, l, r) => {
+            collect(engine, locale, cell, l, assertions);
+            collect(engine, locale, cell, r, assertions);
+        },
+        _ => {}
+    }
+}
+
+impl Display for TestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for assertion in self.assertions.iter().filter(|a| !a.passed) {
+            writeln!(f, "FAILED {}: {}", assertion.cell, assertion.message)?;
+        }
+        writeln!(f, "{} assertions, {} passed, {} failed", self.assertions.len(), self.assertions.len() - self.failed(), self.failed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::Calc;
+
+    #[test]
+    fn evaluates_assertions_from_their_cell() {
+        let calc = Calc::from_string("x\n\"=ASSERT(ROW()=2,\"\"row\"\")\"".to_string());
+        assert_eq!("1 assertions, 1 passed, 0 failed\n", calc.test().unwrap().to_string());
+    }
+
+    #[test]
+    fn reports_failed_assertions() {
+        let calc = Calc::from_string("Total,5\n\"=ASSERT(B1=5,\"\"Total is 5\"\")\",\"=ASSERT(B1>6,\"\"Total over \"\"&6)\"\n=ASSERT(B1/0),\"=IF(ASSERT(B1<0),1,2)\"".to_string());
+        let report = calc.test().unwrap();
+        assert_eq!("\
+FAILED B2: Total over 6
+FAILED A3: B1/0 (#DIV/0!)
+FAILED B3: B1<0
+4 assertions, 1 passed, 3 failed
+", report.to_string());
+    }
+}
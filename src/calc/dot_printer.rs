@@ -0,0 +1,82 @@
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use crate::calc::Table;
+use crate::calc::engine::{Cell, CellRef};
+use crate::framework::Printer;
+
+/// Prints the dependency graph of a sheet as Graphviz DOT, with an edge from every cell to the formulas reading it.
+pub struct DotPrinter;
+
+impl DotPrinter {
+    pub fn new() -> Self {
+        DotPrinter
+    }
+}
+
+impl Printer<Table> for DotPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut edges = BTreeSet::new();
+        for cell in table.cells() {
+            for (start, end) in &cell.references {
+                if start == end {
+                    edges.insert((*start, cell.position));
+                    continue;
+                }
+                for read in table.cells().filter(|c| c.position.is_within(start, end) && c.position != cell.position) {
+                    edges.insert((read.position, cell.position));
+                }
+            }
+        }
+        let nodes: BTreeSet<CellRef> = edges.iter().flat_map(|(from, to)| [*from, *to]).collect();
+
+        let cells: HashMap<CellRef, &Cell> = table.cells().map(|c| (c.position, c)).collect();
+
+        let mut dot = String::from("digraph sheet {\n    node [shape=box];\n");
+        for node in &nodes {
+            let label = match cells.get(node) {
+                Some(cell) => match &cell.formula {
+                    Some(formula) => format!("{}\n{}\n{}", node, formula, cell.content),
+                    None => format!("{}\n{}", node, cell.content),
+                },
+                None => node.to_string(),
+            };
+            dot += &format!("    \"{}\" [label=\"{}\"];\n", node, escape(&label));
+        }
+        for (from, to) in &edges {
+            dot += &format!("    \"{}\" -> \"{}\";\n", from, to);
+        }
+        dot += "}\n";
+        Ok(dot.into_bytes())
+    }
+
+    fn extension() -> &'static str {
+        "dot"
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{Calc, DotPrinter};
+    use crate::framework::{Loader, Printer};
+
+    #[test]
+    fn prints_dependency_graphs() {
+        let table = Calc::from_string("1,\"a\"\"b\",x\n=A1*2,=SUM(A1:B1),".to_string()).with_graph(true).load().unwrap();
+        let dot = String::from_utf8(DotPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!("digraph sheet {
+    node [shape=box];
+    \"A1\" [label=\"A1\\n1\"];
+    \"B1\" [label=\"B1\\na\\\"b\"];
+    \"A2\" [label=\"A2\\n=A1*2\\n2.00\"];
+    \"B2\" [label=\"B2\\n=SUM(A1:B1)\\n1.00\"];
+    \"A1\" -> \"A2\";
+    \"A1\" -> \"B2\";
+    \"B1\" -> \"B2\";
+}
+", dot);
+    }
+}
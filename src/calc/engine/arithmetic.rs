@@ -0,0 +1,142 @@
+use std::cmp::Ordering;
+use crate::calc::engine::{BinaryOp, ErrorKind, Number, Unit, Value};
+
+impl BinaryOp {
+    pub fn apply(&self, left: Value, right: Value) -> Value {
+        match self.try_apply(left, right) {
+            Ok(value) => value,
+            Err(e) => Value::Error(e),
+        }
+    }
+
+    fn try_apply(&self, left: Value, right: Value) -> Result<Value, ErrorKind> {
+        if let Value::Error(e) = left {
+            return Err(e);
+        }
+        if let Value::Error(e) = right {
+            return Err(e);
+        }
+        let (left, right) = match self {
+            BinaryOp::Concat => (left, right),
+            BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Power => (quantity(left), quantity(right)),
+            _ if is_quantity(&left) || is_quantity(&right) => (quantity(left), quantity(right)),
+            _ => (left, right),
+        };
+        if *self != BinaryOp::Concat && (is_quantity(&left) || is_quantity(&right)) {
+            return self.measure(left, right);
+        }
+
+        Ok(match self {
+            BinaryOp::Add => Value::Number(left.as_number()?.add(right.as_number()?)?),
+            BinaryOp::Subtract => Value::Number(left.as_number()?.sub(right.as_number()?)?),
+            BinaryOp::Multiply => Value::Number(left.as_number()?.mul(right.as_number()?)?),
+            BinaryOp::Divide => Value::Number(left.as_number()?.div(right.as_number()?)?),
+            BinaryOp::Power => Value::Number(left.as_number()?.pow(right.as_number()?)?),
+            BinaryOp::Concat => Value::Text(left.as_text()? + &right.as_text()?),
+            _ => Value::Bool(self.holds(compare(&left, &right))),
+        })
+    }
+
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            BinaryOp::Equal => ordering == Ordering::Equal,
+            BinaryOp::NotEqual => ordering != Ordering::Equal,
+            BinaryOp::Less => ordering == Ordering::Less,
+            BinaryOp::LessEqual => ordering != Ordering::Greater,
+            BinaryOp::Greater => ordering == Ordering::Greater,
+            BinaryOp::GreaterEqual => ordering != Ordering::Less,
+            _ => false,
+        }
+    }
+
+    /// Applies the operator to quantities. Sums and comparisons convert the right operand to
+    /// the unit of the left one and are `#UNIT!` for units of different dimensions.
+    fn measure(&self, left: Value, right: Value) -> Result<Value, ErrorKind> {
+        let unit = match (&left, &right) {
+            (Value::Quantity(_, unit), _) | (_, Value::Quantity(_, unit)) => unit.clone(),
+            _ => Unit::default(),
+        };
+        Ok(match self {
+            BinaryOp::Add => Value::Quantity(amount(&left, &unit)?.add(amount(&right, &unit)?)?, unit),
+            BinaryOp::Subtract => Value::Quantity(amount(&left, &unit)?.sub(amount(&right, &unit)?)?, unit),
+            BinaryOp::Multiply | BinaryOp::Divide => {
+                let ((a, u), (b, v)) = (parts(&left)?, parts(&right)?);
+                let (unit, scale) = u.times(&v, if *self == BinaryOp::Multiply { 1 } else { -1 })?;
+                let product = if *self == BinaryOp::Multiply { a.mul(b)? } else { a.div(b)? };
+                Value::measured(product.mul(Number::Float(scale))?, unit)
+            },
+            BinaryOp::Power => {
+                let (a, u) = parts(&left)?;
+                let power = right.as_number()?;
+                if power.to_f64().fract() != 0.0 || power.to_f64().abs() > 64.0 {
+                    return Err(ErrorKind::Unit);
+                }
+                Value::measured(a.pow(power)?, u.power(power.to_f64() as i8)?)
+            },
+            BinaryOp::Concat => Value::Text(left.as_text()? + &right.as_text()?),
+            _ => {
+                let (a, b) = (amount(&left, &unit)?, amount(&right, &unit)?);
+                Value::Bool(self.holds(a.partial_cmp(&b).unwrap_or(Ordering::Equal)))
+            },
+        })
+    }
+}
+
+fn is_quantity(value: &Value) -> bool {
+    matches!(value, Value::Quantity(..))
+}
+
+/// Reads text such as `"30 ms"` as a quantity.
+fn quantity(value: Value) -> Value {
+    match value {
+        Value::Text(t) => Value::parse_quantity(&t, |n| Number::parse(n, false)).unwrap_or(Value::Text(t)),
+        value => value,
+    }
+}
+
+/// Amount of an operand in a unit, blanks counting as zero.
+fn amount(value: &Value, unit: &Unit) -> Result<Number, ErrorKind> {
+    match value {
+        Value::Quantity(n, from) => from.convert(*n, unit),
+        Value::Empty => Ok(Number::Float(0.0)),
+        value => Err(value.as_number().err().unwrap_or(ErrorKind::Unit)),
+    }
+}
+
+fn parts(value: &Value) -> Result<(Number, Unit), ErrorKind> {
+    match value {
+        Value::Quantity(n, unit) => Ok((*n, unit.clone())),
+        value => Ok((value.as_number()?, Unit::default())),
+    }
+}
+
+/// Orders values like Excel: numbers before text before booleans, text case-insensitively.
+/// Blanks compare as the zero value of the other side.
+pub fn compare(left: &Value, right: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Empty | Value::Number(_) | Value::Quantity(..) => 0,
+            Value::Text(_) => 1,
+            Value::Bool(_) => 2,
+            Value::Error(_) => 3,
+        }
+    }
+
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+        (Value::Quantity(l, u), Value::Quantity(r, v)) => v.convert(*r, u).ok().and_then(|r| l.partial_cmp(&r)).unwrap_or(Ordering::Equal),
+        (Value::Text(l), Value::Text(r)) => l.to_lowercase().cmp(&r.to_lowercase()),
+        (Value::Bool(l), Value::Bool(r)) => l.cmp(r),
+        (Value::Empty, r) => compare(&blank_like(r), r),
+        (l, Value::Empty) => compare(l, &blank_like(l)),
+        (l, r) => rank(l).cmp(&rank(r)),
+    }
+}
+
+fn blank_like(value: &Value) -> Value {
+    match value {
+        Value::Text(_) => Value::Text(String::new()),
+        Value::Bool(_) => Value::Bool(false),
+        _ => Value::Number(Number::Float(0.0)),
+    }
+}
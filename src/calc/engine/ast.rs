@@ -1,29 +1,272 @@
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
-use crate::calc::engine::CellRef;
+use crate::calc::engine::{CellRef, Number, Unit};
 
 #[derive(Debug)]
 pub enum Expression {
     Call(String, Vec<Expression>),
     Reference(CellRef),
     Range(CellRef, CellRef),
-    Literal(Value)
+    Literal(Value),
+    Negate(Box<Expression>),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Corners of the cells and ranges read by the expression.
+    pub fn references(&self) -> Vec<(CellRef, CellRef)> {
+        match self {
+            Expression::Reference(r) => vec![(*r, *r)],
+            Expression::Range(start, end) => vec![(*start, *end)],
+            Expression::Call(_, args) => args.iter().flat_map(|a| a.references()).collect(),
+            Expression::Negate(e) => e.references(),
+            Expression::Binary(_, l, r) => [l.references(), r.references()].concat(),
+            Expression::Literal(_) => Vec::new(),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Binary(op, ..) => op.precedence(),
+            _ => u8::MAX,
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            },
+            Expression::Reference(r) => write!(f, "{}", r),
+            Expression::Range(start, end) => write!(f, "{}:{}", start, end),
+            Expression::Literal(Value::Text(t)) => write!(f, "\"{}\"", t.replace('"', "\"\"")),
+            Expression::Literal(Value::Number(n)) => write!(f, "{}", n),
+            Expression::Literal(v) => write!(f, "{}", v),
+            Expression::Negate(e) if matches!(**e, Expression::Binary(..)) => write!(f, "-({})", e),
+            Expression::Negate(e) => write!(f, "-{}", e),
+            Expression::Binary(op, l, r) => {
+                // Operators are left associative, so a right operand of equal precedence keeps its parentheses.
+                if l.precedence() < op.precedence() {
+                    write!(f, "({})", l)?;
+                } else {
+                    write!(f, "{}", l)?;
+                }
+                write!(f, "{}", op)?;
+                if r.precedence() <= op.precedence() {
+                    write!(f, "({})", r)
+                } else {
+                    write!(f, "{}", r)
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl BinaryOp {
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 0,
+            BinaryOp::Concat => 1,
+            BinaryOp::Add | BinaryOp::Subtract => 2,
+            BinaryOp::Multiply | BinaryOp::Divide => 3,
+            BinaryOp::Power => 4,
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Power => "^",
+            BinaryOp::Concat => "&",
+            BinaryOp::Equal => "=",
+            BinaryOp::NotEqual => "<>",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Value {
+    Empty,
     Bool(bool),
-    Number(f64),
+    Number(Number),
     Text(String),
-    Error(&'static str)
+    Error(ErrorKind),
+    /// A number of some unit, such as `512 MiB`.
+    Quantity(Number, Unit),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    Null,
+    Div0,
+    Value,
+    Ref,
+    Name,
+    Num,
+    NA,
+    Circ,
+    Invalid,
+    Spill,
+    Unit,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 11] = [
+        ErrorKind::Null, ErrorKind::Div0, ErrorKind::Value, ErrorKind::Ref,
+        ErrorKind::Name, ErrorKind::Num, ErrorKind::NA, ErrorKind::Circ,
+        ErrorKind::Invalid, ErrorKind::Spill, ErrorKind::Unit,
+    ];
+
+    pub fn parse(text: &str) -> Option<Self> {
+        ErrorKind::ALL.into_iter().find(|e| e.to_string().eq_ignore_ascii_case(text.trim()))
+    }
+
+    /// Number returned by `ERROR.TYPE`, matching Excel where it has one. Errors Excel lacks
+    /// follow its last code, 14 for `#CALC!`, so they never stand for one of its errors.
+    pub fn code(&self) -> u8 {
+        match self {
+            ErrorKind::Null => 1,
+            ErrorKind::Div0 => 2,
+            ErrorKind::Value => 3,
+            ErrorKind::Ref => 4,
+            ErrorKind::Name => 5,
+            ErrorKind::Num => 6,
+            ErrorKind::NA => 7,
+            ErrorKind::Spill => 9,
+            ErrorKind::Circ => 15,
+            ErrorKind::Invalid => 16,
+            ErrorKind::Unit => 17,
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Null => write!(f, "#NULL!"),
+            ErrorKind::Div0 => write!(f, "#DIV/0!"),
+            ErrorKind::Value => write!(f, "#VALUE!"),
+            ErrorKind::Ref => write!(f, "#REF!"),
+            ErrorKind::Name => write!(f, "#NAME?"),
+            ErrorKind::Num => write!(f, "#NUM!"),
+            ErrorKind::NA => write!(f, "#N/A"),
+            ErrorKind::Circ => write!(f, "#CIRC!"),
+            ErrorKind::Invalid => write!(f, "#INVALID!"),
+            ErrorKind::Spill => write!(f, "#SPILL!"),
+            ErrorKind::Unit => write!(f, "#UNIT!"),
+        }
+    }
+}
+
+impl Value {
+    /// Coerces the value to a number the way arithmetic operators do. Quantities are not
+    /// plain numbers, so they are `#UNIT!`.
+    pub fn as_number(&self) -> Result<Number, ErrorKind> {
+        match self {
+            Value::Empty => Ok(Number::Float(0.0)),
+            Value::Number(n) => Ok(*n),
+            Value::Bool(b) => Ok(Number::Float(if *b { 1.0 } else { 0.0 })),
+            Value::Text(t) => Number::parse(t, false).ok_or(ErrorKind::Value),
+            Value::Error(e) => Err(*e),
+            Value::Quantity(..) => Err(ErrorKind::Unit),
+        }
+    }
+
+    /// A quantity, or a plain number when its unit is plain.
+    pub fn measured(n: Number, unit: Unit) -> Value {
+        match unit.is_plain() {
+            true => Value::Number(n),
+            false => Value::Quantity(n, unit),
+        }
+    }
+
+    /// Reads text such as `512 MiB` or `1.5GB` as a quantity, with the number read by `number`.
+    pub fn parse_quantity(text: &str, number: impl Fn(&str) -> Option<Number>) -> Option<Value> {
+        let text = text.trim();
+        let (amount, unit) = match text.rsplit_once(char::is_whitespace) {
+            Some(split) => split,
+            None => text.split_at(text.find(|c: char| c.is_alphabetic() || c == 'µ')?),
+        };
+        Some(Value::Quantity(number(amount)?, Unit::parse(unit)?))
+    }
+
+    /// Coerces the value to text the way the `&` operator does.
+    pub fn as_text(&self) -> Result<String, ErrorKind> {
+        match self {
+            Value::Number(n) => Ok(format!("{}", n)),
+            Value::Quantity(n, unit) => Ok(format!("{} {}", n, unit)),
+            Value::Error(e) => Err(*e),
+            v => Ok(v.to_string()),
+        }
+    }
+
+    /// Orders values the way sorting does: numbers, then text ignoring case, logical values,
+    /// errors and blanks.
+    pub fn sort_order(&self, other: &Value) -> Ordering {
+        let rank = |v: &Value| match v {
+            Value::Number(_) | Value::Quantity(..) => 0,
+            Value::Text(_) => 1,
+            Value::Bool(_) => 2,
+            Value::Error(_) => 3,
+            Value::Empty => 4,
+        };
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.to_f64().total_cmp(&b.to_f64()),
+            (Value::Quantity(a, u), Value::Quantity(b, v)) if u.compatible(v) => match v.convert(*b, u) {
+                Ok(b) => a.to_f64().total_cmp(&b.to_f64()),
+                Err(_) => Ordering::Equal,
+            },
+            (Value::Text(a), Value::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
 }
 
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            Value::Empty => Ok(()),
             Value::Bool(v) => if *v { write!(f, "TRUE") } else { write!(f, "FALSE") },
             Value::Number(n) => write!(f, "{:.2}", n),
             Value::Text(t) => write!(f, "{}", t),
-            Value::Error(e) => write!(f, "#{}", e.to_uppercase()),
+            Value::Error(e) => write!(f, "{}", e),
+            Value::Quantity(n, unit) => write!(f, "{:.2} {}", n, unit),
         }
     }
 }
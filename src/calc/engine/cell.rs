@@ -1,11 +1,37 @@
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use crate::calc::engine::Value;
+use crate::calc::engine::expression_parser::parse_at;
+
+static NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\$?[A-Z]+\$?[1-9]\d*$").unwrap());
+static R1C1: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^R(\[-?\d+\]|\d+)?C(\[-?\d+\]|\d+)?$").unwrap());
+/// A whole R1C1 reference in any form, or the parts of an A1 one, followed by `(` when it is
+/// a function name such as `LOG10`.
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"R(?:\[-?\d+\]|\d+)?C(?:\[-?\d+\]|\d+)?(?:[^A-Za-z0-9_]|$)|(\$?)([A-Z]+)(\$?)(\d+)(\(?)").unwrap()
+});
+/// A reference or range in either style, followed by `(` or more of a word when it is part of
+/// a name such as `LOG10`.
+static AREA: LazyLock<Regex> = LazyLock::new(|| {
+    let reference = r"R(?:\[-?\d+\]|\d+)?C(?:\[-?\d+\]|\d+)?|\$?[A-Z]+\$?\d+";
+    Regex::new(&format!(r"({0})(?::({0}))?([(A-Za-z0-9_]?)", reference)).unwrap()
+});
 
 #[derive(Debug, Clone)]
 pub struct Cell {
     pub position: CellRef,
     pub delimiter: Delimiter,
     pub content: String,
+    /// Source formula, kept once the content is replaced by its result.
+    pub formula: Option<String>,
+    /// Computed value, once evaluated.
+    pub value: Option<Value>,
+    /// Corners of the areas the formula reads, including those computed by functions such
+    /// as `INDIRECT`, when the dependency graph was asked for.
+    pub references: Vec<(CellRef, CellRef)>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Ord)]
@@ -19,23 +45,65 @@ impl CellRef {
         CellRef { row, column }
     }
 
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
     pub fn parse(text: &str) -> Self {
-        let mut row = 0;
-        let mut column = 0;
+        let mut row: usize = 0;
+        let mut column: usize = 0;
 
         for char in text.bytes() {
-            if char >= b'A' && char <= b'Z' {
-                column *= 10;
-                column += char - b'A';
+            if char.is_ascii_uppercase() {
+                column = column * 26 + (char - b'A') as usize + 1;
             }
 
-            if char >= b'0' && char <= b'9' {
-                row *= 10;
-                row += char - b'0' - 1;
+            if char.is_ascii_digit() {
+                row = row * 10 + (char - b'0') as usize;
             }
         }
 
-        CellRef{ row: row as usize, column: column as usize }
+        CellRef{ row: row.saturating_sub(1), column: column.saturating_sub(1) }
+    }
+
+    /// Parses a reference such as `B12` or `$B$12`, rejecting anything else.
+    pub fn from_name(text: &str) -> Option<Self> {
+        if NAME.is_match(text.trim()) { Some(CellRef::parse(text.trim())) } else { None }
+    }
+
+    /// Parses an R1C1 reference such as `R2C3`, or `R[-1]C` relative to a cell, rejecting
+    /// anything else and references before the first row or column.
+    pub fn from_r1c1(text: &str, at: CellRef) -> Option<Self> {
+        let captures = R1C1.captures(text.trim())?;
+        let part = |index: usize, current: usize| match captures.get(index).map(|m| m.as_str()) {
+            None => Some(current),
+            Some(part) => match part.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+                Some(relative) => usize::try_from(current as isize + relative.parse::<isize>().ok()?).ok(),
+                None => part.parse::<usize>().ok()?.checked_sub(1),
+            },
+        };
+        Some(CellRef::new(part(1, at.row)?, part(2, at.column)?))
+    }
+
+    /// Spreadsheet name of a zero based column, such as `A` or `AB`.
+    pub fn column_name(column: usize) -> String {
+        let mut name = Vec::new();
+        let mut column = column + 1;
+        while column > 0 {
+            column -= 1;
+            name.insert(0, b'A' + (column % 26) as u8);
+            column /= 26;
+        }
+        String::from_utf8(name).unwrap()
+    }
+
+    /// Whether the cell lies in the area between two corners.
+    pub fn is_within(&self, start: &CellRef, end: &CellRef) -> bool {
+        (start.row..=end.row).contains(&self.row) && (start.column..=end.column).contains(&self.column)
     }
 
     pub fn range(start: &CellRef, end: &CellRef) -> Vec<CellRef> {
@@ -61,6 +129,101 @@ impl PartialOrd<Self> for CellRef {
     }
 }
 
+impl Display for CellRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", CellRef::column_name(self.column), self.row + 1)
+    }
+}
+
+/// Moves the relative references of a formula by a number of rows and columns,
+/// leaving `$` anchored parts, R1C1 references and quoted text alone.
+pub fn shift_references(formula: &str, rows: isize, columns: isize) -> String {
+    formula.split('"')
+        .enumerate()
+        .map(|(i, part)| if i % 2 == 1 {
+            part.to_string()
+        } else {
+            REFERENCE.replace_all(part, |c: &Captures| {
+                if c.get(2).is_none() || !c[5].is_empty() {
+                    return c[0].to_string();
+                }
+                let position = CellRef::parse(&format!("{}{}", &c[2], &c[4]));
+                let column = match &c[1] {
+                    "$" => c[2].to_string(),
+                    _ => CellRef::column_name((position.column as isize + columns).max(0) as usize),
+                };
+                let row = match &c[3] {
+                    "$" => position.row + 1,
+                    _ => (position.row as isize + rows).max(0) as usize + 1,
+                };
+                format!("{}{}{}{}", &c[1], column, &c[3], row)
+            }).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\"")
+}
+
+/// Rewrites the references of a formula held by a cell for cells that were moved, in A1 style
+/// and keeping their `$` anchors. Returns `None` when a cell it reads has no new position, or a
+/// range no longer holds the same cells in the same order.
+pub fn move_references(formula: &str, at: CellRef, moved: impl Fn(CellRef) -> Option<CellRef>) -> Option<String> {
+    let mut kept = true;
+    let formula = formula.split('"')
+        .enumerate()
+        .map(|(i, part)| if i % 2 == 1 {
+            part.to_string()
+        } else {
+            AREA.replace_all(part, |c: &Captures| {
+                if !c[3].is_empty() {
+                    return c[0].to_string();
+                }
+                let cell = |name: &str| CellRef::from_r1c1(name, at).or_else(|| CellRef::from_name(name));
+                let (first, last) = match (cell(&c[1]), c.get(2).map(|last| cell(last.as_str()))) {
+                    (Some(first), None) => (first, first),
+                    (Some(first), Some(Some(last))) => (first, last),
+                    _ => {
+                        kept = false;
+                        return c[0].to_string();
+                    }
+                };
+                let start = CellRef::new(first.row.min(last.row), first.column.min(last.column));
+                let end = CellRef::new(first.row.max(last.row), first.column.max(last.column));
+                let corner = moved(start).filter(|corner| CellRef::range(&start, &end).into_iter().all(|cell| {
+                    moved(cell) == Some(CellRef::new(corner.row + cell.row - start.row, corner.column + cell.column - start.column))
+                }));
+                let corner = match corner {
+                    Some(corner) => corner,
+                    None => {
+                        kept = false;
+                        return c[0].to_string();
+                    }
+                };
+                let rename = |name: &str, cell: CellRef| {
+                    let column = if name.starts_with('$') { "$" } else { "" };
+                    let row = if name[1..].contains('$') { "$" } else { "" };
+                    format!("{}{}{}{}", column, CellRef::column_name(cell.column), row, cell.row + 1)
+                };
+                match c.get(2) {
+                    Some(name) => format!("{}:{}", rename(&c[1], corner),
+                        rename(name.as_str(), CellRef::new(corner.row + end.row - start.row, corner.column + end.column - start.column))),
+                    None => rename(&c[1], corner),
+                }
+            }).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\"");
+    if kept { Some(formula) } else { None }
+}
+
+/// Writes a formula with R1C1 references in A1 style for the cell holding it, as spreadsheet
+/// files expect. Formulas without them are kept as written, with their `$` anchors.
+pub fn a1_formula(formula: &str, at: CellRef) -> String {
+    let r1c1 = formula.split('"')
+        .step_by(2)
+        .any(|part| REFERENCE.captures_iter(part).any(|c| c.get(2).is_none()));
+    if r1c1 { parse_at(formula, at).to_string() } else { formula.to_string() }
+}
+
 #[derive(Debug, Clone)]
 pub enum Delimiter {
     Comma,
@@ -74,18 +237,91 @@ impl Cell {
             position: CellRef { row, column },
             delimiter,
             content: String::from_utf8(content).expect("UTF-8 format error"),
+            formula: None,
+            value: None,
+            references: Vec::new(),
         }
     }
 }
 
-impl Display for Cell {
+impl Cell {
+    /// Lays out contents as a dense grid with one line per row, as the CSV reader does.
+    pub fn grid(contents: HashMap<CellRef, String>) -> Vec<Cell> {
+        let rows = contents.keys().map(|p| p.row + 1).max().unwrap_or(0);
+        let columns = contents.keys().map(|p| p.column + 1).max().unwrap_or(0);
+        let mut cells = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let content = contents.get(&CellRef::new(row, column)).cloned().unwrap_or_default();
+                let delimiter = match (row + 1 == rows, column + 1 == columns) {
+                    (true, true) => Delimiter::EOF,
+                    (false, true) => Delimiter::NewLine,
+                    _ => Delimiter::Comma,
+                };
+                cells.push(Cell::new(content.into_bytes(), row, column, delimiter));
+            }
+        }
+        cells
+    }
+
+    /// Formats the cell as a CSV field, quoted when needed, followed by its delimiter.
+    pub fn csv(&self, separator: char) -> CsvField<'_> {
+        CsvField { cell: self, separator }
+    }
+}
+
+pub struct CsvField<'a> {
+    cell: &'a Cell,
+    separator: char,
+}
+
+impl<'a> Display for CsvField<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.content)?;
-        match self.delimiter {
-            Delimiter::Comma => write!(f, ","),
-            Delimiter::NewLine => write!(f, "\n"),
+        let content = &self.cell.content;
+        if content.contains([self.separator, '"', '\n']) {
+            write!(f, "\"{}\"", content.replace('"', "\"\""))?;
+        } else {
+            write!(f, "{}", content)?;
+        }
+        match self.cell.delimiter {
+            Delimiter::Comma => write!(f, "{}", self.separator),
+            Delimiter::NewLine => writeln!(f),
             Delimiter::EOF => write!(f, ""),
         }
     }
 }
 
+impl Display for Cell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.csv(','))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::engine::{a1_formula, CellRef, shift_references};
+
+    #[test]
+    fn parses_references() {
+        assert_eq!(CellRef::new(11, 27), CellRef::parse("AB12"));
+        assert_eq!(CellRef::new(0, 0), CellRef::parse("$A$1"));
+        assert_eq!("AB", CellRef::column_name(27));
+        assert_eq!("AB12", CellRef::new(11, 27).to_string());
+        assert_eq!(Some(CellRef::new(2, 1)), CellRef::from_name("$B3"));
+        assert_eq!(None, CellRef::from_name("B0"));
+        assert_eq!(None, CellRef::from_name("SUM"));
+    }
+
+    #[test]
+    fn shifts_relative_references() {
+        assert_eq!("B4>$C$2+C4&\"A1\"", shift_references("B2>$C$2+C2&\"A1\"", 2, 0));
+        assert_eq!("SUM(C$1:$A3)+LOG10(B2)", shift_references("SUM(B$1:$A2)+LOG10(A1)", 1, 1));
+        assert_eq!("R[0]C2+RC2*R2C3-RC[-1]+RCA3", shift_references("R[0]C2+RC2*R2C3-RC[-1]+RCA2", 1, 0));
+    }
+
+    #[test]
+    fn writes_r1c1_formulas_in_a1_style() {
+        assert_eq!("SUM(B3:C3)*A1", a1_formula("SUM(RC[-1]:RC)*R1C1", CellRef::new(2, 2)));
+        assert_eq!("$A$1&\"RC\"", a1_formula("$A$1&\"RC\"", CellRef::new(2, 2)));
+    }
+}
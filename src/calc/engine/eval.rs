@@ -1,13 +1,29 @@
+use std::cell::RefCell;
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap};
-use crate::calc::engine::{Cell, CellRef, Engine, Operator};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use crate::calc::engine::{Cell, CellRef, Delimiter, Engine, ErrorKind, Number, Operator};
 use crate::calc::engine::{Expression, Value};
-use crate::calc::engine::expression_parser::parse;
+use crate::calc::engine::expression_parser::{parse, parse_at};
+use crate::calc::engine::random::Random;
+use crate::calc::locale::Locale;
 
 pub struct CSVEngine {
     heap: BinaryHeap<Reverse<CellRef>>,
     cells: HashMap<CellRef, Cell>,
-    operators: HashMap<&'static str, Box<dyn Operator<CSVEngine>>>
+    operators: HashMap<String, Box<dyn Operator<CSVEngine>>>,
+    evaluating: RefCell<HashSet<CellRef>>,
+    assumed: RefCell<HashMap<CellRef, Value>>,
+    /// Anchor of the formula filling each spilled cell.
+    spills: HashMap<CellRef, CellRef>,
+    /// Anchors whose table would cover other content.
+    blocked: HashSet<CellRef>,
+    /// Tables of the spilling formulas, until the sheet changes.
+    arrays: RefCell<HashMap<CellRef, Vec<Vec<Value>>>>,
+    random: RefCell<Random>,
+    draws: RefCell<HashMap<(CellRef, usize), f64>>,
+    drawing: RefCell<Vec<(CellRef, usize)>>,
+    decimal: bool,
+    locale: Locale,
 }
 
 impl CSVEngine {
@@ -15,7 +31,17 @@ impl CSVEngine {
         let mut engine = CSVEngine {
             cells: HashMap::new(),
             heap: BinaryHeap::new(),
-            operators: HashMap::new()
+            operators: HashMap::new(),
+            evaluating: RefCell::new(HashSet::new()),
+            assumed: RefCell::new(HashMap::new()),
+            spills: HashMap::new(),
+            blocked: HashSet::new(),
+            arrays: RefCell::new(HashMap::new()),
+            random: RefCell::new(Random::new(None)),
+            draws: RefCell::new(HashMap::new()),
+            drawing: RefCell::new(Vec::new()),
+            decimal: false,
+            locale: Locale::default(),
         };
         for cell in reader {
             engine.add_cell(cell);
@@ -24,24 +50,297 @@ impl CSVEngine {
     }
 
     pub fn register_operator(&mut self, operation: impl Operator<CSVEngine> + 'static){
-        self.operators.insert(operation.name(), Box::new(operation));
+        self.operators.insert(operation.name().to_string(), Box::new(operation));
+    }
+
+    /// Whether a function with this name is registered.
+    pub fn has_operator(&self, name: &str) -> bool {
+        self.operators.contains_key(name)
+    }
+
+    /// Evaluates numbers as exact decimals instead of floats.
+    pub fn use_decimal(&mut self, decimal: bool) {
+        self.decimal = decimal;
+    }
+
+    /// Reads numbers and formulas, and writes results, using the conventions of a locale.
+    pub fn use_locale(&mut self, locale: Locale) {
+        self.locale = locale;
+    }
+
+    /// Seeds random functions so every run draws the same numbers.
+    pub fn use_seed(&mut self, seed: Option<u64>) {
+        self.random = RefCell::new(Random::new(seed));
+        self.redraw();
+    }
+
+    /// Forgets the numbers drawn by random functions, so the next evaluation draws new ones.
+    pub fn redraw(&mut self) {
+        self.draws.borrow_mut().clear();
+        self.arrays.borrow_mut().clear();
+    }
+
+    /// Replaces the source content of a cell and any value assumed for it, adding the cell if
+    /// it does not exist.
+    pub fn set_content(&mut self, position: CellRef, content: String) {
+        self.arrays.borrow_mut().clear();
+        self.assumed.borrow_mut().remove(&position);
+        match self.cells.get_mut(&position) {
+            Some(cell) => cell.content = content,
+            None => self.add_cell(Cell::new(content.into_bytes(), position.row(), position.column(), Delimiter::Comma)),
+        }
+    }
+
+    /// Gives a cell a fixed value in place of its content, as when it fails validation.
+    pub fn assume(&mut self, position: CellRef, value: Value) {
+        self.arrays.borrow_mut().clear();
+        self.assumed.borrow_mut().insert(position, value);
+    }
+
+    /// Fills the cells next to formulas calling a spilling function, such as `GROUPBY`, with
+    /// the rest of its table. A formula whose table would cover other content is `#SPILL!`.
+    /// Spilled cells are recomputed from their formula whenever the sheet changes.
+    pub fn spill(&mut self) {
+        self.spills.clear();
+        self.blocked.clear();
+        self.arrays.borrow_mut().clear();
+        let mut added = Vec::new();
+        for cell in self.source() {
+            let anchor = cell.position;
+            if !self.fill(&anchor) {
+                continue;
+            }
+            let spilled: Vec<CellRef> = self.arrays.borrow()[&anchor].iter()
+                .enumerate()
+                .flat_map(|(r, row)| (0..row.len()).map(move |c| CellRef::new(anchor.row() + r, anchor.column() + c)))
+                .skip(1)
+                .collect();
+            let blocked = spilled.iter().any(|position| {
+                self.assumed.borrow().contains_key(position)
+                    || self.spills.contains_key(position)
+                    || self.content(position).is_some_and(|c| !c.trim().is_empty())
+            });
+            if blocked {
+                self.blocked.insert(anchor);
+                continue;
+            }
+            for position in spilled {
+                if !self.cells.contains_key(&position) {
+                    added.push(position);
+                }
+                self.spills.insert(position, anchor);
+            }
+        }
+
+        if !added.is_empty() {
+            for position in added {
+                self.set_content(position, String::new());
+            }
+            self.relayout();
+        }
+    }
+
+    /// Computes the table a formula calling a spilling function fills, as part of its cell,
+    /// unless it is known. Returns whether the cell has one.
+    fn fill(&self, anchor: &CellRef) -> bool {
+        if self.arrays.borrow().contains_key(anchor) {
+            return true;
+        }
+        let (name, arguments) = match self.formula(anchor) {
+            Some(Expression::Call(name, arguments)) => (name, arguments),
+            _ => return false,
+        };
+        let operator = match self.operators.get(&name) {
+            Some(operator) => operator,
+            None => return false,
+        };
+        if !self.evaluating.borrow_mut().insert(*anchor) {
+            self.arrays.borrow_mut().insert(*anchor, vec![vec![Value::Error(ErrorKind::Circ)]]);
+            return true;
+        }
+        let rows = self.within(*anchor, || operator.spill(&arguments, self));
+        self.evaluating.borrow_mut().remove(anchor);
+        match rows {
+            Some(rows) => {
+                self.arrays.borrow_mut().insert(*anchor, rows);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Value of a cell filled by the table of a spilling formula. A table that shrank since
+    /// it was spilled leaves blanks.
+    fn spilled(&self, position: &CellRef, anchor: &CellRef) -> Value {
+        if !self.fill(anchor) {
+            return Value::Empty;
+        }
+        self.arrays.borrow()[anchor]
+            .get(position.row() - anchor.row())
+            .and_then(|row| row.get(position.column() - anchor.column()))
+            .cloned()
+            .unwrap_or(Value::Empty)
+    }
+
+    /// Fills gaps in rows with blank cells and ends every row with a line break, so cells
+    /// added past the edges of the sheet print in place.
+    fn relayout(&mut self) {
+        let mut widths: HashMap<usize, usize> = HashMap::new();
+        for position in self.cells.keys() {
+            let width = widths.entry(position.row()).or_default();
+            *width = (*width).max(position.column() + 1);
+        }
+        let last = widths.keys().max().copied().unwrap_or(0);
+        for (row, width) in widths {
+            for column in 0..width {
+                let position = CellRef::new(row, column);
+                if !self.cells.contains_key(&position) {
+                    self.add_cell(Cell::new(Vec::new(), row, column, Delimiter::Comma));
+                }
+                let cell = self.cells.get_mut(&position).unwrap();
+                cell.delimiter = match (row == last, column + 1 == width) {
+                    (true, true) => Delimiter::EOF,
+                    (false, true) => Delimiter::NewLine,
+                    _ => Delimiter::Comma,
+                };
+            }
+        }
+    }
+
+    /// Source cells, in reading order, as they were before evaluation.
+    pub fn source(&self) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = self.cells.values().cloned().collect();
+        cells.sort_by_key(|c| c.position);
+        cells
+    }
+
+    /// Computes every cell without consuming the engine.
+    pub fn computed(&self) -> Vec<Cell> {
+        self.source().into_iter().map(|c| self.compute(c)).collect()
+    }
+
+    /// Cells the sheet has in the area between two corners, in reading order.
+    pub fn cells_within(&self, start: &CellRef, end: &CellRef) -> Vec<CellRef> {
+        let mut cells: Vec<CellRef> = self.cells.keys()
+            .filter(|position| position.is_within(start, end))
+            .copied()
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// Source content of a cell, if the sheet has it.
+    pub fn content(&self, position: &CellRef) -> Option<&str> {
+        self.cells.get(position).map(|c| c.content.as_str())
+    }
+
+    /// Parsed formula of a cell, if it holds one.
+    pub fn formula(&self, position: &CellRef) -> Option<Expression> {
+        self.content(position)
+            .and_then(|c| c.strip_prefix('='))
+            .map(|f| parse_at(&self.locale.canonical_formula(f), *position))
+    }
+
+    /// Corners of the areas the formula of a cell reads, including those functions such as
+    /// `INDIRECT` and `OFFSET` compute from the sheet.
+    pub fn references(&self, position: &CellRef) -> Vec<(CellRef, CellRef)> {
+        let formula = match self.formula(position) {
+            Some(formula) => formula,
+            None => return Vec::new(),
+        };
+        let mut references = formula.references();
+        self.within(*position, || self.computed_references(&formula, &mut references));
+        references.sort();
+        references.dedup();
+        references
+    }
+
+    /// Runs an evaluation as part of the formula of a cell, so functions such as `ROW()` see it.
+    pub fn within<R>(&self, position: CellRef, evaluation: impl FnOnce() -> R) -> R {
+        self.drawing.borrow_mut().push((position, 0));
+        let result = evaluation();
+        self.drawing.borrow_mut().pop();
+        result
+    }
+
+    fn computed_references(&self, expression: &Expression, references: &mut Vec<(CellRef, CellRef)>) {
+        match expression {
+            Expression::Call(name, arguments) => {
+                if let Some(Ok(area)) = self.reference(name, arguments) {
+                    references.push(area);
+                }
+                for argument in arguments {
+                    self.computed_references(argument, references);
+                }
+            },
+            Expression::Negate(e) => self.computed_references(e, references),
+            Expression::Binary(_, l, r) => {
+                self.computed_references(l, references);
+                self.computed_references(r, references);
+            },
+            _ => {},
+        }
+    }
+
+    /// Every cell whose value depends on a cell, directly or through other formulas, in reading order.
+    pub fn dependents(&self, position: &CellRef) -> Vec<CellRef> {
+        let readers: Vec<(CellRef, Vec<(CellRef, CellRef)>)> = self.cells.keys()
+            .map(|cell| (*cell, self.references(cell)))
+            .filter(|(_, areas)| !areas.is_empty())
+            .collect();
+
+        let mut found = HashSet::new();
+        let mut pending = vec![*position];
+        while let Some(next) = pending.pop() {
+            for (reader, areas) in &readers {
+                if areas.iter().any(|(start, end)| next.is_within(start, end)) && found.insert(*reader) {
+                    pending.push(*reader);
+                }
+            }
+        }
+        let mut dependents: Vec<CellRef> = found.into_iter().filter(|c| c != position).collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Evaluates a formula, written without its leading `=`, against the sheet.
+    pub fn evaluate(&self, formula: &str) -> Value {
+        self.eval(&parse(&self.locale.canonical_formula(formula)))
+    }
+
+    /// Evaluates a formula as if it were in a cell, so relative R1C1 references and `ROW()`
+    /// read from there.
+    pub fn evaluate_at(&self, formula: &str, at: CellRef) -> Value {
+        self.within(at, || self.eval(&parse_at(&self.locale.canonical_formula(formula), at)))
     }
 
     fn compute(&self, mut cell: Cell) -> Cell {
-        if cell.content.starts_with("=") {
-            let parsed = parse(&cell.content[1..]);
-            cell.content = format!("{}", self.eval(&parsed));
+        let value = self.value_at(&cell.position);
+        if cell.content.starts_with('=') {
+            cell.formula = Some(cell.content.clone());
+            cell.content = self.locale.format(&value);
+        } else if self.assumed.borrow().contains_key(&cell.position) || self.spills.contains_key(&cell.position) {
+            cell.content = self.locale.format(&value);
         }
+        cell.value = Some(value);
         cell
     }
 
     fn value_of(&self, cell: &Cell) -> Value {
-        if cell.content.starts_with("=") {
-            let parsed = parse(&cell.content[1..]);
-            return self.eval(&parsed);
+        if let Some(formula) = self.formula(&cell.position) {
+            return match self.eval(&formula) {
+                Value::Empty => Value::Number(Number::Float(0.0)),
+                value => value
+            };
+        }
+        if cell.content.trim().is_empty() {
+            return Value::Empty;
         }
-        if let Ok(float) = cell.content.trim().parse::<f32>() {
-            return Value::Number(float as f64);
+        if let Some(number) = self.locale.parse_number(&cell.content, self.decimal) {
+            return Value::Number(number);
+        }
+        if let Some(quantity) = Value::parse_quantity(&cell.content, |n| self.locale.parse_number(n, self.decimal)) {
+            return quantity;
         }
         if cell.content.to_uppercase() == "TRUE" {
             return Value::Bool(true);
@@ -49,6 +348,9 @@ impl CSVEngine {
         if cell.content.to_uppercase() == "FALSE" {
             return Value::Bool(false);
         }
+        if let Some(error) = ErrorKind::parse(&cell.content) {
+            return Value::Error(error);
+        }
         Value::Text(String::from(&cell.content))
     }
 
@@ -60,10 +362,26 @@ impl CSVEngine {
 
 impl Engine for CSVEngine {
     fn value_at(&self, position: &CellRef) -> Value {
+        if let Some(value) = self.assumed.borrow().get(position) {
+            return value.clone();
+        }
+        if self.blocked.contains(position) {
+            return Value::Error(ErrorKind::Spill);
+        }
+        if let Some(anchor) = self.spills.get(position) {
+            return self.spilled(position, anchor);
+        }
         if let Some(cell) = self.cells.get(position) {
-            self.value_of(cell)
+            if !self.evaluating.borrow_mut().insert(*position) {
+                return Value::Error(ErrorKind::Circ);
+            }
+            self.drawing.borrow_mut().push((*position, 0));
+            let value = self.value_of(cell);
+            self.drawing.borrow_mut().pop();
+            self.evaluating.borrow_mut().remove(position);
+            value
         } else {
-            Value::Number(0.0)
+            Value::Empty
         }
     }
 
@@ -71,8 +389,20 @@ impl Engine for CSVEngine {
         match expr {
             Expression::Call(name, args) => self.call(name, args),
             Expression::Reference(r) => self.value_at(&r),
-            Expression::Range(_, _) => Value::Error("REF!"),
+            Expression::Range(_, _) => Value::Error(ErrorKind::Value),
+            Expression::Literal(Value::Number(n)) if self.decimal => match n.to_decimal_number() {
+                Ok(n) => Value::Number(n),
+                Err(e) => Value::Error(e),
+            },
             Expression::Literal(v) => v.clone(),
+            Expression::Negate(e) => match self.eval(e) {
+                Value::Quantity(n, unit) => Value::Quantity(n.neg(), unit),
+                value => match value.as_number() {
+                    Ok(n) => Value::Number(n.neg()),
+                    Err(e) => Value::Error(e),
+                },
+            },
+            Expression::Binary(op, l, r) => op.apply(self.eval(l), self.eval(r)),
         }
     }
 
@@ -80,9 +410,44 @@ impl Engine for CSVEngine {
         if let Some(operation) = self.operators.get(name) {
             operation.execute(arguments, self)
         } else {
-            Value::Error("NAME?")
+            Value::Error(ErrorKind::Name)
         }
     }
+
+    fn array(&self, name: &str, arguments: &[Expression]) -> Option<Vec<Vec<Value>>> {
+        self.operators.get(name).and_then(|o| o.spill(arguments, self))
+    }
+
+    fn reference(&self, name: &str, arguments: &[Expression]) -> Option<Result<(CellRef, CellRef), ErrorKind>> {
+        self.operators.get(name).and_then(|o| o.reference(arguments, self))
+    }
+
+    fn current(&self) -> Option<CellRef> {
+        self.drawing.borrow().last().map(|(cell, _)| *cell)
+    }
+
+    fn random(&self) -> f64 {
+        let key = match self.drawing.borrow_mut().last_mut() {
+            Some((cell, count)) => {
+                *count += 1;
+                (*cell, *count)
+            },
+            None => return self.random.borrow_mut().uniform(),
+        };
+        *self.draws.borrow_mut().entry(key).or_insert_with(|| self.random.borrow_mut().uniform())
+    }
+
+    fn value_if(&self, cell: &CellRef, input: &CellRef, value: Value) -> Value {
+        let previous = self.assumed.borrow_mut().insert(*input, value);
+        self.arrays.borrow_mut().clear();
+        let result = self.value_at(cell);
+        match previous {
+            Some(previous) => self.assumed.borrow_mut().insert(*input, previous),
+            None => self.assumed.borrow_mut().remove(input),
+        };
+        self.arrays.borrow_mut().clear();
+        result
+    }
 }
 
 impl Iterator for CSVEngine {
@@ -1,7 +1,8 @@
 use logos::{Lexer, Logos};
-use crate::calc::engine::{CellRef, Expression, Value};
+use crate::calc::engine::{BinaryOp, CellRef, ErrorKind, Expression, Number, Value};
 
 #[derive(Logos, Debug, PartialEq, Clone)]
+#[logos(extras = CellRef)]
 enum Token {
     #[token("(")]
     Open,
@@ -14,16 +15,50 @@ enum Token {
     #[token(",")]
     Comma,
 
-    #[regex(r"\$?[A-Z]+\$?\d+")]
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("^")]
+    Caret,
+    #[token("&")]
+    Ampersand,
+    #[token("%")]
+    Percent,
+
+    #[token("=")]
+    Equal,
+    #[token("<>")]
+    NotEqual,
+    #[token("<")]
+    Less,
+    #[token("<=")]
+    LessEqual,
+    #[token(">")]
+    Greater,
+    #[token(">=")]
+    GreaterEqual,
+
+    #[regex(r"\$?[A-Z]+\$?\d+", priority = 10)]
     Reference,
+    #[regex(r"R(\[-?\d+\]|\d+)?C(\[-?\d+\]|\d+)?", priority = 11)]
+    R1C1,
 
-    #[regex("[a-zA-Z]+")]
+    #[regex(r"[a-zA-Z]+(\.[a-zA-Z]+)*")]
     Identifier,
 
-    #[regex(r"\d+(\.\d*)")]
+    #[regex(r"\d+(\.\d*)?([eE][+-]?\d+)?")]
     Number,
     #[regex(r"(TRUE)|(FALSE)")]
     Boolean,
+    #[regex(r#""([^"]|"")*""#)]
+    Text,
+    #[regex(r"#(NULL!|DIV/0!|VALUE!|REF!|NAME\?|NUM!|N/A|CIRC!|INVALID!|SPILL!|UNIT!)")]
+    ErrorLiteral,
 
     #[error]
     #[regex(r"[ \t\n\f]+", logos::skip)]
@@ -32,35 +67,165 @@ enum Token {
 
 
 pub fn parse(content: &str) -> Expression {
-    let mut lex = Token::lexer(content);
-    parse_expression(&mut lex)
+    parse_at(content, CellRef::new(0, 0))
+}
+
+/// Parses the formula of a cell, resolving relative R1C1 references such as `R[-1]C` from it.
+pub fn parse_at(content: &str, at: CellRef) -> Expression {
+    let mut lex = Token::lexer_with_extras(content, at);
+    let expression = parse_expression(&mut lex);
+    if lex.next().is_some() {
+        return Expression::Literal(Value::Error(ErrorKind::Name));
+    }
+    expression
 }
 
 fn parse_expression(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_concat(lex);
+    loop {
+        let op = match peek(lex) {
+            Some(Token::Equal) => BinaryOp::Equal,
+            Some(Token::NotEqual) => BinaryOp::NotEqual,
+            Some(Token::Less) => BinaryOp::Less,
+            Some(Token::LessEqual) => BinaryOp::LessEqual,
+            Some(Token::Greater) => BinaryOp::Greater,
+            Some(Token::GreaterEqual) => BinaryOp::GreaterEqual,
+            _ => return left
+        };
+        lex.next();
+        left = Expression::Binary(op, Box::new(left), Box::new(parse_concat(lex)));
+    }
+}
+
+fn parse_concat(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_additive(lex);
+    while matches!(peek(lex), Some(Token::Ampersand)) {
+        lex.next();
+        left = Expression::Binary(BinaryOp::Concat, Box::new(left), Box::new(parse_additive(lex)));
+    }
+    left
+}
+
+fn parse_additive(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_term(lex);
+    loop {
+        let op = match peek(lex) {
+            Some(Token::Plus) => BinaryOp::Add,
+            Some(Token::Minus) => BinaryOp::Subtract,
+            _ => return left
+        };
+        lex.next();
+        left = Expression::Binary(op, Box::new(left), Box::new(parse_term(lex)));
+    }
+}
+
+fn parse_term(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_power(lex);
+    loop {
+        let op = match peek(lex) {
+            Some(Token::Star) => BinaryOp::Multiply,
+            Some(Token::Slash) => BinaryOp::Divide,
+            _ => return left
+        };
+        lex.next();
+        left = Expression::Binary(op, Box::new(left), Box::new(parse_power(lex)));
+    }
+}
+
+fn parse_power(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_percent(lex);
+    while matches!(peek(lex), Some(Token::Caret)) {
+        lex.next();
+        left = Expression::Binary(BinaryOp::Power, Box::new(left), Box::new(parse_percent(lex)));
+    }
+    left
+}
+
+fn parse_percent(lex: &mut Lexer<Token>) -> Expression {
+    let mut left = parse_unary(lex);
+    while matches!(peek(lex), Some(Token::Percent)) {
+        lex.next();
+        let hundred = Expression::Literal(Value::Number(Number::Float(100.0)));
+        left = Expression::Binary(BinaryOp::Divide, Box::new(left), Box::new(hundred));
+    }
+    left
+}
+
+fn parse_unary(lex: &mut Lexer<Token>) -> Expression {
+    match peek(lex) {
+        Some(Token::Minus) => {
+            lex.next();
+            Expression::Negate(Box::new(parse_unary(lex)))
+        },
+        Some(Token::Plus) => {
+            lex.next();
+            parse_unary(lex)
+        },
+        _ => parse_primary(lex)
+    }
+}
+
+fn parse_primary(lex: &mut Lexer<Token>) -> Expression {
     match lex.next() {
         Some(t) => {
             match t {
                 Token::Identifier => parse_call(lex),
-                Token::Reference => parse_reference(lex),
-                _ => Expression::Literal(Value::Error("EOF"))
+                Token::Reference | Token::R1C1 => parse_reference(t, lex),
+                Token::Number => match Number::parse(lex.slice(), false) {
+                    Some(n) => Expression::Literal(Value::Number(n)),
+                    None => Expression::Literal(Value::Error(ErrorKind::Num))
+                },
+                Token::Boolean => Expression::Literal(Value::Bool(lex.slice() == "TRUE")),
+                Token::Text => {
+                    let quoted = lex.slice();
+                    let text = quoted[1..quoted.len() - 1].replace("\"\"", "\"");
+                    Expression::Literal(Value::Text(text))
+                },
+                Token::ErrorLiteral => match ErrorKind::parse(lex.slice()) {
+                    Some(e) => Expression::Literal(Value::Error(e)),
+                    None => Expression::Literal(Value::Error(ErrorKind::Name))
+                },
+                Token::Open => {
+                    let inner = parse_expression(lex);
+                    if !matches!(lex.next(), Some(Token::Close)) {
+                        return Expression::Literal(Value::Error(ErrorKind::Name));
+                    }
+                    inner
+                },
+                _ => Expression::Literal(Value::Error(ErrorKind::Name))
             }
         }
-        None => Expression::Literal(Value::Error("EOF"))
+        None => Expression::Literal(Value::Error(ErrorKind::Name))
     }
 }
 
-fn parse_reference(lex: &mut Lexer<Token>) -> Expression {
-    let start = CellRef::parse(lex.slice());
+fn parse_reference(token: Token, lex: &mut Lexer<Token>) -> Expression {
+    let start = match cell(token, lex) {
+        Some(start) => start,
+        None => return Expression::Literal(Value::Error(ErrorKind::Ref)),
+    };
     if !matches!(peek(lex), Some(Token::Colon)){
         return Expression::Reference(start);
     }
     lex.next();
-    if !matches!(peek(lex), Some(Token::Reference)){
-        return Expression::Literal(Value::Error("NULL!"));
+    let end = match lex.next() {
+        Some(token @ (Token::Reference | Token::R1C1)) => cell(token, lex),
+        _ => return Expression::Literal(Value::Error(ErrorKind::Null)),
+    };
+    match end {
+        Some(end) => Expression::Range(
+            CellRef::new(start.row().min(end.row()), start.column().min(end.column())),
+            CellRef::new(start.row().max(end.row()), start.column().max(end.column())),
+        ),
+        None => Expression::Literal(Value::Error(ErrorKind::Ref)),
+    }
+}
+
+fn cell(token: Token, lex: &Lexer<Token>) -> Option<CellRef> {
+    match token {
+        Token::R1C1 => CellRef::from_r1c1(lex.slice(), lex.extras),
+        _ => Some(CellRef::parse(lex.slice())),
     }
-    lex.next();
-    let end = CellRef::parse(lex.slice());
-    return Expression::Range(start, end);
 }
 
 fn peek(lex: &mut Lexer<Token>) -> Option<Token> {
@@ -72,7 +237,7 @@ fn parse_call(lex: &mut Lexer<Token>) -> Expression {
     let mut args = Vec::new();
 
     if !matches!(lex.next(), Some(Token::Open)) {
-        return Expression::Literal(Value::Error("NAME"))
+        return Expression::Literal(Value::Error(ErrorKind::Name))
     }
 
     loop {
@@ -87,15 +252,15 @@ fn parse_call(lex: &mut Lexer<Token>) -> Expression {
                     _ => args.push(parse_expression(lex))
                 }
             }
-            None => return Expression::Literal(Value::Error("EOF"))
+            None => return Expression::Literal(Value::Error(ErrorKind::Name))
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::calc::engine::{Expression};
-    use crate::calc::engine::expression_parser::parse;
+    use crate::calc::engine::{BinaryOp, CellRef, Expression, Value};
+    use crate::calc::engine::expression_parser::{parse, parse_at};
 
     #[test]
     fn reference() {
@@ -108,4 +273,38 @@ mod tests {
         let parsed = parse("B2:B3");
         assert!(matches!(parsed, Expression::Range(_, _)), "{:?}", parsed)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn r1c1_references() {
+        let at = CellRef::new(4, 2);
+        assert_eq!("SUM(C1:E5)+B4", parse_at("SUM(R1C3:R[0]C[2])+R[-1]C[-1]", at).to_string());
+        assert_eq!("C5+E5", parse_at("RC+RC5", at).to_string());
+        assert!(matches!(parse_at("R[-5]C", at), Expression::Literal(Value::Error(_))));
+    }
+
+    #[test]
+    fn literals() {
+        assert!(matches!(parse("12"), Expression::Literal(Value::Number(n)) if n.to_f64() == 12.0));
+        assert!(matches!(parse("\"a\"\"b\""), Expression::Literal(Value::Text(t)) if t == "a\"b"));
+        assert!(matches!(parse("#N/A"), Expression::Literal(Value::Error(_))));
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let parsed = parse("1+2*3");
+        assert!(matches!(&parsed, Expression::Binary(BinaryOp::Add, _, r) if matches!(**r, Expression::Binary(BinaryOp::Multiply, _, _))), "{:?}", parsed)
+    }
+
+    #[test]
+    fn dotted_names() {
+        let parsed = parse("ERROR.TYPE(A1)");
+        assert!(matches!(&parsed, Expression::Call(name, _) if name == "ERROR.TYPE"), "{:?}", parsed)
+    }
+
+    #[test]
+    fn prints_expressions() {
+        for formula in ["SUM(A1:B2,-C3)*(1+2)&\"a\"\"b\"", "1-(2-3)", "-(A1+1)^2", "IF(A1>=2,TRUE,#N/A)"] {
+            assert_eq!(formula, parse(formula).to_string());
+        }
+    }
+}
@@ -2,18 +2,44 @@ mod cell;
 mod eval;
 mod expression_parser;
 mod ast;
+mod arithmetic;
+mod number;
+mod random;
+mod unit;
 
 pub use cell::{*};
 pub use eval::{*};
 pub use ast::{*};
+pub use number::Number;
+pub use unit::Unit;
 
 pub trait Engine {
     fn value_at(&self, cell: &CellRef) -> Value;
     fn eval(&self, expr: &Expression) -> Value;
     fn call(&self, name: &str, arguments: &Vec<Expression>) -> Value;
+    /// Rows of values a call spills, for functions returning arrays.
+    fn array(&self, name: &str, arguments: &[Expression]) -> Option<Vec<Vec<Value>>>;
+    /// Corners of the cells a call refers to, for functions returning references.
+    fn reference(&self, name: &str, arguments: &[Expression]) -> Option<Result<(CellRef, CellRef), ErrorKind>>;
+    /// Cell whose formula is being evaluated, if any.
+    fn current(&self) -> Option<CellRef>;
+    /// Value of a cell if `input` held `value` instead of its content.
+    fn value_if(&self, cell: &CellRef, input: &CellRef, value: Value) -> Value;
+    /// A uniform random number in `[0, 1)`, drawn once per call in a cell until the sheet is redrawn.
+    fn random(&self) -> f64;
 }
 
 pub trait Operator<T: Engine> {
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
     fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value;
+    /// Rows of values a formula calling the function directly fills, right and down from
+    /// its own cell. Functions returning a single value fill nothing.
+    fn spill(&self, _arguments: &[Expression], _engine: &T) -> Option<Vec<Vec<Value>>> {
+        None
+    }
+    /// Corners of the cells a call refers to, for functions returning references such as
+    /// `OFFSET`, so functions taking ranges accept the call in place of one.
+    fn reference(&self, _arguments: &[Expression], _engine: &T) -> Option<Result<(CellRef, CellRef), ErrorKind>> {
+        None
+    }
 }
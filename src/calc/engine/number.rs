@@ -0,0 +1,185 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use crate::calc::engine::ErrorKind;
+
+/// A numeric cell value, either a float or an exact decimal in `--decimal` mode.
+/// Operations on mixed operands are carried out in decimal.
+#[derive(Debug, Copy, Clone)]
+pub enum Number {
+    Float(f64),
+    Decimal(Decimal),
+}
+
+impl Number {
+    pub fn parse(text: &str, decimal: bool) -> Option<Self> {
+        let text = text.trim();
+        if decimal {
+            Decimal::from_str(text).or_else(|_| Decimal::from_scientific(text)).ok().map(Number::Decimal)
+        } else {
+            text.parse::<f64>().ok().map(Number::Float)
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Float(f) => f,
+            Number::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Converts to decimal through the shortest text form, so `0.1` stays exactly `0.1`.
+    pub fn to_decimal(self) -> Result<Decimal, ErrorKind> {
+        match self {
+            Number::Float(f) => Decimal::from_str(&f.to_string())
+                .ok()
+                .or_else(|| Decimal::from_f64(f))
+                .ok_or(ErrorKind::Num),
+            Number::Decimal(d) => Ok(d),
+        }
+    }
+
+    pub fn to_decimal_number(self) -> Result<Number, ErrorKind> {
+        self.to_decimal().map(Number::Decimal)
+    }
+
+    pub fn add(self, other: Number) -> Result<Number, ErrorKind> {
+        self.combine(other, |a, b| a + b, Decimal::checked_add)
+    }
+
+    pub fn sub(self, other: Number) -> Result<Number, ErrorKind> {
+        self.combine(other, |a, b| a - b, Decimal::checked_sub)
+    }
+
+    pub fn mul(self, other: Number) -> Result<Number, ErrorKind> {
+        self.combine(other, |a, b| a * b, Decimal::checked_mul)
+    }
+
+    pub fn div(self, other: Number) -> Result<Number, ErrorKind> {
+        if other.is_zero() {
+            return Err(ErrorKind::Div0);
+        }
+        self.combine(other, |a, b| a / b, Decimal::checked_div)
+    }
+
+    pub fn pow(self, other: Number) -> Result<Number, ErrorKind> {
+        let exponent = other.to_f64();
+        if let (Number::Decimal(d), true) = (self, exponent.fract() == 0.0 && exponent.abs() <= 64.0) {
+            let power = (0..exponent.abs() as u32).try_fold(Decimal::ONE, |p, _| p.checked_mul(d));
+            let result = if exponent < 0.0 {
+                power.and_then(|p| Decimal::ONE.checked_div(p))
+            } else {
+                power
+            };
+            return result.map(Number::Decimal).ok_or(ErrorKind::Num);
+        }
+
+        let result = self.to_f64().powf(exponent);
+        if !result.is_finite() {
+            return Err(ErrorKind::Num);
+        }
+        match self {
+            Number::Decimal(_) => Number::Float(result).to_decimal_number(),
+            Number::Float(_) => Ok(Number::Float(result)),
+        }
+    }
+
+    pub fn neg(self) -> Number {
+        match self {
+            Number::Float(f) => Number::Float(-f),
+            Number::Decimal(d) => Number::Decimal(-d),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Float(f) => *f == 0.0,
+            Number::Decimal(d) => d.is_zero(),
+        }
+    }
+
+    pub fn sum(numbers: &[Number]) -> Result<Number, ErrorKind> {
+        numbers.iter().try_fold(Number::Float(0.0), |sum, n| sum.add(*n))
+    }
+
+    fn combine(
+        self,
+        other: Number,
+        float: impl Fn(f64, f64) -> f64,
+        decimal: impl Fn(Decimal, Decimal) -> Option<Decimal>,
+    ) -> Result<Number, ErrorKind> {
+        match (self, other) {
+            (Number::Float(a), Number::Float(b)) => {
+                let result = float(a, b);
+                if result.is_finite() { Ok(Number::Float(result)) } else { Err(ErrorKind::Num) }
+            },
+            (a, b) => decimal(a.to_decimal()?, b.to_decimal()?)
+                .map(Number::Decimal)
+                .ok_or(ErrorKind::Num),
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(f: f64) -> Self {
+        Number::Float(f)
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Float(a), Number::Float(b)) => a.partial_cmp(b),
+            (a, b) => match (a.to_decimal(), b.to_decimal()) {
+                (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+                _ => a.to_f64().partial_cmp(&b.to_f64()),
+            }
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Float(n) => n.fmt(f),
+            Number::Decimal(d) => match f.precision() {
+                Some(p) => d.round_dp_with_strategy(p as u32, RoundingStrategy::MidpointAwayFromZero).fmt(f),
+                None => d.normalize().fmt(f),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::engine::Number;
+
+    #[test]
+    fn decimal_addition_is_exact() {
+        let a = Number::parse("0.1", true).unwrap();
+        let b = Number::parse("0.2", true).unwrap();
+        assert_eq!("0.3", a.add(b).unwrap().to_string());
+    }
+
+    #[test]
+    fn mixed_operands_use_decimal() {
+        let a = Number::parse("0.1", true).unwrap();
+        assert!(matches!(a.mul(Number::from(3.0)).unwrap(), Number::Decimal(_)));
+        assert_eq!("0.3", a.mul(Number::from(3.0)).unwrap().to_string());
+    }
+
+    #[test]
+    fn formats_precision() {
+        assert_eq!("1.50", format!("{:.2}", Number::parse("1.5", true).unwrap()));
+        assert_eq!("1.50", format!("{:.2}", Number::from(1.5)));
+        assert_eq!("0.13", format!("{:.2}", Number::parse("0.125", true).unwrap()));
+    }
+}
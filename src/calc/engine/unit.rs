@@ -0,0 +1,294 @@
+use std::fmt::{Display, Formatter};
+use crate::calc::engine::{ErrorKind, Number};
+
+/// Powers of the base dimensions of a unit: information, time, length, mass and temperature.
+type Dimension = [i8; 5];
+
+const INFORMATION: Dimension = [1, 0, 0, 0, 0];
+const TIME: Dimension = [0, 1, 0, 0, 0];
+const LENGTH: Dimension = [0, 0, 1, 0, 0];
+const MASS: Dimension = [0, 0, 0, 1, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1];
+
+#[derive(PartialEq)]
+enum Prefixes {
+    None,
+    Metric,
+    /// Binary prefixes such as `Mi` and the metric ones from `k` up.
+    Binary,
+}
+
+struct Known {
+    symbols: &'static [&'static str],
+    /// Size in the base unit of its dimension: bits, seconds, metres, grams or kelvin.
+    scale: f64,
+    /// Added after scaling, for temperatures.
+    offset: f64,
+    dimension: Dimension,
+    prefixes: Prefixes,
+}
+
+const fn known(symbols: &'static [&'static str], scale: f64, dimension: Dimension, prefixes: Prefixes) -> Known {
+    Known { symbols, scale, offset: 0.0, dimension, prefixes }
+}
+
+const UNITS: [Known; 20] = [
+    known(&["B", "byte"], 8.0, INFORMATION, Prefixes::Binary),
+    known(&["bit"], 1.0, INFORMATION, Prefixes::Binary),
+    known(&["s", "sec"], 1.0, TIME, Prefixes::Metric),
+    known(&["min", "mn"], 60.0, TIME, Prefixes::None),
+    known(&["h", "hr"], 3600.0, TIME, Prefixes::None),
+    known(&["d", "day"], 86400.0, TIME, Prefixes::None),
+    known(&["wk"], 604800.0, TIME, Prefixes::None),
+    known(&["yr"], 31557600.0, TIME, Prefixes::None),
+    known(&["Hz"], 1.0, [0, -1, 0, 0, 0], Prefixes::Metric),
+    known(&["m"], 1.0, LENGTH, Prefixes::Metric),
+    known(&["in"], 0.0254, LENGTH, Prefixes::None),
+    known(&["ft"], 0.3048, LENGTH, Prefixes::None),
+    known(&["yd"], 0.9144, LENGTH, Prefixes::None),
+    known(&["mi"], 1609.344, LENGTH, Prefixes::None),
+    known(&["l", "L"], 0.001, [0, 0, 3, 0, 0], Prefixes::Metric),
+    known(&["g"], 1.0, MASS, Prefixes::Metric),
+    known(&["lbm"], 453.59237, MASS, Prefixes::None),
+    known(&["K", "kel"], 1.0, TEMPERATURE, Prefixes::None),
+    Known { symbols: &["C", "cel"], scale: 1.0, offset: 273.15, dimension: TEMPERATURE, prefixes: Prefixes::None },
+    Known { symbols: &["F", "fah"], scale: 5.0 / 9.0, offset: 459.67 * 5.0 / 9.0, dimension: TEMPERATURE, prefixes: Prefixes::None },
+];
+
+const METRIC: [(&str, f64); 12] = [
+    ("E", 1e18), ("P", 1e15), ("T", 1e12), ("G", 1e9), ("M", 1e6), ("k", 1e3),
+    ("c", 1e-2), ("m", 1e-3), ("u", 1e-6), ("µ", 1e-6), ("n", 1e-9), ("p", 1e-12),
+];
+
+const BINARY: [(&str, f64); 7] = [
+    ("Ei", 1152921504606846976.0), ("Pi", 1125899906842624.0), ("Ti", 1099511627776.0),
+    ("Gi", 1073741824.0), ("Mi", 1048576.0), ("ki", 1024.0), ("Ki", 1024.0),
+];
+
+/// A known unit, possibly prefixed, raised to a power.
+#[derive(Debug, Clone, PartialEq)]
+struct Factor {
+    symbol: String,
+    scale: f64,
+    offset: f64,
+    dimension: Dimension,
+    power: i8,
+}
+
+impl Factor {
+    fn parse(symbol: &str) -> Option<Factor> {
+        let factor = |known: &Known, scale: f64| Factor {
+            symbol: symbol.to_string(),
+            scale: known.scale * scale,
+            offset: known.offset,
+            dimension: known.dimension,
+            power: 1,
+        };
+        if let Some(known) = UNITS.iter().find(|k| k.symbols.contains(&symbol)) {
+            return Some(factor(known, 1.0));
+        }
+        UNITS.iter().find_map(|known| {
+            let prefixes: Vec<&(&str, f64)> = match known.prefixes {
+                Prefixes::None => return None,
+                Prefixes::Metric => METRIC.iter().collect(),
+                Prefixes::Binary => BINARY.iter().chain(METRIC.iter().filter(|(_, scale)| *scale >= 1e3)).collect(),
+            };
+            prefixes.into_iter().find_map(|(prefix, scale)| {
+                let base = symbol.strip_prefix(prefix)?;
+                known.symbols.contains(&base).then(|| factor(known, *scale))
+            })
+        })
+    }
+}
+
+/// A unit of measure such as `MiB`, `ms` or `MB/s`, made of known units raised to powers.
+/// A unit without factors is a plain number.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unit {
+    factors: Vec<Factor>,
+}
+
+impl Unit {
+    /// Reads units joined by `*` and `/`, each optionally raised to a power such as `m^2`.
+    pub fn parse(text: &str) -> Option<Unit> {
+        let mut factors = Vec::new();
+        let mut divide = false;
+        let mut rest = text.trim();
+        loop {
+            let end = rest.find(['*', '/']).unwrap_or(rest.len());
+            let (symbol, power) = match rest[..end].split_once('^') {
+                Some((symbol, power)) => (symbol, power.trim().parse::<i8>().ok()?),
+                None => (&rest[..end], 1),
+            };
+            let mut factor = Factor::parse(symbol.trim())?;
+            factor.power = if divide { power.checked_neg()? } else { power };
+            factors.push(factor);
+            if end == rest.len() {
+                break;
+            }
+            divide = rest[end..].starts_with('/');
+            rest = &rest[end + 1..];
+        }
+        Unit::new(factors).ok().filter(|u| !u.factors.is_empty())
+    }
+
+    /// Merges factors of the same unit and drops those raised to the power zero, or `#NUM!`
+    /// when a power no longer fits.
+    fn new(factors: Vec<Factor>) -> Result<Unit, ErrorKind> {
+        let mut merged: Vec<Factor> = Vec::new();
+        for factor in factors {
+            match merged.iter_mut().find(|f| f.symbol == factor.symbol) {
+                Some(existing) => existing.power = checked(existing.power.checked_add(factor.power))?,
+                None => merged.push(factor),
+            }
+        }
+        merged.retain(|f| f.power != 0);
+        Ok(Unit { factors: merged })
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    fn scale(&self) -> f64 {
+        self.factors.iter().map(|f| f.scale.powi(f.power as i32)).product()
+    }
+
+    /// Offset of a unit of temperature on its own; compound units are only scaled.
+    fn offset(&self) -> f64 {
+        match self.factors.as_slice() {
+            [factor] if factor.power == 1 => factor.offset,
+            _ => 0.0,
+        }
+    }
+
+    /// Powers of the base dimensions, wider than those of a single unit so they cannot overflow.
+    fn dimension(&self) -> [i32; 5] {
+        let mut dimension = [0; 5];
+        for factor in &self.factors {
+            for (total, d) in dimension.iter_mut().zip(factor.dimension) {
+                *total += d as i32 * factor.power as i32;
+            }
+        }
+        dimension
+    }
+
+    pub fn compatible(&self, other: &Unit) -> bool {
+        self.dimension() == other.dimension()
+    }
+
+    /// Converts an amount of this unit to another of the same dimension, or `#UNIT!`.
+    pub fn convert(&self, amount: Number, to: &Unit) -> Result<Number, ErrorKind> {
+        if !self.compatible(to) {
+            return Err(ErrorKind::Unit);
+        }
+        if self.offset() == 0.0 && to.offset() == 0.0 {
+            return amount.mul(Number::Float(self.scale() / to.scale()));
+        }
+        amount.mul(Number::Float(self.scale()))?
+            .add(Number::Float(self.offset() - to.offset()))?
+            .div(Number::Float(to.scale()))
+    }
+
+    /// Unit of a product of amounts of this unit and another raised to `power`, with the
+    /// number to multiply the product by. A result with the dimension of one of the units
+    /// involved is expressed in that unit, so `GiB / (MB/s)` gives seconds.
+    pub fn times(&self, other: &Unit, power: i8) -> Result<(Unit, f64), ErrorKind> {
+        let mut factors = self.factors.clone();
+        for factor in &other.factors {
+            factors.push(Factor { power: checked(factor.power.checked_mul(power))?, ..factor.clone() });
+        }
+        let product = Unit::new(factors)?;
+        let dimension = product.dimension();
+        let simpler = self.factors.iter().chain(&other.factors)
+            .find(|f| f.dimension.map(i32::from) == dimension)
+            .map(|f| Unit { factors: vec![Factor { power: 1, ..f.clone() }] })
+            .unwrap_or_else(|| match dimension {
+                [0, 0, 0, 0, 0] => Unit { factors: Vec::new() },
+                _ => product.clone(),
+            });
+        let scale = product.scale() / simpler.scale();
+        Ok((simpler, scale))
+    }
+
+    /// This unit raised to a whole power.
+    pub fn power(&self, power: i8) -> Result<Unit, ErrorKind> {
+        let factors = self.factors.iter()
+            .map(|f| Ok(Factor { power: checked(f.power.checked_mul(power))?, ..f.clone() }))
+            .collect::<Result<Vec<Factor>, ErrorKind>>()?;
+        Unit::new(factors)
+    }
+}
+
+/// A power of a unit, or `#NUM!` when it overflows. The lowest `i8` is left out so that
+/// powers can always be negated.
+fn checked(power: Option<i8>) -> Result<i8, ErrorKind> {
+    power.filter(|p| *p != i8::MIN).ok_or(ErrorKind::Num)
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (numerator, denominator): (Vec<&Factor>, Vec<&Factor>) = self.factors.iter().partition(|f| f.power > 0);
+        let power = |p: i8| if p == 1 { String::new() } else { format!("^{}", p) };
+        for (i, factor) in numerator.iter().enumerate() {
+            write!(f, "{}{}{}", if i > 0 { "*" } else { "" }, factor.symbol, power(factor.power))?;
+        }
+        for (i, factor) in denominator.iter().enumerate() {
+            match numerator.is_empty() && i == 0 {
+                true => write!(f, "{}{}", factor.symbol, power(factor.power))?,
+                false => write!(f, "/{}{}", factor.symbol, power(-factor.power))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::engine::{ErrorKind, Number, Unit};
+
+    fn convert(amount: f64, from: &str, to: &str) -> f64 {
+        Unit::parse(from).unwrap().convert(Number::Float(amount), &Unit::parse(to).unwrap()).unwrap().to_f64()
+    }
+
+    #[test]
+    fn parses_units() {
+        assert_eq!("MiB", Unit::parse("MiB").unwrap().to_string());
+        assert_eq!("MB/s", Unit::parse(" MB / s ").unwrap().to_string());
+        assert_eq!("m/s^2", Unit::parse("m/s/s").unwrap().to_string());
+        assert_eq!("Hz^-1", Unit::parse("Hz^-1").unwrap().to_string());
+        assert!(Unit::parse("apples").is_none());
+        assert!(Unit::parse("mB").is_none());
+        assert!(Unit::parse("s/s").is_none());
+    }
+
+    #[test]
+    fn converts_units() {
+        assert_eq!(1073.741824, convert(1.0, "GiB", "MB"));
+        assert_eq!(0.03, convert(30.0, "ms", "s"));
+        assert_eq!(90.0, convert(1.5, "h", "min"));
+        assert_eq!(212.0, convert(100.0, "C", "F").round());
+        assert_eq!(800.0, convert(100.0, "MB/s", "Mbit/s"));
+        assert!(Unit::parse("GB").unwrap().convert(Number::Float(1.0), &Unit::parse("s").unwrap()).is_err());
+    }
+
+    #[test]
+    fn multiplies_units() {
+        let (unit, scale) = Unit::parse("GiB").unwrap().times(&Unit::parse("MB/s").unwrap(), -1).unwrap();
+        assert_eq!(("s".to_string(), 1073.741824), (unit.to_string(), scale));
+        let (unit, scale) = Unit::parse("MB").unwrap().times(&Unit::parse("s").unwrap(), -1).unwrap();
+        assert_eq!(("MB/s".to_string(), 1.0), (unit.to_string(), scale));
+        let (unit, scale) = Unit::parse("ms").unwrap().times(&Unit::parse("s").unwrap(), -1).unwrap();
+        assert!(unit.is_plain());
+        assert_eq!(0.001, scale);
+    }
+
+    #[test]
+    fn rejects_overflowing_powers() {
+        let unit = Unit::parse("m^100").unwrap();
+        assert_eq!(Err(ErrorKind::Num), unit.times(&unit, 1).map(|(unit, _)| unit.to_string()));
+        assert_eq!(Err(ErrorKind::Num), Unit::parse("m^64").unwrap().power(2));
+        assert_eq!("m^-127", Unit::parse("m^127").unwrap().power(-1).unwrap().to_string());
+        assert!(Unit::parse("s/m^-128").is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashSet;
+use crate::calc::Locale;
+use crate::calc::engine::{CellRef, CSVEngine, Engine, Expression, Value};
+
+/// Prints how a cell is computed as a tree of every sub-expression and referenced cell with its value.
+/// Cells explained earlier in the tree are not expanded again.
+pub fn explain(engine: &CSVEngine, locale: Locale, position: CellRef) -> String {
+    let mut tree = Tree { engine, locale, text: String::new(), expanded: HashSet::new(), within: Vec::new() };
+    tree.text += &describe(engine, locale, position);
+    tree.text += "\n";
+    tree.cell_children(position, "");
+    tree.text
+}
+
+/// Lists every cell affected by a change to a cell, with its value and formula.
+pub fn dependents(engine: &CSVEngine, locale: Locale, position: CellRef) -> String {
+    engine.dependents(&position)
+        .into_iter()
+        .map(|cell| describe(engine, locale, cell) + "\n")
+        .collect()
+}
+
+fn describe(engine: &CSVEngine, locale: Locale, position: CellRef) -> String {
+    let value = value(locale, &engine.value_at(&position));
+    match engine.content(&position) {
+        Some(content) if content.starts_with('=') => format!("{} = {}   {}", position, value, content),
+        _ => format!("{} = {}", position, value),
+    }
+}
+
+fn value(locale: Locale, value: &Value) -> String {
+    match value {
+        Value::Empty => "blank".to_string(),
+        Value::Text(t) => format!("\"{}\"", t),
+        v => locale.format(v),
+    }
+}
+
+struct Tree<'a> {
+    engine: &'a CSVEngine,
+    locale: Locale,
+    text: String,
+    expanded: HashSet<CellRef>,
+    /// Cells whose formulas are being explained, innermost last.
+    within: Vec<CellRef>,
+}
+
+impl<'a> Tree<'a> {
+    fn cell_children(&mut self, position: CellRef, indent: &str) {
+        self.expanded.insert(position);
+        if let Some(formula) = self.engine.formula(&position) {
+            self.within.push(position);
+            match formula {
+                Expression::Reference(_) | Expression::Range(..) | Expression::Literal(_) => self.children(&[&formula], indent),
+                _ => self.children(&sub_expressions(&formula), indent),
+            }
+            self.within.pop();
+        }
+    }
+
+    fn children(&mut self, expressions: &[&Expression], indent: &str) {
+        for (i, expression) in expressions.iter().enumerate() {
+            let last = i + 1 == expressions.len();
+            self.text += indent;
+            self.text += if last { "└─ " } else { "├─ " };
+            let indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+            self.expression(expression, &indent);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, indent: &str) {
+        match expression {
+            Expression::Reference(r) => {
+                let seen = self.expanded.contains(r);
+                self.text += &describe(self.engine, self.locale, *r);
+                let formula = self.engine.formula(r).is_some();
+                self.text += if seen && formula { "   (see above)\n" } else { "\n" };
+                if !seen {
+                    self.cell_children(*r, indent);
+                }
+            },
+            Expression::Range(start, end) => {
+                self.text += &format!("{}:{}\n", start, end);
+                let cells = self.referenced(*start, *end);
+                self.children(&cells.iter().collect::<Vec<&Expression>>(), indent);
+            },
+            Expression::Literal(_) => self.text += &format!("{}\n", self.locale.localize_formula(&expression.to_string())),
+            Expression::Call(name, arguments) => match self.evaluate(|e| e.reference(name, arguments)) {
+                Some(Ok((start, end))) => {
+                    let area = if start == end { start.to_string() } else { format!("{}:{}", start, end) };
+                    self.text += &format!("{} = {}\n", self.locale.localize_formula(&expression.to_string()), area);
+                    let cells = self.referenced(start, end);
+                    let mut children = sub_expressions(expression);
+                    children.extend(cells.iter());
+                    self.children(&children, indent);
+                },
+                _ => self.computed(expression, indent),
+            },
+            _ => self.computed(expression, indent),
+        }
+    }
+
+    fn computed(&mut self, expression: &Expression, indent: &str) {
+        let result = self.evaluate(|e| e.eval(expression));
+        self.text += &format!("{} = {}\n", self.locale.localize_formula(&expression.to_string()), value(self.locale, &result));
+        self.children(&sub_expressions(expression), indent);
+    }
+
+    /// Evaluates as part of the formula being explained.
+    fn evaluate<R>(&self, evaluation: impl FnOnce(&CSVEngine) -> R) -> R {
+        match self.within.last() {
+            Some(cell) => self.engine.within(*cell, || evaluation(self.engine)),
+            None => evaluation(self.engine),
+        }
+    }
+
+    /// References to the cells of a range that the sheet has.
+    fn referenced(&self, start: CellRef, end: CellRef) -> Vec<Expression> {
+        self.engine.cells_within(&start, &end).into_iter().map(Expression::Reference).collect()
+    }
+}
+
+fn sub_expressions(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Call(_, args) => args.iter().collect(),
+        Expression::Negate(e) => vec![e],
+        Expression::Binary(_, l, r) => vec![l, r],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::Calc;
+    use crate::calc::engine::CellRef;
+    use crate::calc::explain::{dependents, explain};
+    use crate::calc::Locale;
+
+    #[test]
+    fn explains_cells() {
+        let engine = Calc::from_string("1,2,=A1+B1,=SUM(A1:C1)*-C1".to_string()).engine().unwrap();
+        let tree = explain(&engine, Locale::default(), CellRef::parse("D1"));
+        assert_eq!("\
+D1 = -18.00   =SUM(A1:C1)*-C1
+├─ SUM(A1:C1) = 6.00
+│  └─ A1:C1
+│     ├─ A1 = 1.00
+│     ├─ B1 = 2.00
+│     └─ C1 = 3.00   =A1+B1
+│        ├─ A1 = 1.00
+│        └─ B1 = 2.00
+└─ -C1 = -3.00
+   └─ C1 = 3.00   =A1+B1   (see above)
+", tree);
+    }
+
+    #[test]
+    fn lists_dependents() {
+        let engine = Calc::from_string("1,=A1*2,x\n=B1+1,=A1,=A2".to_string()).engine().unwrap();
+        assert_eq!("B1 = 2.00   =A1*2\nA2 = 3.00   =B1+1\nB2 = 1.00   =A1\nC2 = 3.00   =A2\n", dependents(&engine, Locale::default(), CellRef::parse("A1")));
+        assert_eq!("", dependents(&engine, Locale::default(), CellRef::parse("C1")));
+    }
+
+    #[test]
+    fn follows_computed_references() {
+        let engine = Calc::from_string("2,10,20\n\"=INDIRECT(\"\"C\"\"&A1-1)\",\"=SUM(OFFSET(A1,0,1,1,A1))\",=ROW()".to_string()).engine().unwrap();
+        assert_eq!("A2 = 20.00   =INDIRECT(\"C\"&A1-1)\nB2 = 30.00   =SUM(OFFSET(A1,0,1,1,A1))\n", dependents(&engine, Locale::default(), CellRef::parse("C1")));
+        assert_eq!("\
+B2 = 30.00   =SUM(OFFSET(A1,0,1,1,A1))
+└─ OFFSET(A1,0,1,1,A1) = B1:C1
+   ├─ A1 = 2.00
+   ├─ 0
+   ├─ 1
+   ├─ 1
+   ├─ A1 = 2.00
+   ├─ B1 = 10.00
+   └─ C1 = 20.00
+", explain(&engine, Locale::default(), CellRef::parse("B2")));
+        assert_eq!("C2 = 2.00   =ROW()\n", explain(&engine, Locale::default(), CellRef::parse("C2")));
+    }
+}
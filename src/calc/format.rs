@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::error::Error;
+use crate::calc::engine::{Cell, CellRef, CSVEngine, shift_references, Value};
+
+/// Classes and inline styles attached to a cell by conditional formatting rules,
+/// and a tooltip explaining why the cell is flagged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellFormat {
+    pub classes: Vec<String>,
+    pub styles: Vec<String>,
+    pub title: Option<String>,
+}
+
+enum Style {
+    ColorScale(Vec<(u8, u8, u8)>),
+    DataBar(String),
+    Highlight(String, String),
+    Color(String, String),
+    Class(String, String),
+}
+
+struct Rule {
+    columns: Vec<usize>,
+    style: Style,
+}
+
+/// Conditional formatting rules, one per line:
+///
+/// ```text
+/// B color-scale #f8696b #63be7b
+/// C:D data-bar #638ec6
+/// D highlight #ffc7ce =D2>100
+/// E class over-budget =E2>F2
+/// ```
+///
+/// Conditions are formulas written for the first data row; their references move down with
+/// each row unless anchored with `$`, as in Excel. R1C1 references such as `RC[-1]` are
+/// relative to the formatted cell.
+pub struct FormatRules {
+    rules: Vec<Rule>,
+}
+
+impl FormatRules {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = parse_rule(line).map_err(|e| format!("rules line {}: {}", number + 1, e))?;
+            rules.push(rule);
+        }
+        Ok(FormatRules { rules })
+    }
+
+    /// Evaluates the rules against every cell below the header row.
+    pub fn apply(&self, engine: &CSVEngine, cells: &[Cell]) -> HashMap<CellRef, CellFormat> {
+        let mut formats: HashMap<CellRef, CellFormat> = HashMap::new();
+        for rule in &self.rules {
+            for column in &rule.columns {
+                let column_cells: Vec<&Cell> = cells.iter()
+                    .filter(|c| c.position.column() == *column && c.position.row() > 0)
+                    .collect();
+                let numbers: Vec<f64> = column_cells.iter()
+                    .filter_map(|c| match &c.value {
+                        Some(Value::Number(n)) => Some(n.to_f64()),
+                        _ => None,
+                    })
+                    .collect();
+                let min = numbers.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = numbers.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+                for cell in column_cells {
+                    let format = formats.entry(cell.position).or_default();
+                    let position = match &cell.value {
+                        Some(Value::Number(n)) if max > min => Some((n.to_f64() - min) / (max - min)),
+                        Some(Value::Number(_)) => Some(1.0),
+                        _ => None,
+                    };
+
+                    match (&rule.style, position) {
+                        (Style::ColorScale(colors), Some(position)) => {
+                            format.styles.push(format!("background-color: {}", scale(colors, position)));
+                        },
+                        (Style::DataBar(color), Some(position)) => {
+                            let percent = (position * 100.0).round();
+                            format.styles.push(format!(
+                                "background: linear-gradient(90deg, {} {}%, transparent {}%)", color, percent, percent
+                            ));
+                        },
+                        (Style::Highlight(color, condition), _) if holds(engine, condition, cell.position) => {
+                            format.styles.push(format!("background-color: {}", color));
+                        },
+                        (Style::Color(color, condition), _) if holds(engine, condition, cell.position) => {
+                            format.styles.push(format!("color: {}", color));
+                        },
+                        (Style::Class(class, condition), _) if holds(engine, condition, cell.position) => {
+                            format.classes.push(class.clone());
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+        formats.retain(|_, f| f != &CellFormat::default());
+        formats
+    }
+}
+
+fn holds(engine: &CSVEngine, condition: &str, at: CellRef) -> bool {
+    let condition = shift_references(condition, at.row() as isize - 1, 0);
+    matches!(engine.evaluate_at(&condition, at), Value::Bool(true))
+}
+
+fn parse_rule(line: &str) -> Result<Rule, String> {
+    let (line, condition) = match line.find('=') {
+        Some(i) => (&line[..i], Some(line[i + 1..].trim().to_string())),
+        None => (line, None),
+    };
+
+    let mut parts = line.split_whitespace();
+    let columns = parse_columns(parts.next().ok_or("missing column")?)?;
+    let kind = parts.next().ok_or("missing rule")?;
+    let args: Vec<&str> = parts.collect();
+
+    let with_condition = |arg: Option<&&str>| -> Result<(String, String), String> {
+        let arg = arg.ok_or(format!("{} needs a value", kind))?;
+        let condition = condition.clone().ok_or(format!("{} needs a =condition", kind))?;
+        Ok((arg.to_string(), condition))
+    };
+
+    let style = match kind {
+        "color-scale" => {
+            let colors = args.iter().map(|c| parse_hex(c)).collect::<Result<Vec<_>, _>>()?;
+            if colors.len() < 2 {
+                return Err("color-scale needs at least two colors".to_string());
+            }
+            Style::ColorScale(colors)
+        },
+        "data-bar" => Style::DataBar(args.first().unwrap_or(&"#638ec6").to_string()),
+        "highlight" => {
+            let (color, condition) = with_condition(args.first())?;
+            Style::Highlight(color, condition)
+        },
+        "color" => {
+            let (color, condition) = with_condition(args.first())?;
+            Style::Color(color, condition)
+        },
+        "class" => {
+            let (class, condition) = with_condition(args.first())?;
+            Style::Class(class, condition)
+        },
+        k => return Err(format!("unknown rule {}", k)),
+    };
+
+    Ok(Rule { columns, style })
+}
+
+pub fn parse_columns(text: &str) -> Result<Vec<usize>, String> {
+    let mut columns = Vec::new();
+    for part in text.split(',') {
+        match part.split_once(':') {
+            Some((start, end)) => columns.extend(parse_column(start)?..=parse_column(end)?),
+            None => columns.push(parse_column(part)?),
+        }
+    }
+    Ok(columns)
+}
+
+fn parse_column(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!("invalid column {}", text));
+    }
+    Ok(text.bytes().fold(0, |column, b| column * 26 + (b - b'A') as usize + 1) - 1)
+}
+
+fn parse_hex(color: &str) -> Result<(u8, u8, u8), String> {
+    let hex = color.trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16);
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Ok(r), Ok(g), Ok(b)) => Ok((r, g, b)),
+        _ => Err(format!("expected a #rrggbb color, found {}", color)),
+    }
+}
+
+fn scale(colors: &[(u8, u8, u8)], position: f64) -> String {
+    let segments = (colors.len() - 1) as f64;
+    let index = ((position * segments).floor() as usize).min(colors.len() - 2);
+    let t = position * segments - index as f64;
+    let (a, b) = (colors[index], colors[index + 1]);
+    let mix = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64) * t).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Read};
+    use crate::calc::engine::{Cell, CellRef, CSVEngine};
+    use crate::calc::format::{FormatRules, parse_columns};
+    use crate::calc::reader::CSVReader;
+
+    #[test]
+    fn applies_rules() {
+        let data = "Item,Price,Budget\na,1,2\nb,3,2\nc,5,2";
+        let mut engine = CSVEngine::new(CSVReader::new(BufReader::new(data.as_bytes()).bytes(), b','));
+        let cells: Vec<Cell> = engine.by_ref().collect();
+        let rules = FormatRules::parse("# budget\nB color-scale #000000 #ffffff\nB class over =B2>$C$2").unwrap();
+        let formats = rules.apply(&engine, &cells);
+
+        assert_eq!(vec!["background-color: #000000"], formats[&CellRef::new(1, 1)].styles);
+        assert_eq!(vec!["background-color: #808080"], formats[&CellRef::new(2, 1)].styles);
+        assert!(formats[&CellRef::new(1, 1)].classes.is_empty());
+        assert_eq!(vec!["over"], formats[&CellRef::new(3, 1)].classes);
+        assert!(!formats.contains_key(&CellRef::new(0, 1)));
+    }
+
+    #[test]
+    fn reads_r1c1_conditions_from_the_formatted_cell() {
+        let data = "Item,Size,Flag\nx,5,-\ny,50,-\nz,20,-";
+        let mut engine = CSVEngine::new(CSVReader::new(BufReader::new(data.as_bytes()).bytes(), b','));
+        let cells: Vec<Cell> = engine.by_ref().collect();
+        let formats = FormatRules::parse("C class big =R[0]C2>10").unwrap().apply(&engine, &cells);
+
+        assert!(!formats.contains_key(&CellRef::new(1, 2)));
+        assert_eq!(vec!["big"], formats[&CellRef::new(2, 2)].classes);
+        assert_eq!(vec!["big"], formats[&CellRef::new(3, 2)].classes);
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        assert!(FormatRules::parse("B sparkle").is_err());
+        assert!(FormatRules::parse("B highlight red").is_err());
+    }
+
+    #[test]
+    fn parses_columns() {
+        assert_eq!(vec![1, 3, 4, 5, 26], parse_columns("B,D:F,AA").unwrap());
+    }
+}
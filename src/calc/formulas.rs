@@ -0,0 +1,73 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::calc::CellFormat;
+use crate::calc::engine::{Cell, CellRef, Delimiter, move_references, Value};
+
+/// What formula cells show once a sheet is computed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FormulaMode {
+    /// The computed value.
+    #[default]
+    Values,
+    /// The formula instead of its value.
+    Formulas,
+    /// The value, followed by a column holding the formula.
+    Annotated,
+}
+
+/// Replaces the content of formula cells with their formula.
+pub fn show_formulas(cells: Vec<Cell>) -> Vec<Cell> {
+    cells.into_iter()
+        .map(|mut cell| {
+            if let Some(formula) = &cell.formula {
+                cell.content = formula.clone();
+                cell.value = Some(Value::Text(formula.clone()));
+            }
+            cell
+        })
+        .collect()
+}
+
+/// Inserts a column after every column holding formulas, with the formula of each cell
+/// and a header naming the column it annotates.
+pub fn annotate(cells: Vec<Cell>, formats: HashMap<CellRef, CellFormat>) -> (Vec<Cell>, HashMap<CellRef, CellFormat>) {
+    let annotated: BTreeSet<usize> = cells.iter()
+        .filter(|c| c.formula.is_some())
+        .map(|c| c.position.column())
+        .collect();
+    let column = |c: usize| c + annotated.range(..c).count();
+    let moved = |p: &CellRef| CellRef::new(p.row(), column(p.column()));
+
+    let mut result = Vec::with_capacity(cells.len() + annotated.len());
+    for mut cell in cells {
+        let original = cell.position;
+        cell.position = moved(&original);
+        if !annotated.contains(&original.column()) {
+            result.push(cell);
+            continue;
+        }
+
+        let content = match (original.row(), &cell.formula) {
+            (0, _) if cell.content.trim().is_empty() => format!("{} formula", CellRef::column_name(original.column())),
+            (0, _) => format!("{} formula", cell.content),
+            (_, Some(formula)) => formula.clone(),
+            (_, None) => String::new(),
+        };
+        let annotation = Cell {
+            position: CellRef::new(original.row(), cell.position.column() + 1),
+            delimiter: cell.delimiter.clone(),
+            value: Some(if content.is_empty() { Value::Empty } else { Value::Text(content.clone()) }),
+            content,
+            formula: None,
+            references: Vec::new(),
+        };
+        // The formula reads the cells where they moved, for spreadsheet outputs.
+        cell.formula = cell.formula.take()
+            .and_then(|formula| move_references(&formula, original, |p| Some(moved(&p))));
+        cell.delimiter = Delimiter::Comma;
+        result.push(cell);
+        result.push(annotation);
+    }
+
+    let formats = formats.into_iter().map(|(p, f)| (moved(&p), f)).collect();
+    (result, formats)
+}
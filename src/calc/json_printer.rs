@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::error::Error;
+use crate::calc::Table;
+use crate::calc::engine::{Cell, CellRef, Value};
+use crate::framework::Printer;
+
+/// Prints a table as a JSON array of row objects keyed by the header row.
+pub struct JsonPrinter;
+
+impl JsonPrinter {
+    pub fn new() -> Self {
+        JsonPrinter
+    }
+}
+
+impl Printer<Table> for JsonPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let objects = objects(&table)?;
+        if objects.is_empty() {
+            return Ok(b"[]\n".to_vec());
+        }
+        Ok(format!("[\n  {}\n]\n", objects.join(",\n  ")).into_bytes())
+    }
+
+    fn extension() -> &'static str {
+        "json"
+    }
+}
+
+/// Prints a table as one JSON row object per line.
+pub struct NdjsonPrinter;
+
+impl NdjsonPrinter {
+    pub fn new() -> Self {
+        NdjsonPrinter
+    }
+}
+
+impl Printer<Table> for NdjsonPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut output = String::new();
+        for object in objects(&table)? {
+            output += &object;
+            output += "\n";
+        }
+        Ok(output.into_bytes())
+    }
+
+    fn extension() -> &'static str {
+        "ndjson"
+    }
+}
+
+fn objects(table: &Table) -> Result<Vec<String>, Box<dyn Error>> {
+    let rows = table.rows();
+    let mut rows = rows.iter();
+    let keys = match rows.next() {
+        Some(header) => keys(header),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut objects = Vec::new();
+    for row in rows {
+        let mut fields = Vec::new();
+        for (column, key) in keys.iter().enumerate() {
+            let cell = row.iter().find(|c| c.position.column() == column);
+            let value = cell.and_then(|c| c.value.as_ref()).unwrap_or(&Value::Empty);
+            fields.push(format!("{}:{}", serde_json::to_string(key)?, json(value)?));
+        }
+        objects.push(format!("{{{}}}", fields.join(",")));
+    }
+    Ok(objects)
+}
+
+/// Header names, falling back to the column name when blank or repeated.
+fn keys(header: &[&Cell]) -> Vec<String> {
+    let columns = header.iter().map(|c| c.position.column() + 1).max().unwrap_or(0);
+    let mut seen = HashSet::new();
+    (0..columns)
+        .map(|column| {
+            let name = header.iter()
+                .find(|c| c.position.column() == column)
+                .map(|c| c.content.trim().to_string())
+                .unwrap_or_default();
+            if name.is_empty() || !seen.insert(name.clone()) {
+                CellRef::column_name(column)
+            } else {
+                name
+            }
+        })
+        .collect()
+}
+
+fn json(value: &Value) -> Result<String, Box<dyn Error>> {
+    Ok(match value {
+        Value::Empty => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Text(t) => serde_json::to_string(t)?,
+        Value::Quantity(n, unit) => format!("{{\"value\":{},\"unit\":{}}}", n, serde_json::to_string(&unit.to_string())?),
+        Value::Error(e) => format!("{{\"error\":{}}}", serde_json::to_string(&e.to_string())?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{Calc, JsonPrinter, NdjsonPrinter};
+    use crate::framework::{Loader, Printer};
+
+    #[test]
+    fn prints_typed_rows() {
+        let table = Calc::from_string("Item,Price,Paid\n\"a \"\"b\"\"\",1.5,TRUE\nc,=1/0,".to_string()).load().unwrap();
+        let output = String::from_utf8(JsonPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!(
+            "[\n  {\"Item\":\"a \\\"b\\\"\",\"Price\":1.5,\"Paid\":true},\n  {\"Item\":\"c\",\"Price\":{\"error\":\"#DIV/0!\"},\"Paid\":null}\n]\n",
+            output
+        );
+    }
+
+    #[test]
+    fn prints_lines() {
+        let table = Calc::from_string("a,,a\n1,2,3\n4,5,6".to_string()).load().unwrap();
+        let output = String::from_utf8(NdjsonPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!("{\"a\":1,\"B\":2,\"C\":3}\n{\"a\":4,\"B\":5,\"C\":6}\n", output);
+    }
+}
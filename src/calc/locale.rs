@@ -0,0 +1,174 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::calc::engine::{Number, Value};
+
+/// Number and list conventions used to read and write a sheet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Locale {
+    name: &'static str,
+    /// Separates the integer and fractional digits of a number.
+    pub decimal: char,
+    /// Separates groups of thousands when reading numbers.
+    pub grouping: char,
+    /// Separates CSV fields and formula arguments.
+    pub separator: char,
+}
+
+const LOCALES: [Locale; 8] = [
+    Locale { name: "en", decimal: '.', grouping: ',', separator: ',' },
+    Locale { name: "de", decimal: ',', grouping: '.', separator: ';' },
+    Locale { name: "fr", decimal: ',', grouping: ' ', separator: ';' },
+    Locale { name: "es", decimal: ',', grouping: '.', separator: ';' },
+    Locale { name: "it", decimal: ',', grouping: '.', separator: ';' },
+    Locale { name: "nl", decimal: ',', grouping: '.', separator: ';' },
+    Locale { name: "pt", decimal: ',', grouping: '.', separator: ';' },
+    Locale { name: "ch", decimal: '.', grouping: '\'', separator: ';' },
+];
+
+impl Default for Locale {
+    fn default() -> Self {
+        LOCALES[0]
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.to_lowercase();
+        let name = match name.as_str() {
+            "de-ch" | "de_ch" | "fr-ch" | "fr_ch" | "it-ch" | "it_ch" => "ch",
+            n => n.split(['-', '_']).next().unwrap_or(n),
+        };
+        LOCALES.into_iter()
+            .find(|l| l.name == name)
+            .ok_or_else(|| format!("unknown locale {}, expected one of {}", name, LOCALES.map(|l| l.name).join(", ")))
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Locale {
+    /// Reads a number such as `1.234,56` or `12,5 %`, percentages becoming fractions.
+    pub fn parse_number(&self, text: &str, decimal: bool) -> Option<Number> {
+        let mut text = text.trim();
+        let percent = text.ends_with('%');
+        if percent {
+            text = text[..text.len() - 1].trim_end();
+        }
+        if !text.chars().any(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let (integer, fraction) = match text.split_once(self.decimal) {
+            Some((i, f)) => (i, Some(f)),
+            None => (text, None),
+        };
+
+        let mut canonical = self.ungroup(integer)?;
+        if let Some(fraction) = fraction {
+            canonical.push('.');
+            canonical.push_str(fraction);
+        }
+
+        let number = Number::parse(&canonical, decimal)?;
+        if percent {
+            number.div(Number::Float(100.0)).ok()
+        } else {
+            Some(number)
+        }
+    }
+
+    /// Removes grouping separators, which must split the digits into groups of three.
+    fn ungroup(&self, integer: &str) -> Option<String> {
+        let is_grouping = |c: char| c == self.grouping || (self.grouping == ' ' && c.is_whitespace());
+        if !integer.contains(is_grouping) {
+            return Some(integer.to_string());
+        }
+
+        let mut groups = integer.split(is_grouping);
+        let first = groups.next()?;
+        let digits = first.trim_start_matches(['-', '+']);
+        if digits.is_empty() || digits.len() > 3 {
+            return None;
+        }
+
+        let mut canonical = first.to_string();
+        for group in groups {
+            if group.len() != 3 || !group.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            canonical.push_str(group);
+        }
+        Some(canonical)
+    }
+
+    /// Rewrites a formula written in this locale to the `,` argument and `.` decimal syntax.
+    pub fn canonical_formula(&self, formula: &str) -> String {
+        let mut canonical = String::with_capacity(formula.len());
+        let mut quoted = false;
+        for c in formula.chars() {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            canonical.push(match c {
+                c if quoted => c,
+                c if c == self.separator => ',',
+                c if c == self.decimal => '.',
+                c => c,
+            });
+        }
+        canonical
+    }
+
+    /// Rewrites a formula from the `,` argument and `.` decimal syntax to this locale.
+    pub fn localize_formula(&self, formula: &str) -> String {
+        let mut localized = String::with_capacity(formula.len());
+        let mut quoted = false;
+        let mut previous = ' ';
+        for c in formula.chars() {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            localized.push(match c {
+                c if quoted => c,
+                ',' => self.separator,
+                '.' if previous.is_ascii_digit() => self.decimal,
+                c => c,
+            });
+            previous = c;
+        }
+        localized
+    }
+
+    pub fn format(&self, value: &Value) -> String {
+        match value {
+            Value::Number(_) | Value::Quantity(..) if self.decimal != '.' => value.to_string().replace('.', &self.decimal.to_string()),
+            _ => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::locale::Locale;
+
+    #[test]
+    fn parses_grouped_numbers() {
+        let de: Locale = "de-DE".parse().unwrap();
+        assert_eq!(1234.56, de.parse_number("1.234,56", false).unwrap().to_f64());
+        assert_eq!(0.125, de.parse_number("12,5 %", false).unwrap().to_f64());
+        assert!(de.parse_number("1.23", false).is_none());
+    }
+
+    #[test]
+    fn rewrites_formulas() {
+        let fr: Locale = "fr".parse().unwrap();
+        assert_eq!("SUM(A1,2.5,\"a;b\")", fr.canonical_formula("SUM(A1;2,5;\"a;b\")"));
+        assert_eq!("SUM(A1;2,5;\"a,b\")", fr.localize_formula("SUM(A1,2.5,\"a,b\")"));
+    }
+}
@@ -1,18 +1,57 @@
 mod operators;
-mod engine;
+pub mod engine;
 mod reader;
+mod plugins;
+mod locale;
+mod format;
+mod json_printer;
+mod terminal_printer;
+mod xlsx;
+mod formulas;
+mod repl;
+mod explain;
+mod dot_printer;
+mod scenario;
+mod assertions;
+mod validation;
+mod view;
+mod pivot;
+mod sql;
 
+use std::cell::OnceCell;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::Write;
 use std::io::{BufReader, Read};
+use std::fs;
+use std::path::Path;
 use std::slice::Iter;
 use std::string::String;
 
 use operators::{*};
-use engine::CSVEngine;
+use engine::{CSVEngine, Operator};
 use reader::CSVReader;
-use crate::calc::engine::Cell;
+use plugins::load_plugins;
+use xlsx::read_xlsx;
+pub use locale::Locale;
+pub use format::CellFormat;
+pub use json_printer::{JsonPrinter, NdjsonPrinter};
+pub use terminal_printer::TerminalPrinter;
+pub use xlsx::XlsxPrinter;
+pub use formulas::FormulaMode;
+pub use repl::run_repl;
+pub use dot_printer::DotPrinter;
+pub use sql::Query;
+pub use plugins::default_plugins;
+pub use assertions::TestReport;
+pub use validation::ValidationReport;
+use scenario::{parse_assignment, parse_cells, seek, simulate, Sweep};
+use format::FormatRules;
+use validation::{ValidationRules, Violation};
+use view::View;
+use pivot::Pivot;
+use crate::calc::engine::{Cell, CellRef, ErrorKind, Value};
 use crate::framework::{Loader, Printer};
 
 pub enum Source {
@@ -20,47 +59,424 @@ pub enum Source {
     FromString(String)
 }
 
+impl Default for Source {
+    fn default() -> Self {
+        Source::FromString(String::new())
+    }
+}
+
+#[derive(Default)]
 pub struct Calc {
     source: Source,
+    plugins: Vec<String>,
+    decimal: bool,
+    locale: Locale,
+    rules: Option<String>,
+    sheet: Option<String>,
+    formulas: FormulaMode,
+    overrides: Vec<String>,
+    sweep: Option<String>,
+    simulate: Option<usize>,
+    collect: Vec<String>,
+    goal: Option<(String, String)>,
+    seed: Option<u64>,
+    validation: Option<String>,
+    /// Rules read from `validation`, once they are first needed.
+    schema: OnceCell<ValidationRules>,
+    view: View,
+    pivot: Option<String>,
+    graph: bool,
 }
 
 impl Calc {
     pub fn from_file(path: String) -> Self {
-        Calc{ source: Source::FromFile(path) }
+        Calc{ source: Source::FromFile(path), ..Default::default() }
     }
 
     pub fn from_string(source: String) -> Self {
-        Calc{ source: Source::FromString(source) }
+        Calc{ source: Source::FromString(source), ..Default::default() }
+    }
+
+    pub fn with_plugins(mut self, plugins: Vec<String>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn with_decimal(mut self, decimal: bool) -> Self {
+        self.decimal = decimal;
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Reads conditional formatting rules from a file.
+    pub fn with_rules(mut self, rules: Option<String>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Worksheet to read from an XLSX workbook instead of the first one.
+    pub fn with_sheet(mut self, sheet: Option<String>) -> Self {
+        self.sheet = sheet;
+        self
+    }
+
+    pub fn with_formulas(mut self, formulas: FormulaMode) -> Self {
+        self.formulas = formulas;
+        self
+    }
+
+    /// Replaces the content of cells before computing, such as `B2=0.07`, leaving the file untouched.
+    pub fn with_overrides(mut self, overrides: Vec<String>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Recomputes the sheet for every value of an input, such as `B2=0.05..0.10:0.01`,
+    /// and outputs the collected cells instead of the sheet.
+    pub fn with_sweep(mut self, sweep: Option<String>) -> Self {
+        self.sweep = sweep;
+        self
+    }
+
+    /// Recomputes the sheet a number of times with new random draws, and outputs
+    /// statistics of the collected cells instead of the sheet.
+    pub fn with_simulation(mut self, iterations: Option<usize>) -> Self {
+        self.simulate = iterations;
+        self
+    }
+
+    /// Cells output by a sweep or simulation.
+    pub fn with_collect(mut self, collect: Vec<String>) -> Self {
+        self.collect = collect;
+        self
+    }
+
+    /// Seeds random functions so every run draws the same numbers.
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Changes a cell until a target cell reaches a value, such as `F20=0`, before computing.
+    pub fn with_goal_seek(mut self, goal: Option<String>, by_changing: Option<String>) -> Self {
+        self.goal = goal.zip(by_changing);
+        self
+    }
+
+    /// Reads column validation rules from a file; cells breaking them evaluate to `#INVALID!`.
+    pub fn with_validation(mut self, validation: Option<String>) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Records the areas every formula reads, for the dependency graph.
+    pub fn with_graph(mut self, graph: bool) -> Self {
+        self.graph = graph;
+        self
+    }
+
+    /// Summarizes the computed sheet, such as `rows=B,cols=C,values=sum(D)`, and outputs the
+    /// summary instead of the sheet.
+    pub fn with_pivot(mut self, pivot: Option<String>) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Sorts rows below the header by columns, such as `C desc,A`.
+    pub fn with_sort_by(mut self, sort_by: Option<String>) -> Self {
+        self.view.sort_by = sort_by;
+        self
+    }
+
+    /// Keeps the rows meeting a condition, such as `C>100`.
+    pub fn with_filter(mut self, filter: Option<String>) -> Self {
+        self.view.filter = filter;
+        self
+    }
+
+    /// Keeps some columns, in order, such as `A,C,F`.
+    pub fn with_columns(mut self, columns: Option<String>) -> Self {
+        self.view.columns = columns;
+        self
+    }
+
+    /// Keeps at most a number of rows below the header.
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.view.limit = limit;
+        self
     }
 }
 
 pub struct Table {
-    cells: Vec<Cell>
+    cells: Vec<Cell>,
+    locale: Locale,
+    formats: HashMap<CellRef, CellFormat>,
 }
 
 impl Table {
     pub fn cells(&self) -> Iter<'_, Cell> {
         self.cells.iter()
     }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Conditional formatting of a cell, if any rule applies to it.
+    pub fn format(&self, position: &CellRef) -> Option<&CellFormat> {
+        self.formats.get(position)
+    }
+
+    pub fn rows(&self) -> Vec<Vec<&Cell>> {
+        let mut rows: Vec<Vec<&Cell>> = Vec::new();
+        for cell in &self.cells {
+            match rows.last_mut() {
+                Some(row) if row[0].position.row() == cell.position.row() => row.push(cell),
+                _ => rows.push(vec![cell]),
+            }
+        }
+        rows
+    }
+
+    /// Whether each column holds only numbers below the header row.
+    pub fn numeric_columns(&self) -> Vec<bool> {
+        let rows = self.rows();
+        let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        (0..columns)
+            .map(|c| {
+                let mut values = rows.iter().skip(1).filter_map(|r| r.get(c)).filter_map(|c| c.value.as_ref());
+                let mut any = false;
+                let all = values.all(|v| match v {
+                    Value::Empty => true,
+                    Value::Number(_) | Value::Quantity(..) => { any = true; true },
+                    _ => false,
+                });
+                all && any
+            })
+            .collect()
+    }
 }
 
-impl Loader for Calc {
-    type Result = Table;
+impl Calc {
+    /// Reads the sheet into an engine with every function and plugin registered, with the
+    /// overrides, validation and goal seek applied.
+    pub fn engine(&self) -> Result<CSVEngine, Box<dyn Error>> {
+        let mut engine = self.sheet()?;
+        for assignment in &self.overrides {
+            let (position, content) = parse_assignment(assignment)?;
+            engine.set_content(position, content);
+        }
 
-    fn load(&self) -> Result<Table, Box<dyn Error>> {
+        self.mark_invalid(&mut engine)?;
+
+        if let Some((goal, input)) = &self.goal {
+            seek(&mut engine, goal, input, self.locale)?;
+        }
+
+        engine.spill();
+        Ok(engine)
+    }
+
+    /// Reads the sheet as written into an engine with every function and plugin registered.
+    fn sheet(&self) -> Result<CSVEngine, Box<dyn Error>> {
+        let separator = self.locale.separator as u8;
         let mut engine =  match &self.source {
-            Source::FromFile(path) => CSVEngine::new(CSVReader::new(BufReader::new(File::open(path)?).bytes())),
-            Source::FromString(data) => CSVEngine::new(CSVReader::new(BufReader::new(data.as_bytes()).bytes())),
+            Source::FromFile(path) if path.to_lowercase().ends_with(".xlsx") => {
+                CSVEngine::new(read_xlsx(File::open(path)?, self.sheet.as_deref(), self.locale)?.into_iter())
+            },
+            Source::FromFile(path) => CSVEngine::new(CSVReader::new(BufReader::new(File::open(path)?).bytes(), separator)),
+            Source::FromString(data) => CSVEngine::new(CSVReader::new(BufReader::new(data.as_bytes()).bytes(), separator)),
         };
 
+        engine.use_decimal(self.decimal);
+        engine.use_locale(self.locale);
+        engine.use_seed(self.seed);
+        self.register(&mut engine)?;
+        Ok(engine)
+    }
+
+    /// Text of a sheet read as CSV, or `None` for a workbook.
+    fn csv(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(match &self.source {
+            Source::FromFile(path) if path.to_lowercase().ends_with(".xlsx") => None,
+            Source::FromFile(path) => Some(fs::read_to_string(path)?),
+            Source::FromString(data) => Some(data.clone()),
+        })
+    }
+
+    /// Makes the cells breaking the schema `#INVALID!`.
+    fn mark_invalid(&self, engine: &mut CSVEngine) -> Result<(), Box<dyn Error>> {
+        for violation in self.violations(engine)? {
+            engine.assume(violation.cell, Value::Error(ErrorKind::Invalid));
+        }
+        Ok(())
+    }
+
+    /// An engine holding the computed values of a table, to evaluate conditions against.
+    fn evaluator(&self, table: &Table) -> Result<CSVEngine, Box<dyn Error>> {
+        let mut engine = CSVEngine::new(table.cells().cloned());
+        for cell in table.cells() {
+            if let Some(value) = &cell.value {
+                engine.assume(cell.position, value.clone());
+            }
+        }
+        engine.use_locale(self.locale);
+        self.register(&mut engine)?;
+        Ok(engine)
+    }
+
+    /// Registers every function and plugin with an engine.
+    fn register(&self, engine: &mut CSVEngine) -> Result<(), Box<dyn Error>> {
         engine.register_operator(Sum{});
         engine.register_operator(Count{});
+        engine.register_operator(CountBlank{});
         engine.register_operator(Average{});
+        engine.register_operator(IfError{});
+        engine.register_operator(IfNa{});
+        engine.register_operator(Na{});
+        engine.register_operator(ErrorType{});
+        engine.register_operator(IsError{});
+        engine.register_operator(IsNa{});
+        engine.register_operator(IsNumber{});
+        engine.register_operator(IsText{});
+        engine.register_operator(IsBlank{});
+        engine.register_operator(IsLogical{});
+        engine.register_operator(Assert{});
+        engine.register_operator(GoalSeek{});
+        engine.register_operator(Rand{});
+        engine.register_operator(RandBetween{});
+        engine.register_operator(RandArray{});
+        engine.register_operator(NormInv{});
+        engine.register_operator(NormSInv{});
+        engine.register_operator(LognormInv{});
+        engine.register_operator(Uniform{});
+        engine.register_operator(Triangular{});
+        engine.register_operator(Lognormal{});
+        engine.register_operator(GroupBy{});
+        engine.register_operator(PivotBy{});
+        engine.register_operator(MMult{});
+        engine.register_operator(MInverse{});
+        engine.register_operator(MDeterm{});
+        engine.register_operator(Transpose{});
+        engine.register_operator(Slope{});
+        engine.register_operator(Intercept{});
+        engine.register_operator(Rsq{});
+        engine.register_operator(Correl{});
+        engine.register_operator(CovarianceS{});
+        engine.register_operator(Linest{});
+        engine.register_operator(Trend{});
+        engine.register_operator(ForecastLinear{});
+        engine.register_operator(Growth{});
+        engine.register_operator(Indirect{});
+        engine.register_operator(Offset{});
+        engine.register_operator(Row{});
+        engine.register_operator(Column{});
+        engine.register_operator(Rows{});
+        engine.register_operator(Columns{});
+        engine.register_operator(Address{});
+        engine.register_operator(Convert{});
+
+        for plugin in &self.plugins {
+            for function in load_plugins(Path::new(plugin))? {
+                let name = Operator::<CSVEngine>::name(&function).to_string();
+                if engine.has_operator(&name) {
+                    return Err(format!("plugin {}: function {} is already defined", plugin, name).into());
+                }
+                engine.register_operator(function);
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the sheet of an engine, applying the formatting rules and formula mode.
+    pub fn table(&self, engine: &CSVEngine) -> Result<Table, Box<dyn Error>> {
+        let mut cells = engine.computed();
+        if self.graph {
+            for cell in cells.iter_mut().filter(|c| c.formula.is_some()) {
+                cell.references = engine.references(&cell.position);
+            }
+        }
+        let mut formats = match &self.rules {
+            Some(path) => FormatRules::parse(&fs::read_to_string(path)?)?.apply(engine, &cells),
+            None => HashMap::new(),
+        };
+        for violation in self.violations(engine)? {
+            let format = formats.entry(violation.cell).or_default();
+            format.classes.push("invalid".to_string());
+            format.title = Some(violation.message);
+        }
+        let (cells, formats) = match self.formulas {
+            FormulaMode::Values => (cells, formats),
+            FormulaMode::Formulas => (formulas::show_formulas(cells), formats),
+            FormulaMode::Annotated => formulas::annotate(cells, formats),
+        };
 
         Ok(Table {
-            cells: engine.collect()
+            cells,
+            locale: self.locale,
+            formats,
         })
     }
+
+    /// Evaluation tree of a cell, with the value of every sub-expression and referenced cell.
+    pub fn explain(&self, cell: &str) -> Result<String, Box<dyn Error>> {
+        Ok(explain::explain(&self.engine()?, self.locale, parse_cell(cell)?))
+    }
+
+    /// Cells affected by a change to a cell, directly or through other formulas.
+    pub fn dependents(&self, cell: &str) -> Result<String, Box<dyn Error>> {
+        Ok(explain::dependents(&self.engine()?, self.locale, parse_cell(cell)?))
+    }
+
+    /// Evaluates every `ASSERT` of the sheet.
+    pub fn test(&self) -> Result<TestReport, Box<dyn Error>> {
+        Ok(TestReport::run(&self.engine()?, self.locale))
+    }
+
+    /// Checks the sheet against its validation rules.
+    pub fn validate(&self) -> Result<ValidationReport, Box<dyn Error>> {
+        Ok(ValidationReport { violations: self.violations(&self.engine()?)? })
+    }
+
+    fn violations(&self, engine: &CSVEngine) -> Result<Vec<Violation>, Box<dyn Error>> {
+        let path = match &self.validation {
+            Some(path) => path,
+            None => return Ok(Vec::new()),
+        };
+        if self.schema.get().is_none() {
+            let _ = self.schema.set(ValidationRules::parse(&fs::read_to_string(path)?, self.locale)?);
+        }
+        Ok(self.schema.get().map(|rules| rules.check(&engine.source())).unwrap_or_default())
+    }
+}
+
+fn parse_cell(cell: &str) -> Result<CellRef, Box<dyn Error>> {
+    CellRef::from_name(cell).ok_or_else(|| format!("{} is not a cell reference", cell).into())
+}
+
+impl Loader for Calc {
+    type Result = Table;
+
+    fn load(&self) -> Result<Table, Box<dyn Error>> {
+        let collect = parse_cells(&self.collect)?;
+        let table = match (&self.sweep, self.simulate) {
+            (Some(sweep), _) => Sweep::parse(sweep, self.locale)?.table(self, &collect)?,
+            (None, Some(iterations)) => simulate(self, iterations, &collect)?,
+            (None, None) => self.table(&self.engine()?)?,
+        };
+        let table = match &self.pivot {
+            Some(pivot) => Pivot::parse(pivot)?.table(&table),
+            None => table,
+        };
+        match self.view.is_empty() {
+            true => Ok(table),
+            false => self.view.apply(self, table),
+        }
+    }
 }
 
 pub struct CsvPrinter;
@@ -75,7 +491,7 @@ impl Printer<Table> for CsvPrinter {
     fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
         let mut buffer = Vec::new();
         for cell in table.cells() {
-            write!(&mut buffer, "{}", cell)?;
+            write!(&mut buffer, "{}", cell.csv(table.locale().separator))?;
         }
         Ok(buffer)
     }
@@ -87,13 +503,14 @@ impl Printer<Table> for CsvPrinter {
 
 #[cfg(test)]
 mod tests {
-    use crate::calc::{Calc, CsvPrinter, Table};
+    use std::collections::HashMap;
+    use crate::calc::{Calc, CsvPrinter, FormulaMode, Locale, Table};
     use crate::calc::engine::Cell;
     use crate::framework::{Loader, Printer};
 
     impl Table {
         pub fn new(cells: Vec<Cell>) -> Self {
-            Table{ cells }
+            Table{ cells, locale: Locale::default(), formats: HashMap::new() }
         }
     }
 
@@ -102,6 +519,26 @@ mod tests {
         assert_eq!("0,0.00", eval("0,=A1"));
     }
 
+    #[test]
+    fn references_past_row_nine() {
+        assert_eq!("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n20.00", eval("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n=A10*2"));
+    }
+
+    #[test]
+    fn formula_modes() {
+        let source = "Item,Price\na,=1+1\nb,\"=SUM(B2,3)\"".to_string();
+        let print = |mode| {
+            let table = Calc::from_string(source.clone()).with_formulas(mode).load().unwrap();
+            String::from_utf8(CsvPrinter::new().print(table).unwrap()).unwrap()
+        };
+        assert_eq!("Item,Price\na,=1+1\nb,\"=SUM(B2,3)\"", print(FormulaMode::Formulas));
+        assert_eq!("Item,Price,Price formula\na,2.00,=1+1\nb,5.00,\"=SUM(B2,3)\"", print(FormulaMode::Annotated));
+
+        let table = Calc::from_string("Total,Units\n=B2*2,5".to_string()).with_formulas(FormulaMode::Annotated).load().unwrap();
+        let formulas: Vec<_> = table.cells.iter().map(|c| c.formula.as_deref()).collect();
+        assert_eq!(vec![None, None, None, Some("=C2*2"), None, None], formulas);
+    }
+
     #[test]
     fn operators() {
         assert_eq!("1,2,3,6.00", eval("1,2,3,=SUM(A1:C1)"));
@@ -109,6 +546,155 @@ mod tests {
         assert_eq!("1,2,3,2.00", eval("1,2,3,=AVERAGE(A1:C1)"));
     }
 
+    #[test]
+    fn arithmetic() {
+        assert_eq!("2,3,7.00", eval("2,3,=1+A1*B1"));
+        assert_eq!("2,4.00", eval("2,=-A1^2"));
+        assert_eq!("a,2,a2", eval("a,2,=A1&B1"));
+        assert_eq!("2,TRUE", eval("2,=A1>1"));
+    }
+
+    #[test]
+    fn error_propagation() {
+        assert_eq!("1,0,#DIV/0!", eval("1,0,=A1/B1"));
+        assert_eq!("1,#DIV/0!,#DIV/0!", eval("1,=A1/0,=SUM(A1:B1)"));
+        assert_eq!("a,#VALUE!", eval("a,=A1+1"));
+        assert_eq!("#NAME?", eval("=NOPE(1)"));
+        assert_eq!("#CIRC!", eval("=A1"));
+    }
+
+    #[test]
+    fn aggregates_skip_text_in_ranges() {
+        assert_eq!("Price,1,2,3.00", eval("Price,1,2,=SUM(A1:C1)"));
+        assert_eq!("a,#DIV/0!", eval("a,=AVERAGE(A1)"));
+    }
+
+    #[test]
+    fn parses_full_precision() {
+        assert_eq!("0.1,100000000.00", eval("0.1,=A1*1000000000"));
+        assert_eq!("0.1,0.2,FALSE", eval("0.1,0.2,=A1+B1=0.3"));
+    }
+
+    #[test]
+    fn decimal_mode() {
+        let table = Calc::from_string("0.1,0.2,=A1+B1=0.3,=SUM(A1:B1)*3/7".to_string()).with_decimal(true).load().unwrap();
+        let output = String::from_utf8(CsvPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!("0.1,0.2,TRUE,0.13", output);
+    }
+
+    #[test]
+    fn locales() {
+        let de: Locale = "de".parse().unwrap();
+        let table = Calc::from_string("1.234,5;10 %;=SUMME(A1);\"=SUM(A1;B1;0,5)\"".to_string()).with_locale(de).load().unwrap();
+        let output = String::from_utf8(CsvPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!("1.234,5;10 %;#NAME?;1235,10", output);
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!("50%,2,1.00,0.25", eval("50%,2,=A1*B1,=A1/2"));
+        assert_eq!("0.10", eval("=10%"));
+    }
+
+    #[test]
+    fn quotes_output_fields() {
+        assert_eq!("\"a,b\",\"a,b!\"", eval("\"a,b\",\"=A1&\"\"!\"\"\""));
+    }
+
+    #[test]
+    fn blank_cells() {
+        assert_eq!("1,,3,4.00", eval("1,,3,=SUM(A1:C1)"));
+        assert_eq!("1,,3,2.00", eval("1,,3,=COUNT(A1:C1)"));
+        assert_eq!("1,,3,2.00", eval("1,,3,=AVERAGE(A1:C1)"));
+        assert_eq!("1,,3,1.00", eval("1,,3,=COUNTBLANK(A1:C1)"));
+        assert_eq!("1,,1.00", eval("1,,=A1+B1*2+C9"));
+        assert_eq!(",TRUE,FALSE", eval(",=ISBLANK(A1),=ISBLANK(B1)"));
+        assert_eq!(",TRUE,TRUE", eval(",=A1=0,=A1=\"\"\"\"\"\""));
+    }
+
+    #[test]
+    fn error_functions() {
+        assert_eq!("1,0,0.00", eval("1,0,\"=IFERROR(A1/B1, 0)\""));
+        assert_eq!("#N/A,x", eval("#N/A,\"=IFNA(A1, \"\"x\"\")\""));
+        assert_eq!("1,0,2.00", eval("1,0,=ERROR.TYPE(A1/B1)"));
+        assert_eq!("#NA,#N/A", eval("#NA,=ERROR.TYPE(A1)"));
+        assert_eq!("#SPILL!,x,9.00\n,,1\n,,2", eval("=TRANSPOSE(C2:C3),x,=ERROR.TYPE(A1)\n,,1\n,,2"));
+        assert_eq!("TRUE,TRUE", eval("=ISERROR(NA()),=ISNA(NA())"));
+        assert_eq!("1,a,TRUE,TRUE,TRUE", eval("1,a,=ISNUMBER(A1),=ISTEXT(B1),=ISLOGICAL(TRUE)"));
+    }
+
+    #[test]
+    fn goal_seek() {
+        assert_eq!("2,-5.00,3.00", eval("2,=A1*A1-9,\"=GOALSEEK(B1,0,A1)\""));
+        assert_eq!("100,0.01,0.25", eval("100,=1/A1,\"=GOALSEEK(B1,4,A1)\""));
+        assert_eq!("1,2.00,#NUM!", eval("1,=A1*A1+1,\"=GOALSEEK(B1,0,A1)\""));
+        assert_eq!("1,#VALUE!", eval("1,\"=GOALSEEK(A1,0,2)\""));
+    }
+
+    #[test]
+    fn random_functions() {
+        assert_eq!("1.96,1.00,3.00", eval("\"=NORM.INV(0.975,0,1)\",\"=LOGNORM.INV(0.5,0,1)\",\"=RANDBETWEEN(3,3.5)\""));
+        assert_eq!("#NUM!,#NUM!,#VALUE!", eval("\"=NORM.INV(0,0,1)\",\"=TRIANGULAR(1,5,2)\",\"=RANDARRAY(0,2)\""));
+        assert_eq!("4.00,4.00\n4.00,4.00", eval("\"=RANDARRAY(2,2,4,4.5,TRUE)\""));
+        assert!(eval("=RAND(),=A1-A1").ends_with(",0.00"));
+
+        let seeded = || Calc::from_string("=RAND(),=RANDARRAY(1,1,1,6,TRUE)".to_string()).with_seed(Some(5)).load().unwrap();
+        let draws: Vec<String> = seeded().cells().map(|c| c.content.clone()).collect();
+        assert_eq!(draws, seeded().cells().map(|c| c.content.clone()).collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn matrix_functions() {
+        assert_eq!("1,2\n3,4\n-2.00,7.00,10.00\n,15.00,22.00", eval("1,2\n3,4\n=MDETERM(A1:B2),\"=MMULT(A1:B2,A1:B2)\"\n"));
+        assert_eq!("1,2,-2.00,1.00\n3,4,1.50,-0.50", eval("1,2,=MINVERSE(A1:B2)\n3,4"));
+        assert_eq!("1,2,10.00,14.00,1.00\n3,4,14.00,20.00,2.00", eval("1,2,\"=MMULT(TRANSPOSE(A1:B2),A1:B2)\",,=TRANSPOSE(A1:B1)\n3,4"));
+        assert_eq!("1,2,#NUM!,#VALUE!,#VALUE!\n2,4", eval("1,2,=MINVERSE(A1:B2),=MDETERM(A1:A2),\"=MMULT(A1:B2,A1:B1)\"\n2,4"));
+    }
+
+    #[test]
+    fn regression_functions() {
+        let data = "1,3\n2,5\n3,7.5\n4,8.5\n5,11\n";
+        let csv = eval(&format!("{}\"=SLOPE(B1:B5,A1:A5)\",\"=INTERCEPT(B1:B5,A1:A5)\",\"=RSQ(B1:B5,A1:A5)\",\"=CORREL(A1:A5,B1:B5)\",\"=COVARIANCE.S(A1:A5,B1:B5)\",\"=FORECAST.LINEAR(6,B1:B5,A1:A5)\"", data));
+        assert!(csv.ends_with("\n1.95,1.15,0.99,0.99,4.88,12.85"), "{}", csv);
+
+        let csv = eval(&format!("{}\"=LINEST(B1:B5,A1:A5,TRUE,TRUE)\"", data));
+        assert!(csv.ends_with("\n1.95,1.15\n0.13,0.42\n0.99,0.40\n240.16,3.00\n38.02,0.47"), "{}", csv);
+        let csv = eval(&format!("{}\"=TREND(B1:B5,A1:A5,A1:A2)\",\"=GROWTH(B1:B5,A1:A5,6)\",=LINEST(B1:B5),,\"=LINEST(B1:B5,A1:A5,FALSE)\"", data));
+        assert!(csv.ends_with("\n3.10,16.30,1.95,1.15,2.26,0.00\n5.05"), "{}", csv);
+
+        assert_eq!("2,3,4,#N/A,#DIV/0!", eval("2,3,4,\"=SLOPE(A1:C1,A1:B1)\",\"=SLOPE(A1:C1,G1:I1)\""));
+        assert_eq!("0,2,#DIV/0!,#NUM!", eval("0,2,\"=CORREL(A1,B1)\",\"=GROWTH(A1:B1,A1:B1,0)\""));
+    }
+
+    #[test]
+    fn reference_functions() {
+        assert_eq!("2,10,20,30,20.00,60.00,#REF!", eval("2,10,20,30,\"=INDIRECT(\"\"C\"\"&A1-1)\",\"=SUM(INDIRECT(\"\"B1:D\"\"&A1-1))\",\"=INDIRECT(\"\"B0\"\")\""));
+        assert_eq!("2,10,20,30,50.00,#REF!,#VALUE!", eval("2,10,20,30,\"=SUM(OFFSET(A1,0,2,1,A1))\",\"=OFFSET(A1,0,-1)\",\"=OFFSET(1,0,0)\""));
+        assert_eq!("1,1.00\n2,2.00\n3,3.00", eval("1,\"=OFFSET(A1,0,0,3,1)\"\n2\n3"));
+        assert_eq!("1,1.00\n2,2.00", eval("1,\"=INDIRECT(\"\"A1:A2\"\")\"\n2"));
+        assert_eq!("1,#REF!,#REF!,#REF!", eval("1,\"=SUM(OFFSET(A1,0,0,100000,100000))\",\"=ROWS(OFFSET(A1,0,0,1E30,1))\",\"=SUM(INDIRECT(\"\"A1:ZZZ99999\"\"))\""));
+        assert_eq!("1,3.00,3.00,2.00,3.00,3.00,4.00\n2", eval("1,=ROW(B3),=COLUMN(),=ROWS(A1:C2),=COLUMNS(A1:C2),=COLUMN(C1:D1)\n2"));
+        assert_eq!("$C$2,C$2,R[2]C[3],R2C3,'My Sheet'!$C$2,#VALUE!", eval("\"=ADDRESS(2,3)\",\"=ADDRESS(2,3,2)\",\"=ADDRESS(2,3,4,FALSE)\",\"=ADDRESS(2,3,1,FALSE)\",\"=ADDRESS(2,3,1,TRUE,\"\"My Sheet\"\")\",\"=ADDRESS(0,1)\""));
+    }
+
+    #[test]
+    fn r1c1_references() {
+        assert_eq!("1,2,3.00\n4,=R[-1]C[2],3.00", eval("1,2,=RC[-2]+R1C2\n4,\"=\"\"=R[-1]C[2]\"\"\",=R[-1]C[-1]*2+R[0]C1-5"));
+        assert_eq!("3,0,6.00,3.00", eval("3,0,=R1C1*2,\"=INDIRECT(\"\"RC[-3]\"\",FALSE)\""));
+    }
+
+    #[test]
+    fn quantities() {
+        assert_eq!("512 MiB,1 GB,1465.67 MiB,0.54 GB,1024.00 MiB,1.86,TRUE", eval("512 MiB,1 GB,=A1+B1,\"=CONVERT(A1,\"\"MiB\"\",\"\"GB\"\")\",=A1*2,=B1/A1,=A1<B1"));
+        assert_eq!("30 ms,0.25 s,280.00 ms,35.00 ms,-30.00 ms,#UNIT!,#UNIT!", eval("30 ms,0.25 s,=SUM(A1:B1),\"=A1+\"\"5 ms\"\"\",=-A1,=A1+1,\"=A1+\"\"1 GB\"\"\""));
+        assert_eq!("100 MB,2 s,50.00 MB/s,120.00 s,1073.74,212.00,#N/A,#UNIT!", eval("100 MB,2 s,=A1/B1,\"=\"\"6 GB\"\"/C1\",\"=CONVERT(1,\"\"GiB\"\",\"\"MB\"\")\",\"=CONVERT(100,\"\"C\"\",\"\"F\"\")\",\"=CONVERT(1,\"\"apple\"\",\"\"s\"\")\",\"=CONVERT(1,\"\"m\"\",\"\"s\"\")\""));
+    }
+
+    #[test]
+    fn assertions() {
+        assert_eq!("1,TRUE,FALSE,#VALUE!", eval("1,\"=ASSERT(A1=1,\"\"one\"\")\",=ASSERT(A1>1),\"=ASSERT(\"\"a\"\")\""));
+    }
+
     fn eval(input: &str) -> String {
         let table = Calc::from_string(input.to_string()).load().unwrap();
         let printer = CsvPrinter::new();
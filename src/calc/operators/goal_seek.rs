@@ -0,0 +1,116 @@
+use crate::calc::engine::{CellRef, Engine, ErrorKind, Expression, Number, Operator, Value};
+
+/// Finds the value of `input` that makes `target` evaluate to `goal`, starting from the
+/// current value of `input` with Newton's method and falling back to bisection once a
+/// sign change is bracketed.
+pub fn goal_seek<T: Engine>(engine: &T, target: &CellRef, goal: f64, input: &CellRef) -> Result<f64, ErrorKind> {
+    let f = |x: f64| match engine.value_if(target, input, Value::Number(Number::Float(x))).as_number() {
+        Ok(n) => n.to_f64() - goal,
+        Err(_) => f64::NAN,
+    };
+    let tolerance = 1e-9 * goal.abs().max(1.0);
+    let start = engine.value_at(input).as_number().map(|n| n.to_f64()).unwrap_or(0.0);
+
+    newton(&f, start, tolerance)
+        .or_else(|| bracket(&f, start, tolerance))
+        .map(|x| shortest(&f, x, tolerance))
+        .ok_or(ErrorKind::Num)
+}
+
+/// Rounds a solution to the fewest significant digits that still reach the goal.
+fn shortest(f: &impl Fn(f64) -> f64, x: f64, tolerance: f64) -> f64 {
+    (0..16)
+        .filter_map(|digits| format!("{:.*e}", digits, x).parse::<f64>().ok())
+        .find(|rounded| f(*rounded).abs() <= tolerance)
+        .unwrap_or(x)
+}
+
+/// Newton's method with a numerical slope, halving steps that land further from the goal.
+fn newton(f: &impl Fn(f64) -> f64, mut x: f64, tolerance: f64) -> Option<f64> {
+    let mut y = f(x);
+    for _ in 0..100 {
+        if !y.is_finite() {
+            return None;
+        }
+        if y.abs() <= tolerance {
+            return Some(x);
+        }
+        let h = x.abs().max(1.0) * 1e-7;
+        let slope = (f(x + h) - y) / h;
+        if slope == 0.0 || !slope.is_finite() {
+            return None;
+        }
+        let mut step = y / slope;
+        let mut next = f(x - step);
+        for _ in 0..50 {
+            if next.is_finite() && next.abs() < y.abs() {
+                break;
+            }
+            step /= 2.0;
+            next = f(x - step);
+        }
+        x -= step;
+        y = next;
+    }
+    None
+}
+
+/// Steps away from the start in growing steps, either way, until the sign changes.
+fn bracket(f: &impl Fn(f64) -> f64, start: f64, tolerance: f64) -> Option<f64> {
+    for direction in [1.0, -1.0] {
+        let (mut a, mut ya) = (start, f(start));
+        let mut step = start.abs().max(1.0) * 0.1 * direction;
+        for _ in 0..64 {
+            let b = a + step;
+            let yb = f(b);
+            if ya.is_finite() && yb.is_finite() && ya.signum() != yb.signum() {
+                return bisect(f, a, b, ya, tolerance);
+            }
+            (a, ya) = (b, yb);
+            step *= 2.0;
+        }
+    }
+    None
+}
+
+fn bisect(f: &impl Fn(f64) -> f64, mut a: f64, mut b: f64, mut ya: f64, tolerance: f64) -> Option<f64> {
+    for _ in 0..200 {
+        let middle = (a + b) / 2.0;
+        let y = f(middle);
+        if y.abs() <= tolerance {
+            return Some(middle);
+        }
+        if !y.is_finite() || middle == a || middle == b {
+            return None;
+        }
+        if y.signum() == ya.signum() {
+            (a, ya) = (middle, y);
+        } else {
+            b = middle;
+        }
+    }
+    None
+}
+
+pub struct GoalSeek;
+impl<T: Engine> Operator<T> for GoalSeek {
+    fn name(&self) -> &'static str {
+        "GOALSEEK"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match arguments.as_slice() {
+            [Expression::Reference(target), goal, Expression::Reference(input)] => {
+                let goal = match engine.eval(goal).as_number() {
+                    Ok(goal) => goal.to_f64(),
+                    Err(e) => return Value::Error(e)
+                };
+                match goal_seek(engine, target, goal, input) {
+                    Ok(x) => Value::Number(Number::Float(x)),
+                    Err(e) => Value::Error(e)
+                }
+            },
+            _ => Value::Error(ErrorKind::Value)
+        }
+    }
+}
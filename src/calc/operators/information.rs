@@ -0,0 +1,117 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Operator, Value};
+use crate::calc::operators::single;
+
+pub struct IfError;
+impl<T: Engine> Operator<T> for IfError {
+    fn name(&self) -> &'static str {
+        "IFERROR"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match arguments.as_slice() {
+            [value, fallback] => match engine.eval(value) {
+                Value::Error(_) => engine.eval(fallback),
+                v => v
+            },
+            _ => Value::Error(ErrorKind::Value)
+        }
+    }
+}
+
+pub struct IfNa;
+impl<T: Engine> Operator<T> for IfNa {
+    fn name(&self) -> &'static str {
+        "IFNA"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match arguments.as_slice() {
+            [value, fallback] => match engine.eval(value) {
+                Value::Error(ErrorKind::NA) => engine.eval(fallback),
+                v => v
+            },
+            _ => Value::Error(ErrorKind::Value)
+        }
+    }
+}
+
+pub struct Na;
+impl<T: Engine> Operator<T> for Na {
+    fn name(&self) -> &'static str {
+        "NA"
+    }
+
+    fn execute(&self, _: &Vec<Expression>, _: &T) -> Value {
+        Value::Error(ErrorKind::NA)
+    }
+}
+
+pub struct ErrorType;
+impl<T: Engine> Operator<T> for ErrorType {
+    fn name(&self) -> &'static str {
+        "ERROR.TYPE"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match single(arguments).map(|arg| engine.eval(arg)) {
+            Ok(Value::Error(e)) => Value::Number((e.code() as f64).into()),
+            Ok(_) => Value::Error(ErrorKind::NA),
+            Err(e) => Value::Error(e)
+        }
+    }
+}
+
+/// Whether the condition of an assertion holds, numbers counting as true when not zero.
+pub fn holds(condition: Value) -> Result<bool, ErrorKind> {
+    match condition {
+        Value::Bool(b) => Ok(b),
+        Value::Error(e) => Err(e),
+        Value::Text(_) => Err(ErrorKind::Value),
+        v => v.as_number().map(|n| n.to_f64() != 0.0),
+    }
+}
+
+/// `ASSERT(condition, [message])` is `TRUE` when the condition holds and `FALSE` otherwise.
+/// `calc --test` reports the message of every assertion that does not hold.
+pub struct Assert;
+impl<T: Engine> Operator<T> for Assert {
+    fn name(&self) -> &'static str {
+        "ASSERT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match arguments.as_slice() {
+            [condition] | [condition, _] => match holds(engine.eval(condition)) {
+                Ok(b) => Value::Bool(b),
+                Err(e) => Value::Error(e)
+            },
+            _ => Value::Error(ErrorKind::Value)
+        }
+    }
+}
+
+/// Declares an `IS*` function that tests the type of its single argument.
+macro_rules! is_function {
+    ($operator: ident, $name: literal, $test: pat) => {
+        pub struct $operator;
+        impl<T: Engine> Operator<T> for $operator {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+                match single(arguments) {
+                    Ok(arg) => Value::Bool(matches!(engine.eval(arg), $test)),
+                    Err(e) => Value::Error(e)
+                }
+            }
+        }
+    };
+}
+
+is_function!(IsError, "ISERROR", Value::Error(_));
+is_function!(IsNa, "ISNA", Value::Error(ErrorKind::NA));
+is_function!(IsNumber, "ISNUMBER", Value::Number(_) | Value::Quantity(..));
+is_function!(IsText, "ISTEXT", Value::Text(_));
+is_function!(IsLogical, "ISLOGICAL", Value::Bool(_));
+is_function!(IsBlank, "ISBLANK", Value::Empty);
@@ -0,0 +1,87 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Number, Operator, Value};
+use crate::calc::operators::{cells, measures};
+
+pub struct Sum;
+impl<T: Engine> Operator<T> for Sum {
+    fn name(&self) -> &'static str {
+        "SUM"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match measures(arguments, engine) {
+            Ok((numbers, unit)) => match Number::sum(&numbers) {
+                Ok(sum) => Value::measured(sum, unit),
+                Err(e) => Value::Error(e)
+            },
+            Err(e) => Value::Error(e)
+        }
+    }
+}
+
+pub struct Count;
+impl<T: Engine> Operator<T> for Count {
+    fn name(&self) -> &'static str {
+        "COUNT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        let mut count: u64 = 0;
+        for arg in arguments {
+            if let Some(cells) = cells(arg, engine) {
+                for cell in cells.unwrap_or_default() {
+                    if let Value::Number(_) | Value::Quantity(..) = engine.value_at(&cell) {
+                        count += 1;
+                    }
+                }
+            } else if let Value::Quantity(..) = engine.eval(arg) {
+                count += 1;
+            } else if engine.eval(arg).as_number().is_ok() {
+                count += 1;
+            }
+        }
+        Value::Number((count as f64).into())
+    }
+}
+
+pub struct CountBlank;
+impl<T: Engine> Operator<T> for CountBlank {
+    fn name(&self) -> &'static str {
+        "COUNTBLANK"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        let mut count: u64 = 0;
+        for arg in arguments {
+            match cells(arg, engine) {
+                Some(Ok(cells)) => for cell in cells {
+                    match engine.value_at(&cell) {
+                        Value::Empty => count += 1,
+                        Value::Text(t) if t.is_empty() => count += 1,
+                        _ => {}
+                    }
+                },
+                Some(Err(e)) => return Value::Error(e),
+                None => return Value::Error(ErrorKind::Value)
+            }
+        }
+        Value::Number((count as f64).into())
+    }
+}
+
+pub struct Average;
+impl<T: Engine> Operator<T> for Average {
+    fn name(&self) -> &'static str {
+        "AVERAGE"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match measures(arguments, engine) {
+            Ok((numbers, _)) if numbers.is_empty() => Value::Error(ErrorKind::Div0),
+            Ok((numbers, unit)) => match Number::sum(&numbers).and_then(|sum| sum.div((numbers.len() as f64).into())) {
+                Ok(average) => Value::measured(average, unit),
+                Err(e) => Value::Error(e)
+            },
+            Err(e) => Value::Error(e)
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Number, Operator, Value};
+use crate::calc::operators::{array, corner, single, spilled};
+
+pub type Matrix = Vec<Vec<f64>>;
+
+/// Numbers of an array argument, which must all be numbers.
+pub fn matrix<T: Engine>(argument: &Expression, engine: &T) -> Result<Matrix, ErrorKind> {
+    array(argument, engine)?.into_iter()
+        .map(|row| row.into_iter().map(|v| match v {
+            Value::Number(n) => Ok(n.to_f64()),
+            Value::Error(e) => Err(e),
+            _ => Err(ErrorKind::Value),
+        }).collect())
+        .collect()
+}
+
+/// Values of a computed matrix, with `#NUM!` where the result is not finite.
+pub fn values(matrix: Matrix) -> Vec<Vec<Value>> {
+    matrix.into_iter().map(|row| row.into_iter().map(number).collect()).collect()
+}
+
+pub fn number(n: f64) -> Value {
+    match n.is_finite() {
+        true => Value::Number(Number::Float(n)),
+        false => Value::Error(ErrorKind::Num),
+    }
+}
+
+pub fn multiply(a: &Matrix, b: &Matrix) -> Result<Matrix, ErrorKind> {
+    let inner = a.first().map_or(0, |r| r.len());
+    if inner != b.len() || inner == 0 {
+        return Err(ErrorKind::Value);
+    }
+    let columns = b[0].len();
+    Ok(a.iter()
+        .map(|row| (0..columns).map(|c| row.iter().zip(b).map(|(x, other)| x * other[c]).sum()).collect())
+        .collect())
+}
+
+pub fn transpose<V: Clone>(a: &[Vec<V>]) -> Vec<Vec<V>> {
+    let columns = a.first().map_or(0, |r| r.len());
+    (0..columns).map(|c| a.iter().map(|row| row[c].clone()).collect()).collect()
+}
+
+fn square(a: &Matrix) -> Result<usize, ErrorKind> {
+    match a.len() {
+        n if n > 0 && a.iter().all(|row| row.len() == n) => Ok(n),
+        _ => Err(ErrorKind::Value),
+    }
+}
+
+/// Reduces a matrix by Gaussian elimination with partial pivoting, applying the same row
+/// operations to `other`. Returns the determinant.
+fn eliminate(a: &mut Matrix, other: &mut Matrix) -> f64 {
+    let n = a.len();
+    let mut determinant = 1.0;
+    for column in 0..n {
+        let pivot = (column..n).max_by(|x, y| a[*x][column].abs().total_cmp(&a[*y][column].abs())).unwrap_or(column);
+        if a[pivot][column].abs() < 1e-12 {
+            return 0.0;
+        }
+        if pivot != column {
+            a.swap(pivot, column);
+            other.swap(pivot, column);
+            determinant = -determinant;
+        }
+        let scale = a[column][column];
+        determinant *= scale;
+        a[column].iter_mut().chain(other[column].iter_mut()).for_each(|value| *value /= scale);
+        let (reduced, carried) = (a[column].clone(), other[column].clone());
+        for row in 0..n {
+            let factor = a[row][column];
+            if row == column || factor == 0.0 {
+                continue;
+            }
+            for (value, pivot) in a[row].iter_mut().zip(&reduced).chain(other[row].iter_mut().zip(&carried)) {
+                *value -= factor * pivot;
+            }
+        }
+    }
+    determinant
+}
+
+pub fn determinant(a: &Matrix) -> Result<f64, ErrorKind> {
+    let n = square(a)?;
+    Ok(eliminate(&mut a.clone(), &mut vec![Vec::new(); n]))
+}
+
+/// Inverse of a square matrix, `#NUM!` when it is singular.
+pub fn inverse(a: &Matrix) -> Result<Matrix, ErrorKind> {
+    let n = square(a)?;
+    let mut inverse: Matrix = (0..n).map(|r| (0..n).map(|c| if r == c { 1.0 } else { 0.0 }).collect()).collect();
+    if eliminate(&mut a.clone(), &mut inverse) == 0.0 {
+        return Err(ErrorKind::Num);
+    }
+    Ok(inverse)
+}
+
+/// `MMULT(a, b)` spills the matrix product of two arrays.
+pub struct MMult;
+impl<T: Engine> Operator<T> for MMult {
+    fn name(&self) -> &'static str {
+        "MMULT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(match arguments {
+            [a, b] => matrix(a, engine).and_then(|a| multiply(&a, &matrix(b, engine)?)).map(values),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// `MINVERSE(array)` spills the inverse of a square matrix.
+pub struct MInverse;
+impl<T: Engine> Operator<T> for MInverse {
+    fn name(&self) -> &'static str {
+        "MINVERSE"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(single(arguments).and_then(|a| inverse(&matrix(a, engine)?)).map(values)))
+    }
+}
+
+pub struct MDeterm;
+impl<T: Engine> Operator<T> for MDeterm {
+    fn name(&self) -> &'static str {
+        "MDETERM"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match single(arguments).and_then(|a| determinant(&matrix(a, engine)?)) {
+            Ok(d) => number(d),
+            Err(e) => Value::Error(e),
+        }
+    }
+}
+
+/// `TRANSPOSE(array)` spills an array with its rows as columns. Blank cells become zero.
+pub struct Transpose;
+impl<T: Engine> Operator<T> for Transpose {
+    fn name(&self) -> &'static str {
+        "TRANSPOSE"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(single(arguments).and_then(|a| array(a, engine)).map(|rows| {
+            let rows: Vec<Vec<Value>> = rows.into_iter()
+                .map(|row| row.into_iter().map(|v| if matches!(v, Value::Empty) { number(0.0) } else { v }).collect())
+                .collect();
+            transpose(&rows)
+        })))
+    }
+}
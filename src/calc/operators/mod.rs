@@ -0,0 +1,121 @@
+mod math;
+mod information;
+mod goal_seek;
+mod random;
+mod pivot;
+mod matrix;
+mod regression;
+mod reference;
+mod units;
+
+pub use math::{*};
+pub use information::{*};
+pub use goal_seek::{GoalSeek, goal_seek};
+pub use random::{*};
+pub use pivot::{*};
+pub use matrix::{MMult, MInverse, MDeterm, Transpose};
+pub use regression::{*};
+pub use reference::{*};
+pub use units::Convert;
+
+use crate::calc::engine::{CellRef, Engine, ErrorKind, Expression, Number, Unit, Value};
+
+/// Collects the numbers of aggregate arguments the way Excel does: referenced cells
+/// only contribute numbers and quantities, other arguments are coerced and errors propagate.
+/// Quantities are converted to the unit of the first one.
+fn measures<T: Engine>(arguments: &[Expression], engine: &T) -> Result<(Vec<Number>, Unit), ErrorKind> {
+    let mut values = Vec::new();
+    for arg in arguments {
+        if let Some(cells) = cells(arg, engine) {
+            for cell in cells? {
+                match engine.value_at(&cell) {
+                    value @ (Value::Number(_) | Value::Quantity(..)) => values.push(value),
+                    Value::Error(e) => return Err(e),
+                    _ => {}
+                }
+            }
+        } else {
+            match engine.eval(arg) {
+                quantity @ Value::Quantity(..) => values.push(quantity),
+                value => values.push(Value::Number(value.as_number()?)),
+            }
+        }
+    }
+    amounts(values)
+}
+
+/// Amounts of numbers and quantities in the unit of the first quantity. Mixing quantities
+/// with plain numbers, or with quantities of another dimension, is `#UNIT!`.
+fn amounts(values: Vec<Value>) -> Result<(Vec<Number>, Unit), ErrorKind> {
+    let unit = values.iter()
+        .find_map(|v| match v {
+            Value::Quantity(_, unit) => Some(unit.clone()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let numbers = values.into_iter()
+        .map(|v| match v {
+            Value::Quantity(n, from) => from.convert(n, &unit),
+            Value::Number(n) if unit.is_plain() => Ok(n),
+            _ => Err(ErrorKind::Unit),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((numbers, unit))
+}
+
+/// Corners of the cells a reference argument refers to: a reference, a range, or a call to
+/// a function returning a reference such as `OFFSET`.
+fn area<T: Engine>(argument: &Expression, engine: &T) -> Option<Result<(CellRef, CellRef), ErrorKind>> {
+    match argument {
+        Expression::Reference(r) => Some(Ok((*r, *r))),
+        Expression::Range(a, b) => Some(Ok((*a, *b))),
+        Expression::Call(name, arguments) => engine.reference(name, arguments),
+        _ => None
+    }
+}
+
+/// Cells referred to by a reference argument.
+fn cells<T: Engine>(argument: &Expression, engine: &T) -> Option<Result<Vec<CellRef>, ErrorKind>> {
+    area(argument, engine).map(|area| area.map(|(start, end)| CellRef::range(&start, &end)))
+}
+
+fn single(arguments: &[Expression]) -> Result<&Expression, ErrorKind> {
+    match arguments {
+        [argument] => Ok(argument),
+        _ => Err(ErrorKind::Value)
+    }
+}
+
+/// Values of a range argument, row by row.
+fn grid<T: Engine>(argument: &Expression, engine: &T) -> Result<Vec<Vec<Value>>, ErrorKind> {
+    let (start, end) = area(argument, engine).ok_or(ErrorKind::Value)??;
+    Ok(rows_between(start, end, engine))
+}
+
+fn rows_between<T: Engine>(start: CellRef, end: CellRef, engine: &T) -> Vec<Vec<Value>> {
+    (start.row()..=end.row())
+        .map(|row| (start.column()..=end.column()).map(|column| engine.value_at(&CellRef::new(row, column))).collect())
+        .collect()
+}
+
+/// Values of an array argument, row by row: a range, a call to a function returning a
+/// reference or an array, or a single value.
+fn array<T: Engine>(argument: &Expression, engine: &T) -> Result<Vec<Vec<Value>>, ErrorKind> {
+    if let Some(area) = area(argument, engine) {
+        let (start, end) = area?;
+        return Ok(rows_between(start, end, engine));
+    }
+    match argument {
+        Expression::Call(name, arguments) => Ok(engine.array(name, arguments).unwrap_or_else(|| vec![vec![engine.eval(argument)]])),
+        _ => Ok(vec![vec![engine.eval(argument)]]),
+    }
+}
+
+fn spilled(table: Result<Vec<Vec<Value>>, ErrorKind>) -> Vec<Vec<Value>> {
+    table.unwrap_or_else(|e| vec![vec![Value::Error(e)]])
+}
+
+/// A formula nested in another one only gets the first cell of the table.
+fn corner(table: Option<Vec<Vec<Value>>>) -> Value {
+    table.and_then(|t| t.into_iter().next()).and_then(|r| r.into_iter().next()).unwrap_or(Value::Empty)
+}
@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::calc::engine::{Engine, ErrorKind, Expression, Number, Operator, Value};
+use crate::calc::operators::{amounts, corner, grid, spilled};
+
+/// How the values of a group are summarized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Count,
+    CountA,
+    Average,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_uppercase().as_str() {
+            "SUM" => Some(Aggregate::Sum),
+            "COUNT" => Some(Aggregate::Count),
+            "COUNTA" => Some(Aggregate::CountA),
+            "AVERAGE" => Some(Aggregate::Average),
+            "MIN" => Some(Aggregate::Min),
+            "MAX" => Some(Aggregate::Max),
+            _ => None,
+        }
+    }
+
+    /// Summarizes values, skipping blanks and text like the functions of the same name.
+    pub fn apply<'a>(&self, values: impl Iterator<Item=&'a Value>) -> Value {
+        let mut measures = Vec::new();
+        let mut filled = 0;
+        for value in values {
+            match value {
+                Value::Error(e) => return Value::Error(*e),
+                Value::Number(_) | Value::Quantity(..) => measures.push(value.clone()),
+                Value::Empty => continue,
+                _ => {}
+            }
+            filled += 1;
+        }
+        let (numbers, unit) = match amounts(measures) {
+            Ok((numbers, unit)) => (numbers.into_iter().map(Number::to_f64).collect::<Vec<f64>>(), unit),
+            Err(e) => return Value::Error(e),
+        };
+        let result = match self {
+            Aggregate::Sum => numbers.iter().sum(),
+            Aggregate::Count => numbers.len() as f64,
+            Aggregate::CountA => filled as f64,
+            Aggregate::Average if numbers.is_empty() => return Value::Error(ErrorKind::Div0),
+            Aggregate::Average => numbers.iter().sum::<f64>() / numbers.len() as f64,
+            Aggregate::Min => numbers.iter().copied().reduce(f64::min).unwrap_or(0.0),
+            Aggregate::Max => numbers.iter().copied().reduce(f64::max).unwrap_or(0.0),
+        };
+        match self {
+            Aggregate::Count | Aggregate::CountA => Value::Number(Number::Float(result)),
+            _ => Value::measured(Number::Float(result), unit),
+        }
+    }
+}
+
+/// A row of source data: the keys it is grouped by, the key of its pivot column, if any,
+/// and the values to summarize.
+pub struct Record {
+    pub keys: Vec<Value>,
+    pub column: Option<Value>,
+    pub values: Vec<Value>,
+}
+
+/// Summarizes records by their keys, sorted, followed by a total row. Without pivoting every
+/// value column is summarized, as `GROUPBY` does. Pivots summarize the single value column
+/// for every column key, followed by a total column, as `PIVOTBY` does.
+///
+/// `headers` names the key and value columns; tables without it have no header row, except
+/// pivots, whose first row holds the column keys.
+pub fn pivot(records: &[Record], aggregate: Aggregate, headers: Option<(Vec<Value>, Vec<Value>)>, pivoting: bool) -> Vec<Vec<Value>> {
+    let width = match (&headers, records.first()) {
+        (Some((keys, _)), _) => keys.len(),
+        (None, Some(record)) => record.keys.len(),
+        (None, None) => 1,
+    };
+    let mut groups: BTreeMap<Key, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        groups.entry(Key(&record.keys)).or_default().push(record);
+    }
+    let columns: Vec<&Value> = records.iter()
+        .filter_map(|r| r.column.as_ref().map(|c| Key(std::slice::from_ref(c))))
+        .collect::<BTreeSet<Key>>()
+        .into_iter()
+        .map(|k| &k.0[0])
+        .collect();
+    let values = records.first().map_or(0, |r| r.values.len());
+
+    let mut table = Vec::new();
+    match (&headers, pivoting) {
+        (Some((keys, values)), false) => table.push(keys.iter().chain(values).cloned().collect()),
+        (_, true) => {
+            let mut header = match &headers {
+                Some((keys, _)) => keys.clone(),
+                None => vec![Value::Empty; width],
+            };
+            header.extend(columns.iter().map(|c| (*c).clone()));
+            header.push(Value::Text("Total".to_string()));
+            table.push(header);
+        },
+        (None, false) => {},
+    }
+
+    let summary = |group: &[&Record]| -> Vec<Value> {
+        let mut row = Vec::new();
+        if pivoting {
+            let mut cells: Vec<Vec<&Value>> = vec![Vec::new(); columns.len()];
+            for record in group {
+                if let (Some(column), Some(value)) = (&record.column, record.values.first()) {
+                    if let Ok(index) = columns.binary_search_by(|c| c.sort_order(column)) {
+                        cells[index].push(value);
+                    }
+                }
+            }
+            for cells in cells {
+                row.push(if cells.is_empty() { Value::Empty } else { aggregate.apply(cells.into_iter()) });
+            }
+            row.push(aggregate.apply(group.iter().filter_map(|r| r.values.first())));
+        } else {
+            for index in 0..values {
+                row.push(aggregate.apply(group.iter().filter_map(|r| r.values.get(index))));
+            }
+        }
+        row
+    };
+
+    for (keys, group) in &groups {
+        let mut row = keys.0.to_vec();
+        row.extend(summary(group));
+        table.push(row);
+    }
+    let mut total = vec![Value::Text("Total".to_string())];
+    total.resize(width.max(1), Value::Empty);
+    total.extend(summary(&records.iter().collect::<Vec<&Record>>()));
+    table.push(total);
+    table
+}
+
+/// Keys ordered as the summary sorts them, so records can be bucketed in one pass.
+struct Key<'a>(&'a [Value]);
+
+impl Ord for Key<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().zip(other.0).map(|(x, y)| x.sort_order(y)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Key<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Key<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Key<'_> {}
+
+/// Builds records from ranges of keys, optional column keys and values, skipping rows
+/// without any key. With headers the first row of every range names its columns.
+fn summarize<T: Engine>(keys: &Expression, columns: Option<&Expression>, values: &Expression, aggregate: &Expression, headers: Option<&Expression>, engine: &T) -> Result<Vec<Vec<Value>>, ErrorKind> {
+    let aggregate = match engine.eval(aggregate) {
+        Value::Text(name) => Aggregate::parse(&name).ok_or(ErrorKind::Value)?,
+        Value::Error(e) => return Err(e),
+        _ => return Err(ErrorKind::Value),
+    };
+    let headers = match headers.map(|h| engine.eval(h)) {
+        Some(Value::Error(e)) => return Err(e),
+        Some(h) => h.as_number()?.to_f64() != 0.0,
+        None => false,
+    };
+    let mut keys = grid(keys, engine)?;
+    let mut columns = columns.map(|c| grid(c, engine)).transpose()?;
+    let mut values = grid(values, engine)?;
+    if values.len() != keys.len() || columns.as_ref().is_some_and(|c| c.len() != keys.len() || c[0].len() != 1 || values[0].len() != 1) {
+        return Err(ErrorKind::Value);
+    }
+
+    let names = if headers {
+        let (key_names, value_names) = (keys.remove(0), values.remove(0));
+        if let Some(columns) = columns.as_mut() {
+            columns.remove(0);
+        }
+        Some((key_names, value_names))
+    } else {
+        None
+    };
+    let records: Vec<Record> = keys.into_iter().zip(values).enumerate()
+        .filter(|(_, (keys, _))| keys.iter().any(|k| !matches!(k, Value::Empty)))
+        .map(|(row, (keys, values))| Record { keys, column: columns.as_ref().map(|c| c[row][0].clone()), values })
+        .collect();
+    Ok(pivot(&records, aggregate, names, columns.is_some()))
+}
+
+/// `GROUPBY(keys, values, aggregate, [headers])` summarizes the values of every distinct key
+/// with `"SUM"`, `"COUNT"`, `"COUNTA"`, `"AVERAGE"`, `"MIN"` or `"MAX"`, and spills the table.
+pub struct GroupBy;
+impl<T: Engine> Operator<T> for GroupBy {
+    fn name(&self) -> &'static str {
+        "GROUPBY"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(match arguments {
+            [keys, values, aggregate] => summarize(keys, None, values, aggregate, None, engine),
+            [keys, values, aggregate, headers] => summarize(keys, None, values, aggregate, Some(headers), engine),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// `PIVOTBY(keys, column keys, values, aggregate, [headers])` summarizes the values of every
+/// distinct key in a column for every distinct column key, and spills the table.
+pub struct PivotBy;
+impl<T: Engine> Operator<T> for PivotBy {
+    fn name(&self) -> &'static str {
+        "PIVOTBY"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(match arguments {
+            [keys, columns, values, aggregate] => summarize(keys, Some(columns), values, aggregate, None, engine),
+            [keys, columns, values, aggregate, headers] => summarize(keys, Some(columns), values, aggregate, Some(headers), engine),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
@@ -0,0 +1,214 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Number, Operator, Value};
+use crate::calc::operators::{corner, spilled};
+
+/// Evaluates every argument to a number.
+fn floats<T: Engine>(arguments: &[Expression], engine: &T) -> Result<Vec<f64>, ErrorKind> {
+    arguments.iter().map(|a| engine.eval(a).as_number().map(|n| n.to_f64())).collect()
+}
+
+fn number(result: Result<f64, ErrorKind>) -> Value {
+    match result {
+        Ok(n) if n.is_finite() => Value::Number(Number::Float(n)),
+        Ok(_) => Value::Error(ErrorKind::Num),
+        Err(e) => Value::Error(e),
+    }
+}
+
+/// Inverse of the standard normal distribution, by Acklam's rational approximation.
+fn norm_s_inv(p: f64) -> Result<f64, ErrorKind> {
+    if !(p > 0.0 && p < 1.0) {
+        return Err(ErrorKind::Num);
+    }
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+    let tail = |q: f64| (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0);
+
+    Ok(if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    })
+}
+
+/// A draw in `(0, 1)`, which the inverse distributions accept.
+fn open_draw<T: Engine>(engine: &T) -> f64 {
+    engine.random().max(f64::MIN_POSITIVE)
+}
+
+/// A whole number between `bottom` and `top`, both included.
+fn between(bottom: f64, top: f64, draw: f64) -> Result<f64, ErrorKind> {
+    let (bottom, top) = (bottom.ceil(), top.floor());
+    if bottom > top {
+        return Err(ErrorKind::Num);
+    }
+    Ok(bottom + (draw * (top - bottom + 1.0)).floor())
+}
+
+pub struct Rand;
+impl<T: Engine> Operator<T> for Rand {
+    fn name(&self) -> &'static str {
+        "RAND"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match arguments.as_slice() {
+            [] => number(Ok(engine.random())),
+            _ => Value::Error(ErrorKind::Value)
+        }
+    }
+}
+
+pub struct RandBetween;
+impl<T: Engine> Operator<T> for RandBetween {
+    fn name(&self) -> &'static str {
+        "RANDBETWEEN"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [bottom, top] => between(*bottom, *top, engine.random()),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// Most cells `RANDARRAY` spills into, so a typo cannot exhaust memory.
+const MAX_DRAWS: f64 = 1e6;
+
+/// `RANDARRAY([rows], [columns], [min], [max], [whole])`, spilling `rows` by `columns` draws.
+pub struct RandArray;
+impl<T: Engine> Operator<T> for RandArray {
+    fn name(&self) -> &'static str {
+        "RANDARRAY"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(floats(arguments, engine).and_then(|args| {
+            let arg = |i: usize, default: f64| args.get(i).copied().unwrap_or(default);
+            let (rows, columns) = (arg(0, 1.0).trunc(), arg(1, 1.0).trunc());
+            let (min, max) = (arg(2, 0.0), arg(3, 1.0));
+            if args.len() > 5 || rows < 1.0 || columns < 1.0 || min > max {
+                return Err(ErrorKind::Value);
+            }
+            if rows * columns > MAX_DRAWS {
+                return Err(ErrorKind::Num);
+            }
+            let whole = arg(4, 0.0) != 0.0;
+            (0..rows as usize).map(|_| (0..columns as usize).map(|_| Ok(number(match whole {
+                true => between(min, max, engine.random()),
+                false => Ok(min + engine.random() * (max - min)),
+            }))).collect()).collect()
+        })))
+    }
+}
+
+pub struct NormInv;
+impl<T: Engine> Operator<T> for NormInv {
+    fn name(&self) -> &'static str {
+        "NORM.INV"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [_, _, sd] if *sd <= 0.0 => Err(ErrorKind::Num),
+            [p, mean, sd] => norm_s_inv(*p).map(|z| mean + sd * z),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+pub struct NormSInv;
+impl<T: Engine> Operator<T> for NormSInv {
+    fn name(&self) -> &'static str {
+        "NORM.S.INV"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [p] => norm_s_inv(*p),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+pub struct LognormInv;
+impl<T: Engine> Operator<T> for LognormInv {
+    fn name(&self) -> &'static str {
+        "LOGNORM.INV"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [_, _, sd] if *sd <= 0.0 => Err(ErrorKind::Num),
+            [p, mean, sd] => norm_s_inv(*p).map(|z| (mean + sd * z).exp()),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// Draws uniformly between a minimum and a maximum.
+pub struct Uniform;
+impl<T: Engine> Operator<T> for Uniform {
+    fn name(&self) -> &'static str {
+        "UNIFORM"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [min, max] if min > max => Err(ErrorKind::Num),
+            [min, max] => Ok(min + engine.random() * (max - min)),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// Draws from a triangular distribution, given its minimum, most likely value and maximum.
+pub struct Triangular;
+impl<T: Engine> Operator<T> for Triangular {
+    fn name(&self) -> &'static str {
+        "TRIANGULAR"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [min, mode, max] if min > mode || mode > max || min == max => Err(ErrorKind::Num),
+            [min, mode, max] => {
+                let draw = engine.random();
+                let width = max - min;
+                if draw < (mode - min) / width {
+                    Ok(min + (draw * width * (mode - min)).sqrt())
+                } else {
+                    Ok(max - ((1.0 - draw) * width * (max - mode)).sqrt())
+                }
+            },
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
+
+/// Draws from a lognormal distribution, given the mean and standard deviation of its logarithm.
+pub struct Lognormal;
+impl<T: Engine> Operator<T> for Lognormal {
+    fn name(&self) -> &'static str {
+        "LOGNORMAL"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        number(floats(arguments, engine).and_then(|args| match args.as_slice() {
+            [_, sd] if *sd <= 0.0 => Err(ErrorKind::Num),
+            [mean, sd] => norm_s_inv(open_draw(engine)).map(|z| (mean + sd * z).exp()),
+            _ => Err(ErrorKind::Value)
+        }))
+    }
+}
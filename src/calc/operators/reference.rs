@@ -0,0 +1,272 @@
+use crate::calc::engine::{CellRef, Engine, ErrorKind, Expression, Number, Operator, Value};
+use crate::calc::operators::{area, array, holds, rows_between};
+
+type Area = (CellRef, CellRef);
+
+/// Most cells a reference function may refer to, so that huge areas are `#REF!` instead of
+/// taking forever to read.
+const MAX_CELLS: usize = 1 << 24;
+
+/// Value of the cell a reference function refers to, or of its top left cell when there
+/// are several, which the formula spills.
+fn referenced<T: Engine>(area: Option<Result<Area, ErrorKind>>, engine: &T) -> Value {
+    match area {
+        Some(Ok((start, _))) => engine.value_at(&start),
+        None => Value::Error(ErrorKind::Value),
+        Some(Err(e)) => Value::Error(e),
+    }
+}
+
+/// The area between two corners, unless it holds too many cells.
+fn bounded(start: CellRef, end: CellRef) -> Result<Area, ErrorKind> {
+    let cells = (end.row() - start.row() + 1).checked_mul(end.column() - start.column() + 1);
+    match cells {
+        Some(cells) if cells <= MAX_CELLS => Ok((start, end)),
+        _ => Err(ErrorKind::Ref),
+    }
+}
+
+/// Values of the cells a reference function refers to, when there are several.
+fn spill_area<T: Engine>(area: Option<Result<Area, ErrorKind>>, engine: &T) -> Option<Vec<Vec<Value>>> {
+    match area {
+        Some(Ok((start, end))) if start != end => Some(rows_between(start, end, engine)),
+        _ => None,
+    }
+}
+
+fn whole<T: Engine>(argument: &Expression, engine: &T) -> Result<isize, ErrorKind> {
+    engine.eval(argument).as_number().map(|n| n.to_f64().trunc() as isize)
+}
+
+fn count(n: usize) -> Value {
+    Value::Number(Number::Float(n as f64))
+}
+
+/// `INDIRECT(text, [a1])` refers to the cell or range named by text, such as `"B" & A1`, or
+/// `"R[-1]C"` in R1C1 style when `a1` is `FALSE`.
+pub struct Indirect;
+impl<T: Engine> Operator<T> for Indirect {
+    fn name(&self) -> &'static str {
+        "INDIRECT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        referenced(self.reference(arguments, engine), engine)
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        spill_area(self.reference(arguments, engine), engine)
+    }
+
+    fn reference(&self, arguments: &[Expression], engine: &T) -> Option<Result<Area, ErrorKind>> {
+        let indirect = || {
+            let (text, a1) = match arguments {
+                [text] => (text, true),
+                [text, a1] => (text, holds(engine.eval(a1))?),
+                _ => return Err(ErrorKind::Value),
+            };
+            let text = match engine.eval(text) {
+                Value::Text(t) => t.trim().to_uppercase(),
+                Value::Error(e) => return Err(e),
+                _ => return Err(ErrorKind::Ref),
+            };
+            let at = engine.current().unwrap_or(CellRef::new(0, 0));
+            let cell = |part: &str| match a1 {
+                true => CellRef::from_name(part),
+                false => CellRef::from_r1c1(part, at),
+            };
+            let (start, end) = text.split_once(':').unwrap_or((&text, &text));
+            match (cell(start), cell(end)) {
+                (Some(start), Some(end)) => bounded(
+                    CellRef::new(start.row().min(end.row()), start.column().min(end.column())),
+                    CellRef::new(start.row().max(end.row()), start.column().max(end.column())),
+                ),
+                _ => Err(ErrorKind::Ref),
+            }
+        };
+        Some(indirect())
+    }
+}
+
+/// `OFFSET(reference, rows, columns, [height], [width])` refers to the range moved from a
+/// reference by rows and columns, as high and wide as the reference unless given.
+pub struct Offset;
+impl<T: Engine> Operator<T> for Offset {
+    fn name(&self) -> &'static str {
+        "OFFSET"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        referenced(self.reference(arguments, engine), engine)
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        spill_area(self.reference(arguments, engine), engine)
+    }
+
+    fn reference(&self, arguments: &[Expression], engine: &T) -> Option<Result<Area, ErrorKind>> {
+        let offset = || {
+            let (base, rows, columns, size) = match arguments {
+                [base, rows, columns, size @ ..] if size.len() <= 2 => (base, rows, columns, size),
+                _ => return Err(ErrorKind::Value),
+            };
+            let (start, end) = area(base, engine).ok_or(ErrorKind::Value)??;
+            let height = size.first().map_or(Ok((end.row() - start.row() + 1) as isize), |h| whole(h, engine))?;
+            let width = size.get(1).map_or(Ok((end.column() - start.column() + 1) as isize), |w| whole(w, engine))?;
+            let row = (start.row() as isize).checked_add(whole(rows, engine)?).ok_or(ErrorKind::Ref)?;
+            let column = (start.column() as isize).checked_add(whole(columns, engine)?).ok_or(ErrorKind::Ref)?;
+            if row < 0 || column < 0 || height < 1 || width < 1 {
+                return Err(ErrorKind::Ref);
+            }
+            let (row, column, height, width) = (row as usize, column as usize, height as usize, width as usize);
+            match (row.checked_add(height - 1), column.checked_add(width - 1)) {
+                (Some(last_row), Some(last_column)) => bounded(CellRef::new(row, column), CellRef::new(last_row, last_column)),
+                _ => Err(ErrorKind::Ref),
+            }
+        };
+        Some(offset())
+    }
+}
+
+/// Corners of the reference argument of `ROW` and `COLUMN`, or the calling cell without one.
+fn position<T: Engine>(arguments: &[Expression], engine: &T) -> Result<Area, ErrorKind> {
+    match arguments {
+        [] => engine.current().map(|c| (c, c)).ok_or(ErrorKind::Value),
+        [reference] => area(reference, engine).ok_or(ErrorKind::Value)?,
+        _ => Err(ErrorKind::Value),
+    }
+}
+
+/// `ROW([reference])` is the row number of a reference, or of the calling cell. A range of
+/// several rows spills their numbers down.
+pub struct Row;
+impl<T: Engine> Operator<T> for Row {
+    fn name(&self) -> &'static str {
+        "ROW"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match position(arguments, engine) {
+            Ok((start, _)) => count(start.row() + 1),
+            Err(e) => Value::Error(e),
+        }
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        match position(arguments, engine) {
+            Ok((start, end)) if end.row() > start.row() => Some((start.row()..=end.row()).map(|r| vec![count(r + 1)]).collect()),
+            _ => None,
+        }
+    }
+}
+
+/// `COLUMN([reference])` is the column number of a reference, or of the calling cell. A
+/// range of several columns spills their numbers right.
+pub struct Column;
+impl<T: Engine> Operator<T> for Column {
+    fn name(&self) -> &'static str {
+        "COLUMN"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match position(arguments, engine) {
+            Ok((start, _)) => count(start.column() + 1),
+            Err(e) => Value::Error(e),
+        }
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        match position(arguments, engine) {
+            Ok((start, end)) if end.column() > start.column() => Some(vec![(start.column()..=end.column()).map(|c| count(c + 1)).collect()]),
+            _ => None,
+        }
+    }
+}
+
+/// Rows and columns of a reference or array argument.
+fn size<T: Engine>(arguments: &[Expression], engine: &T) -> Result<(usize, usize), ErrorKind> {
+    match arguments {
+        [argument] => match area(argument, engine) {
+            Some(area) => area.map(|(start, end)| (end.row() - start.row() + 1, end.column() - start.column() + 1)),
+            None => array(argument, engine).map(|rows| (rows.len(), rows.first().map_or(0, |r| r.len()))),
+        },
+        _ => Err(ErrorKind::Value),
+    }
+}
+
+pub struct Rows;
+impl<T: Engine> Operator<T> for Rows {
+    fn name(&self) -> &'static str {
+        "ROWS"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match size(arguments, engine) {
+            Ok((rows, _)) => count(rows),
+            Err(e) => Value::Error(e),
+        }
+    }
+}
+
+pub struct Columns;
+impl<T: Engine> Operator<T> for Columns {
+    fn name(&self) -> &'static str {
+        "COLUMNS"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        match size(arguments, engine) {
+            Ok((_, columns)) => count(columns),
+            Err(e) => Value::Error(e),
+        }
+    }
+}
+
+/// `ADDRESS(row, column, [absolute], [a1], [sheet])` is the text of a reference: `$B$3` by
+/// default, `B$3`, `$B3` or `B3` with `absolute` 2 to 4, and in R1C1 style when `a1` is `FALSE`,
+/// where relative parts are offsets such as `R[3]C[2]`.
+pub struct Address;
+impl<T: Engine> Operator<T> for Address {
+    fn name(&self) -> &'static str {
+        "ADDRESS"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        let address = || {
+            let (row, column, rest) = match arguments.as_slice() {
+                [row, column, rest @ ..] if rest.len() <= 3 => (whole(row, engine)?, whole(column, engine)?, rest),
+                _ => return Err(ErrorKind::Value),
+            };
+            let absolute = rest.first().map_or(Ok(1), |a| whole(a, engine))?;
+            let a1 = rest.get(1).map_or(Ok(true), |a| holds(engine.eval(a)))?;
+            let (absolute_row, absolute_column) = match absolute {
+                1 => (true, true),
+                2 => (true, false),
+                3 => (false, true),
+                4 => (false, false),
+                _ => return Err(ErrorKind::Value),
+            };
+            if (a1 || absolute_row) && row < 1 || (a1 || absolute_column) && column < 1 {
+                return Err(ErrorKind::Value);
+            }
+
+            let address = if a1 {
+                let dollar = |absolute: bool| if absolute { "$" } else { "" };
+                format!("{}{}{}{}", dollar(absolute_column), CellRef::column_name(column as usize - 1), dollar(absolute_row), row)
+            } else {
+                let part = |absolute: bool, n: isize| if absolute { n.to_string() } else { format!("[{}]", n) };
+                format!("R{}C{}", part(absolute_row, row), part(absolute_column, column))
+            };
+            Ok(match rest.get(2).map(|s| engine.eval(s)) {
+                Some(Value::Error(e)) => return Err(e),
+                Some(Value::Text(sheet)) if sheet.contains(' ') => format!("'{}'!{}", sheet, address),
+                Some(Value::Text(sheet)) => format!("{}!{}", sheet, address),
+                _ => address,
+            })
+        };
+        match address() {
+            Ok(address) => Value::Text(address),
+            Err(e) => Value::Error(e),
+        }
+    }
+}
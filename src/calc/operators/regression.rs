@@ -0,0 +1,364 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Operator, Value};
+use crate::calc::operators::{array, corner, holds, spilled};
+use crate::calc::operators::matrix::{inverse, Matrix, matrix, multiply, number, transpose, values};
+
+/// Pairs of numbers from two arrays of the same size, skipping pairs where either is not a number.
+fn pairs<T: Engine>(ys: &Expression, xs: &Expression, engine: &T) -> Result<Vec<(f64, f64)>, ErrorKind> {
+    let ys: Vec<Value> = array(ys, engine)?.into_iter().flatten().collect();
+    let xs: Vec<Value> = array(xs, engine)?.into_iter().flatten().collect();
+    if ys.len() != xs.len() {
+        return Err(ErrorKind::NA);
+    }
+    let mut pairs = Vec::new();
+    for (y, x) in ys.into_iter().zip(xs) {
+        match (y, x) {
+            (Value::Error(e), _) | (_, Value::Error(e)) => return Err(e),
+            (Value::Number(y), Value::Number(x)) => pairs.push((y.to_f64(), x.to_f64())),
+            _ => {}
+        }
+    }
+    Ok(pairs)
+}
+
+/// Sums of squares and products of pairs about their means.
+struct Moments {
+    count: f64,
+    mean_y: f64,
+    mean_x: f64,
+    yy: f64,
+    xx: f64,
+    xy: f64,
+}
+
+impl Moments {
+    fn of(pairs: &[(f64, f64)]) -> Result<Self, ErrorKind> {
+        if pairs.is_empty() {
+            return Err(ErrorKind::Div0);
+        }
+        let count = pairs.len() as f64;
+        let mean_y = pairs.iter().map(|p| p.0).sum::<f64>() / count;
+        let mean_x = pairs.iter().map(|p| p.1).sum::<f64>() / count;
+        let sum = |f: &dyn Fn(f64, f64) -> f64| pairs.iter().map(|(y, x)| f(y - mean_y, x - mean_x)).sum();
+        Ok(Moments { count, mean_y, mean_x, yy: sum(&|y, _| y * y), xx: sum(&|_, x| x * x), xy: sum(&|y, x| y * x) })
+    }
+
+    fn slope(&self) -> Result<f64, ErrorKind> {
+        if self.xx == 0.0 {
+            return Err(ErrorKind::Div0);
+        }
+        Ok(self.xy / self.xx)
+    }
+
+    fn intercept(&self) -> Result<f64, ErrorKind> {
+        Ok(self.mean_y - self.slope()? * self.mean_x)
+    }
+
+    fn correlation(&self) -> Result<f64, ErrorKind> {
+        let spread = (self.xx * self.yy).sqrt();
+        if spread == 0.0 {
+            return Err(ErrorKind::Div0);
+        }
+        Ok(self.xy / spread)
+    }
+}
+
+fn moments<T: Engine>(ys: &Expression, xs: &Expression, engine: &T) -> Result<Moments, ErrorKind> {
+    Moments::of(&pairs(ys, xs, engine)?)
+}
+
+fn result(result: Result<f64, ErrorKind>) -> Value {
+    match result {
+        Ok(n) => number(n),
+        Err(e) => Value::Error(e),
+    }
+}
+
+/// Evaluates a statistic of the moments of two arrays.
+fn statistic<T: Engine>(arguments: &[Expression], engine: &T, f: impl Fn(Moments) -> Result<f64, ErrorKind>) -> Value {
+    result(match arguments {
+        [ys, xs] => moments(ys, xs, engine).and_then(f),
+        _ => Err(ErrorKind::Value),
+    })
+}
+
+pub struct Slope;
+impl<T: Engine> Operator<T> for Slope {
+    fn name(&self) -> &'static str {
+        "SLOPE"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        statistic(arguments, engine, |m| m.slope())
+    }
+}
+
+pub struct Intercept;
+impl<T: Engine> Operator<T> for Intercept {
+    fn name(&self) -> &'static str {
+        "INTERCEPT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        statistic(arguments, engine, |m| m.intercept())
+    }
+}
+
+pub struct Rsq;
+impl<T: Engine> Operator<T> for Rsq {
+    fn name(&self) -> &'static str {
+        "RSQ"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        statistic(arguments, engine, |m| m.correlation().map(|r| r * r))
+    }
+}
+
+pub struct Correl;
+impl<T: Engine> Operator<T> for Correl {
+    fn name(&self) -> &'static str {
+        "CORREL"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        statistic(arguments, engine, |m| m.correlation())
+    }
+}
+
+pub struct CovarianceS;
+impl<T: Engine> Operator<T> for CovarianceS {
+    fn name(&self) -> &'static str {
+        "COVARIANCE.S"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        statistic(arguments, engine, |m| match m.count {
+            c if c < 2.0 => Err(ErrorKind::Div0),
+            c => Ok(m.xy / (c - 1.0)),
+        })
+    }
+}
+
+/// `FORECAST.LINEAR(x, known ys, known xs)` predicts the `y` of `x` on the line fitting the known values.
+pub struct ForecastLinear;
+impl<T: Engine> Operator<T> for ForecastLinear {
+    fn name(&self) -> &'static str {
+        "FORECAST.LINEAR"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        result(match arguments.as_slice() {
+            [x, ys, xs] => engine.eval(x).as_number().and_then(|x| {
+                let moments = moments(ys, xs, engine)?;
+                Ok(moments.intercept()? + moments.slope()? * x.to_f64())
+            }),
+            _ => Err(ErrorKind::Value),
+        })
+    }
+}
+
+/// Known values of a regression. Known ys are a column, with a column of known xs for every
+/// variable, or a row with a row for every variable.
+struct Known {
+    ys: Vec<f64>,
+    /// The variables of every known y.
+    xs: Matrix,
+    by_rows: bool,
+}
+
+impl Known {
+    fn of<T: Engine>(ys: &Expression, xs: Option<&Expression>, engine: &T) -> Result<Self, ErrorKind> {
+        let ys = matrix(ys, engine)?;
+        let by_rows = ys.len() == 1 && ys[0].len() > 1;
+        let ys: Vec<f64> = ys.into_iter().flatten().collect();
+        let xs = match xs {
+            Some(xs) => observations(matrix(xs, engine)?, ys.len(), by_rows)?,
+            None => (1..=ys.len()).map(|x| vec![x as f64]).collect(),
+        };
+        Ok(Known { ys, xs, by_rows })
+    }
+
+    /// New values of the variables, a row for each, and how many columns their results span.
+    fn new_xs<T: Engine>(&self, xs: Option<&Expression>, engine: &T) -> Result<(Matrix, usize), ErrorKind> {
+        let variables = self.xs[0].len();
+        let xs = match xs {
+            Some(xs) => matrix(xs, engine)?,
+            None if self.by_rows => transpose(&self.xs),
+            None => self.xs.clone(),
+        };
+        if variables == 1 {
+            let width = xs.first().map_or(1, |r| r.len());
+            return Ok((xs.into_iter().flatten().map(|x| vec![x]).collect(), width));
+        }
+        let xs = if self.by_rows { transpose(&xs) } else { xs };
+        if xs.iter().any(|row| row.len() != variables) {
+            return Err(ErrorKind::Ref);
+        }
+        let width = if self.by_rows { xs.len() } else { 1 };
+        Ok((xs, width))
+    }
+}
+
+/// Rows of variables for each of `count` known values, from columns or rows of known xs.
+fn observations(xs: Matrix, count: usize, by_rows: bool) -> Result<Matrix, ErrorKind> {
+    let (rows, columns) = (xs.len(), xs.first().map_or(0, |r| r.len()));
+    if rows * columns == count && (rows == 1 || columns == 1) {
+        return Ok(xs.into_iter().flatten().map(|x| vec![x]).collect());
+    }
+    match by_rows {
+        false if rows == count => Ok(xs),
+        true if columns == count => Ok(transpose(&xs)),
+        _ => Err(ErrorKind::Ref),
+    }
+}
+
+/// Least squares fit of `y = m1*x1 + ... + mk*xk + b`, with `b` zero without a constant.
+struct Fit {
+    slopes: Vec<f64>,
+    intercept: f64,
+    /// Inverse of the normal equations, scaling the variance of the coefficients.
+    inverse: Matrix,
+    constant: bool,
+}
+
+impl Fit {
+    fn of(known: &Known, constant: bool) -> Result<Self, ErrorKind> {
+        let design: Matrix = known.xs.iter()
+            .map(|x| x.iter().copied().chain(if constant { Some(1.0) } else { None }).collect())
+            .collect();
+        let transposed = transpose(&design);
+        let inverse = inverse(&multiply(&transposed, &design)?)?;
+        let ys: Matrix = known.ys.iter().map(|y| vec![*y]).collect();
+        let mut coefficients: Vec<f64> = multiply(&multiply(&inverse, &transposed)?, &ys)?.into_iter().flatten().collect();
+        let intercept = if constant { coefficients.pop().unwrap_or(0.0) } else { 0.0 };
+        Ok(Fit { slopes: coefficients, intercept, inverse, constant })
+    }
+
+    fn predict(&self, x: &[f64]) -> f64 {
+        self.slopes.iter().zip(x).map(|(m, x)| m * x).sum::<f64>() + self.intercept
+    }
+
+    /// Rows of `LINEST`: coefficients, their standard errors, r² and the standard error of y,
+    /// the F statistic and degrees of freedom, and the regression and residual sums of squares.
+    fn statistics(&self, known: &Known) -> Vec<Vec<Value>> {
+        let count = known.ys.len() as f64;
+        let parameters = self.inverse.len() as f64;
+        let freedom = count - parameters;
+        let residual: f64 = known.ys.iter().zip(&known.xs).map(|(y, x)| (y - self.predict(x)).powi(2)).sum();
+        let mean = if self.constant { known.ys.iter().sum::<f64>() / count } else { 0.0 };
+        let total: f64 = known.ys.iter().map(|y| (y - mean).powi(2)).sum();
+        let regression = total - residual;
+        let variance = residual / freedom;
+
+        let width = self.slopes.len() + 1;
+        let padded = |row: Vec<Value>| {
+            let mut row = row;
+            row.resize(width, Value::Error(ErrorKind::NA));
+            row
+        };
+        let mut errors: Vec<Value> = (0..self.slopes.len()).rev().map(|i| number((variance * self.inverse[i][i]).sqrt())).collect();
+        errors.push(match self.constant {
+            true => number((variance * self.inverse[self.slopes.len()][self.slopes.len()]).sqrt()),
+            false => Value::Error(ErrorKind::NA),
+        });
+        vec![
+            self.coefficients(),
+            errors,
+            padded(vec![number(regression / total), number(variance.sqrt())]),
+            padded(vec![number((regression / self.slopes.len() as f64) / variance), number(freedom)]),
+            padded(vec![number(regression), number(residual)]),
+        ]
+    }
+
+    /// Slopes from the last variable to the first, then the intercept, as `LINEST` lists them.
+    fn coefficients(&self) -> Vec<Value> {
+        self.slopes.iter().rev().chain(Some(&self.intercept)).map(|n| number(*n)).collect()
+    }
+}
+
+fn flag<T: Engine>(argument: Option<&Expression>, engine: &T) -> Result<bool, ErrorKind> {
+    argument.map_or(Ok(true), |a| holds(engine.eval(a)))
+}
+
+/// `LINEST(known ys, [known xs], [constant], [statistics])` spills the coefficients of the
+/// least squares line through the known values, and with statistics four more rows about the fit.
+pub struct Linest;
+impl<T: Engine> Operator<T> for Linest {
+    fn name(&self) -> &'static str {
+        "LINEST"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        let linest = || {
+            if arguments.is_empty() || arguments.len() > 4 {
+                return Err(ErrorKind::Value);
+            }
+            let known = Known::of(&arguments[0], arguments.get(1), engine)?;
+            let fit = Fit::of(&known, flag(arguments.get(2), engine)?)?;
+            Ok(match arguments.get(3).map(|a| holds(engine.eval(a))).transpose()? {
+                Some(true) => fit.statistics(&known),
+                _ => vec![fit.coefficients()],
+            })
+        };
+        Some(spilled(linest()))
+    }
+}
+
+/// Values on the least squares fit of known values, for `TREND` and `GROWTH`.
+fn trend<T: Engine>(arguments: &[Expression], engine: &T, exponential: bool) -> Result<Vec<Vec<Value>>, ErrorKind> {
+    if arguments.is_empty() || arguments.len() > 4 {
+        return Err(ErrorKind::Value);
+    }
+    let mut known = Known::of(&arguments[0], arguments.get(1), engine)?;
+    if exponential {
+        if known.ys.iter().any(|y| *y <= 0.0) {
+            return Err(ErrorKind::Num);
+        }
+        known.ys = known.ys.iter().map(|y| y.ln()).collect();
+    }
+    let fit = Fit::of(&known, flag(arguments.get(3), engine)?)?;
+    let (xs, width) = known.new_xs(arguments.get(2), engine)?;
+    let predicted: Vec<f64> = xs.iter()
+        .map(|x| fit.predict(x))
+        .map(|y| if exponential { y.exp() } else { y })
+        .collect();
+    Ok(values(predicted.chunks(width.max(1)).map(|row| row.to_vec()).collect()))
+}
+
+/// `TREND(known ys, [known xs], [new xs], [constant])` spills the values of the least squares
+/// line at new xs, the known xs by default.
+pub struct Trend;
+impl<T: Engine> Operator<T> for Trend {
+    fn name(&self) -> &'static str {
+        "TREND"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(trend(arguments, engine, false)))
+    }
+}
+
+/// `GROWTH(known ys, [known xs], [new xs], [constant])` spills the values of the exponential
+/// curve `y = b*m^x` fitting the known values, at new xs.
+pub struct Growth;
+impl<T: Engine> Operator<T> for Growth {
+    fn name(&self) -> &'static str {
+        "GROWTH"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        corner(self.spill(arguments, engine))
+    }
+
+    fn spill(&self, arguments: &[Expression], engine: &T) -> Option<Vec<Vec<Value>>> {
+        Some(spilled(trend(arguments, engine, true)))
+    }
+}
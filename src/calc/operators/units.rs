@@ -0,0 +1,44 @@
+use crate::calc::engine::{Engine, ErrorKind, Expression, Number, Operator, Unit, Value};
+
+fn unit<T: Engine>(argument: &Expression, engine: &T) -> Result<Unit, ErrorKind> {
+    match engine.eval(argument) {
+        Value::Text(t) => Unit::parse(&t).ok_or(ErrorKind::NA),
+        Value::Error(e) => Err(e),
+        _ => Err(ErrorKind::NA),
+    }
+}
+
+/// `CONVERT(value, from, to)` converts a number from one unit to another, like Excel. A
+/// quantity is converted from its own unit, which must have the dimension of `from`, and
+/// stays a quantity. Unknown units are `#N/A` and units of different dimensions `#UNIT!`.
+pub struct Convert;
+impl<T: Engine> Operator<T> for Convert {
+    fn name(&self) -> &'static str {
+        "CONVERT"
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        let convert = || {
+            let (value, from, to) = match arguments.as_slice() {
+                [value, from, to] => (engine.eval(value), unit(from, engine)?, unit(to, engine)?),
+                _ => return Err(ErrorKind::Value),
+            };
+            if !from.compatible(&to) {
+                return Err(ErrorKind::Unit);
+            }
+            let value = match value {
+                Value::Text(t) => Value::parse_quantity(&t, |n| Number::parse(n, false)).unwrap_or(Value::Text(t)),
+                value => value,
+            };
+            match value {
+                Value::Quantity(n, unit) if unit.compatible(&from) => Ok(Value::Quantity(unit.convert(n, &to)?, to)),
+                Value::Quantity(..) => Err(ErrorKind::Unit),
+                value => Ok(Value::Number(from.convert(value.as_number()?, &to)?)),
+            }
+        };
+        match convert() {
+            Ok(value) => value,
+            Err(e) => Value::Error(e),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::error::Error;
+use crate::calc::Table;
+use crate::calc::engine::{Cell, Value};
+use crate::calc::format::parse_columns;
+use crate::calc::operators::{Aggregate, pivot, Record};
+use crate::calc::scenario::results;
+
+/// A summary of the computed sheet, such as `rows=B,cols=C,values=sum(D)`. Rows are grouped
+/// by one or more key columns and, with `cols`, spread over the distinct values of a column.
+pub struct Pivot {
+    rows: Vec<usize>,
+    columns: Option<usize>,
+    values: Vec<usize>,
+    aggregate: Aggregate,
+}
+
+impl Pivot {
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        // Commas separate settings and the columns of a setting alike, as in rows=A,B,cols=C.
+        let mut settings: Vec<(String, String)> = Vec::new();
+        for part in text.split(',') {
+            match (part.split_once('='), settings.last_mut()) {
+                (Some((key, value)), _) => settings.push((key.trim().to_lowercase(), value.trim().to_string())),
+                (None, Some((_, value))) => *value = format!("{},{}", value, part.trim()),
+                (None, None) => return Err(format!("{} is not of the form rows=COLUMNS", part).into()),
+            }
+        }
+
+        let (mut rows, mut columns, mut values) = (None, None, None);
+        for (key, value) in settings {
+            match key.as_str() {
+                "rows" => rows = Some(parse_columns(&value)?),
+                "cols" => columns = match parse_columns(&value)?.as_slice() {
+                    [column] => Some(*column),
+                    _ => return Err("cols takes a single column".into()),
+                },
+                "values" => {
+                    let (name, inner) = value.strip_suffix(')').and_then(|v| v.split_once('('))
+                        .ok_or_else(|| format!("{} is not of the form sum(COLUMNS)", value))?;
+                    let aggregate = Aggregate::parse(name).ok_or_else(|| format!("unknown aggregate {}", name))?;
+                    values = Some((parse_columns(inner)?, aggregate));
+                },
+                k => return Err(format!("unknown pivot setting {}", k).into()),
+            }
+        }
+
+        let rows = rows.ok_or("a pivot needs rows=COLUMNS")?;
+        let (values, aggregate) = values.ok_or("a pivot needs values=AGGREGATE(COLUMNS)")?;
+        if columns.is_some() && values.len() != 1 {
+            return Err("a pivot with cols takes a single values column".into());
+        }
+        Ok(Pivot { rows, columns, values, aggregate })
+    }
+
+    /// Summarizes the rows below the header, naming columns after the header row.
+    pub fn table(&self, table: &Table) -> Table {
+        let rows = table.rows();
+        let value = |row: &[&Cell], column: usize| row.iter()
+            .find(|c| c.position.column() == column)
+            .and_then(|c| c.value.clone())
+            .unwrap_or(Value::Empty);
+        let pick = |row: &[&Cell], columns: &[usize]| columns.iter().map(|c| value(row, *c)).collect::<Vec<Value>>();
+
+        let headers = rows.first().map(|header| (pick(header, &self.rows), pick(header, &self.values)));
+        let records: Vec<Record> = rows.iter().skip(1)
+            .map(|row| Record {
+                keys: pick(row, &self.rows),
+                column: self.columns.map(|c| value(row, c)),
+                values: pick(row, &self.values),
+            })
+            .filter(|r| r.keys.iter().any(|k| !matches!(k, Value::Empty)))
+            .collect();
+
+        let mut summary = pivot(&records, self.aggregate, headers, self.columns.is_some()).into_iter();
+        let locale = table.locale();
+        let header = summary.next().unwrap_or_default().iter().map(|v| locale.format(v)).collect();
+        let rows = summary.map(|row| row.into_iter().map(|v| (locale.format(&v), v)).collect()).collect();
+        results(locale, header, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{Calc, CsvPrinter};
+    use crate::calc::pivot::Pivot;
+    use crate::calc::engine::{CellRef, Engine};
+    use crate::framework::{Loader, Printer};
+
+    const SALES: &str = "Category,Month,Amount\nFood,Jan,10\nRent,Jan,500\nFood,Feb,15\nFood,Jan,5\nTravel,Feb,120\n,,";
+
+    fn print(calc: Calc) -> String {
+        String::from_utf8(CsvPrinter::new().print(calc.load().unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn groups_rows() {
+        let calc = Calc::from_string(SALES.to_string()).with_pivot(Some("rows=A,values=sum(C)".to_string()));
+        assert_eq!("Category,Amount\nFood,30.00\nRent,500.00\nTravel,120.00\nTotal,650.00", print(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_pivot(Some("rows=A,B,values=count(C)".to_string()));
+        assert_eq!("Category,Month,Amount\nFood,Feb,1.00\nFood,Jan,2.00\nRent,Jan,1.00\nTravel,Feb,1.00\nTotal,,5.00", print(calc));
+    }
+
+    #[test]
+    fn pivots_columns() {
+        let calc = Calc::from_string(SALES.to_string()).with_pivot(Some("rows=A,cols=B,values=average(C)".to_string()));
+        assert_eq!("Category,Feb,Jan,Total\nFood,15.00,7.50,10.00\nRent,,500.00,500.00\nTravel,120.00,,120.00\nTotal,67.50,171.67,130.00", print(calc));
+    }
+
+    #[test]
+    fn rejects_bad_pivots() {
+        assert!(Pivot::parse("values=sum(C)").is_err());
+        assert!(Pivot::parse("rows=A,values=median(C)").is_err());
+        assert!(Pivot::parse("rows=A,cols=B,values=sum(C:D)").is_err());
+        assert!(Pivot::parse("rows=A,by=B,values=sum(C)").is_err());
+    }
+
+    #[test]
+    fn spills_formulas() {
+        let source = format!("{}\n\"=GROUPBY(A2:A7,C2:C7,\"\"sum\"\")\"", SALES);
+        let csv = print(Calc::from_string(source));
+        assert!(csv.ends_with("\nFood,30.00\nRent,500.00\nTravel,120.00\nTotal,650.00"), "{}", csv);
+
+        let pivot = "\"=PIVOTBY(A1:A6,B1:B6,C1:C6,\"\"SUM\"\",TRUE)\"";
+        let csv = print(Calc::from_string(format!("{}\n{},,,,=D12", SALES, pivot)));
+        assert!(csv.ends_with("\nCategory,Feb,Jan,Total,650.00\nFood,15.00,15.00,30.00\nRent,,500.00,500.00\nTravel,120.00,,120.00\nTotal,135.00,515.00,650.00"), "{}", csv);
+
+        let blocked = print(Calc::from_string(format!("{}\n\"=GROUPBY(A2:A7,C2:C7,\"\"SUM\"\")\"\nx", SALES)));
+        assert!(blocked.ends_with("\n#SPILL!\nx"), "{}", blocked);
+        assert!(print(Calc::from_string("1,\"=GROUPBY(A1,A1,\"\"MEDIAN\"\")\"".to_string())).ends_with("#VALUE!"));
+    }
+
+    #[test]
+    fn recomputes_spilled_cells() {
+        let mut engine = Calc::from_string("1,=TRANSPOSE(A1:A2),,=C1*10\n2".to_string()).engine().unwrap();
+        engine.set_content(CellRef::parse("A2"), "7".to_string());
+        assert_eq!("7.00", engine.value_at(&CellRef::parse("C1")).to_string());
+        assert_eq!("70.00", engine.value_at(&CellRef::parse("D1")).to_string());
+    }
+}
@@ -0,0 +1,273 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use wasmi::core::ValueType;
+use wasmi::{Config, Engine as WasmEngine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+use crate::calc::engine::{CellRef, Engine, ErrorKind, Expression, Operator, Value};
+
+const ALLOC: &str = "doffice_alloc";
+const MEMORY: &str = "memory";
+const FUEL_PER_CALL: u64 = 10_000_000;
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+const TAG_EMPTY: u8 = 0;
+const TAG_NUMBER: u8 = 1;
+const TAG_TEXT: u8 = 2;
+const TAG_BOOL: u8 = 3;
+const TAG_ERROR: u8 = 4;
+
+struct PluginInstance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+pub struct PluginFunction {
+    name: String,
+    func: TypedFunc<(i32, i32), i64>,
+    instance: Rc<RefCell<PluginInstance>>,
+}
+
+/// Plugins the command line loads for every sheet, from `doffice/plugins` in the user's
+/// configuration directory when it exists.
+pub fn default_plugins() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config.join("doffice").join("plugins")).filter(|path| path.is_dir())
+}
+
+pub fn load_plugins(path: &Path) -> Result<Vec<PluginFunction>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return load_plugin(path);
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?.path();
+        if entry.extension().is_some_and(|e| e == "wasm") {
+            paths.push(entry);
+        }
+    }
+    paths.sort();
+
+    let mut functions = Vec::new();
+    for path in paths {
+        functions.append(&mut load_plugin(&path)?);
+    }
+    Ok(functions)
+}
+
+fn load_plugin(path: &Path) -> Result<Vec<PluginFunction>, Box<dyn Error>> {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = WasmEngine::new(&config);
+
+    let module = Module::new(&engine, File::open(path)?)?;
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(MEMORY_LIMIT)
+        .instances(1)
+        .build();
+
+    let mut store = Store::new(&engine, limits);
+    store.limiter(|limits| limits);
+    store.add_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+
+    let linker = <Linker<StoreLimits>>::new(&engine);
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+
+    let plugin_error = |message: &str| format!("plugin {}: {}", path.display(), message);
+    let memory = instance.get_memory(&store, MEMORY)
+        .ok_or_else(|| plugin_error("missing exported memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&store, ALLOC)
+        .map_err(|_| plugin_error("missing exported doffice_alloc(i32) -> i32"))?;
+
+    let mut functions = Vec::new();
+    for export in module.exports() {
+        let is_operator = match export.ty().func() {
+            Some(ty) => ty.params() == [ValueType::I32, ValueType::I32] && ty.results() == [ValueType::I64],
+            None => false
+        };
+
+        if is_operator && export.name() != ALLOC {
+            let func = instance.get_typed_func::<(i32, i32), i64>(&store, export.name())?;
+            functions.push((export.name().to_uppercase(), func));
+        }
+    }
+
+    let instance = Rc::new(RefCell::new(PluginInstance { store, memory, alloc }));
+    Ok(functions.into_iter()
+        .map(|(name, func)| PluginFunction { name, func, instance: instance.clone() })
+        .collect())
+}
+
+impl PluginFunction {
+    fn invoke(&self, arguments: &[Value]) -> Result<Value, Box<dyn Error>> {
+        let mut input = Vec::new();
+        for argument in arguments {
+            encode(argument, &mut input);
+        }
+
+        let mut plugin = self.instance.borrow_mut();
+        let remaining = plugin.store.consume_fuel(0).map_err(|e| e.to_string())?;
+        plugin.store.add_fuel(FUEL_PER_CALL.saturating_sub(remaining)).map_err(|e| e.to_string())?;
+
+        let alloc = plugin.alloc;
+        let ptr = alloc.call(&mut plugin.store, input.len() as i32)?;
+        let memory = plugin.memory;
+        memory.write(&mut plugin.store, ptr as u32 as usize, &input).map_err(|e| e.to_string())?;
+
+        let packed = self.func.call(&mut plugin.store, (ptr, input.len() as i32))? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+
+        let output = memory.data(&plugin.store).get(out_ptr..out_ptr.saturating_add(out_len))
+            .ok_or("plugin result out of bounds")?;
+        decode(output).ok_or_else(|| "malformed plugin result".into())
+    }
+}
+
+impl<T: Engine> Operator<T> for PluginFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, arguments: &Vec<Expression>, engine: &T) -> Value {
+        let mut values = Vec::new();
+        for arg in arguments {
+            if let Expression::Range(a, b) = arg {
+                for cell in CellRef::range(a, b) {
+                    values.push(engine.value_at(&cell));
+                }
+            } else {
+                values.push(engine.eval(arg));
+            }
+        }
+
+        self.invoke(&values).unwrap_or(Value::Error(ErrorKind::Value))
+    }
+}
+
+fn encode(value: &Value, buffer: &mut Vec<u8>) {
+    match value {
+        Value::Empty => buffer.push(TAG_EMPTY),
+        Value::Number(n) => {
+            buffer.push(TAG_NUMBER);
+            buffer.extend_from_slice(&n.to_f64().to_le_bytes());
+        }
+        Value::Quantity(..) => encode(&Value::Text(value.to_string()), buffer),
+        Value::Text(t) => {
+            buffer.push(TAG_TEXT);
+            buffer.extend_from_slice(&(t.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(t.as_bytes());
+        }
+        Value::Bool(b) => {
+            buffer.push(TAG_BOOL);
+            buffer.push(*b as u8);
+        }
+        Value::Error(e) => {
+            let name = e.to_string();
+            buffer.push(TAG_ERROR);
+            buffer.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(name.as_bytes());
+        }
+    }
+}
+
+fn decode(buffer: &[u8]) -> Option<Value> {
+    let (tag, payload) = buffer.split_first()?;
+    match *tag {
+        TAG_EMPTY => Some(Value::Empty),
+        TAG_NUMBER => Some(Value::Number(f64::from_le_bytes(payload.get(..8)?.try_into().ok()?).into())),
+        TAG_BOOL => Some(Value::Bool(*payload.first()? != 0)),
+        TAG_TEXT => Some(Value::Text(decode_text(payload)?)),
+        TAG_ERROR => {
+            // Plugins may name errors with or without the leading `#`.
+            let name = decode_text(payload)?;
+            let error = ErrorKind::parse(&name).or_else(|| ErrorKind::parse(&format!("#{}", name)));
+            Some(Value::Error(error.unwrap_or(ErrorKind::Value)))
+        },
+        _ => None
+    }
+}
+
+fn decode_text(payload: &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(payload.get(..4)?.try_into().ok()?) as usize;
+    String::from_utf8(payload.get(4..4 + len)?.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::fs;
+    use crate::calc::Calc;
+    use crate::calc::CsvPrinter;
+    use crate::framework::{Loader, Printer, TempDir};
+
+    // Doubles its first numeric argument, reusing the input buffer for the result, fails
+    // with an error named without its `#` and returns a result outside its memory.
+    const DOUBLE: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "doffice_alloc") (param i32) (result i32) i32.const 16)
+            (func (export "double") (param i32 i32) (result i64)
+                (f64.store (i32.add (local.get 0) (i32.const 1))
+                    (f64.mul (f64.load (i32.add (local.get 0) (i32.const 1))) (f64.const 2)))
+                (i64.or (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32)) (i64.const 9)))
+            (func (export "spin") (param i32 i32) (result i64)
+                (loop (br 0))
+                (i64.const 0))
+            (data (i32.const 64) "\04\06\00\00\00DIV/0!")
+            (func (export "fail") (param i32 i32) (result i64)
+                (i64.const 0x400000000b))
+            (func (export "stray") (param i32 i32) (result i64)
+                (i64.const 0x7fff0000ffffffff)))
+    "#;
+
+    #[test]
+    fn calls_plugin_functions() {
+        assert_eq!("2,4.00", eval("calls", "2,=DOUBLE(A1)"));
+    }
+
+    #[test]
+    fn reads_plugin_errors() {
+        assert_eq!("#DIV/0!,2.00", eval("errors", "=FAIL(),=ERROR.TYPE(A1)"));
+    }
+
+    #[test]
+    fn limits_plugin_fuel() {
+        assert_eq!("2,#VALUE!", eval("fuel", "2,=SPIN(A1)"));
+    }
+
+    #[test]
+    fn rejects_results_out_of_bounds() {
+        assert_eq!("#VALUE!", eval("bounds", "=STRAY()"));
+    }
+
+    #[test]
+    fn rejects_builtin_names() {
+        let sum = r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "doffice_alloc") (param i32) (result i32) i32.const 16)
+                (func (export "sum") (param i32 i32) (result i64) (i64.const 0)))
+        "#;
+        assert!(load("clash", sum, "1").is_err());
+    }
+
+    fn eval(name: &str, input: &str) -> String {
+        load(name, DOUBLE, input).unwrap()
+    }
+
+    fn load(name: &str, module: &str, input: &str) -> Result<String, Box<dyn Error>> {
+        let dir = TempDir::new(&format!("plugins-{}", name));
+        fs::write(dir.0.join("plugin.wasm"), wat::parse_str(module)?)?;
+
+        let calc = Calc::from_string(input.to_string()).with_plugins(vec![dir.0.display().to_string()]);
+        let table = calc.load()?;
+        Ok(String::from_utf8(CsvPrinter::new().print(table)?)?)
+    }
+}
@@ -3,13 +3,14 @@ use crate::calc::engine::{Cell, Delimiter};
 
 pub struct CSVReader<T> {
     reader: Bytes<T>,
+    separator: u8,
     row: usize,
     column: usize
 }
 
 impl<T> CSVReader<T> {
-    pub fn new(reader: Bytes<T>) -> Self {
-        CSVReader{ reader, row: 0, column: 0 }
+    pub fn new(reader: Bytes<T>, separator: u8) -> Self {
+        CSVReader{ reader, separator, row: 0, column: 0 }
     }
 }
 
@@ -19,14 +20,21 @@ impl<T: Read> Iterator for CSVReader<T> {
     fn next(&mut self) -> Option<Self::Item> {
         let mut content = Vec::new();
         let mut escaped = false;
+        let mut closed_quote = false;
         loop {
             match self.reader.next() {
                 Some(res) => {
                     match res {
                         Ok(byte) => {
+                            let after_quote = closed_quote;
+                            closed_quote = false;
                             match byte {
                                 b'"' => {
+                                    if after_quote {
+                                        content.push(byte);
+                                    }
                                     escaped = !escaped;
+                                    closed_quote = !escaped;
                                 }
                                 b'\n' => {
                                     let cell = Cell::new(content, self.row, self.column, Delimiter::NewLine);
@@ -34,7 +42,7 @@ impl<T: Read> Iterator for CSVReader<T> {
                                     self.column = 0;
                                     return Some(cell);
                                 },
-                                b',' => {
+                                b if b == self.separator => {
                                     if escaped {
                                         content.push(byte);
                                     }else{
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use crate::calc::{Calc, TerminalPrinter, XlsxPrinter};
+use crate::calc::engine::{Cell, CellRef, CSVEngine, Delimiter, Engine};
+use crate::framework::Printer;
+
+const HELP: &str = "\
+=FORMULA         evaluate a formula against the sheet
+A1               show the value and formula of a cell
+set A1 CONTENT   set the content of a cell, a value or a =formula
+show             print the computed sheet
+write [FILE]     save the sheet with the cells set, to a .csv or .xlsx FILE or the one read
+help             show this message
+quit             leave without saving
+";
+
+/// Reads commands from `input` to query and edit the sheet of `calc`, which was read from `path`.
+pub fn run_repl(calc: &Calc, path: &str, input: impl BufRead, mut output: impl Write) -> Result<(), Box<dyn Error>> {
+    let mut engine = calc.engine()?;
+    let mut edits = BTreeMap::new();
+    writeln!(output, "{} loaded, type help for commands", path)?;
+
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match command {
+            "" => {},
+            "quit" | "exit" => return Ok(()),
+            "help" => write!(output, "{}", HELP)?,
+            "show" => {
+                let table = calc.table(&engine)?;
+                output.write_all(&TerminalPrinter::new(false, 40).print(table)?)?;
+            },
+            "set" => match rest.split_once(char::is_whitespace) {
+                Some((cell, content)) if CellRef::from_name(cell).is_some() => {
+                    let position = CellRef::parse(cell);
+                    engine.set_content(position, content.trim().to_string());
+                    edits.insert(position, content.trim().to_string());
+                    calc.mark_invalid(&mut engine)?;
+                    writeln!(output, "{}", describe(&engine, calc, position))?;
+                },
+                _ => writeln!(output, "usage: set A1 CONTENT")?,
+            },
+            "write" => {
+                let target = if rest.is_empty() { path } else { rest };
+                let extension = Path::new(target).extension().map(|e| e.to_string_lossy().to_lowercase());
+                match extension.as_deref() {
+                    Some("xlsx") => {
+                        let mut sheet = calc.sheet()?;
+                        for (position, content) in &edits {
+                            sheet.set_content(*position, content.clone());
+                        }
+                        sheet.spill();
+                        fs::write(target, XlsxPrinter::new().print(calc.table(&sheet)?)?)?;
+                    },
+                    Some("csv" | "tsv" | "txt") => fs::write(target, source(calc, &edits)?)?,
+                    _ if target == path => fs::write(target, source(calc, &edits)?)?,
+                    _ => {
+                        writeln!(output, "cannot write {}, use a .csv or .xlsx file", target)?;
+                        write!(output, "> ")?;
+                        output.flush()?;
+                        continue;
+                    },
+                }
+                writeln!(output, "saved {}", target)?;
+            },
+            cell if rest.is_empty() && CellRef::from_name(cell).is_some() => {
+                writeln!(output, "{}", describe(&engine, calc, CellRef::parse(cell)))?;
+            },
+            _ => {
+                let formula = line.strip_prefix('=').unwrap_or(line);
+                writeln!(output, "{}", calc.locale.format(&engine.evaluate(formula)))?;
+            },
+        }
+
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn describe(engine: &CSVEngine, calc: &Calc, position: CellRef) -> String {
+    let value = calc.locale.format(&engine.value_at(&position));
+    match engine.content(&position) {
+        Some(content) if content.starts_with('=') => format!("{} {}", value, content),
+        _ => value,
+    }
+}
+
+/// The sheet as read with the cells set in the session, as CSV. The text of a CSV sheet is
+/// kept as written apart from the fields set.
+fn source(calc: &Calc, edits: &BTreeMap<CellRef, String>) -> Result<String, Box<dyn Error>> {
+    let separator = calc.locale.separator;
+    let text = match calc.csv()? {
+        Some(text) => text,
+        None => {
+            let mut sheet = calc.sheet()?;
+            for (position, content) in edits {
+                sheet.set_content(*position, content.clone());
+            }
+            let contents = sheet.source().into_iter().map(|c| (c.position, c.content)).collect();
+            return Ok(Cell::grid(contents).iter().map(|c| c.csv(separator).to_string()).collect());
+        },
+    };
+
+    let body = text.strip_suffix('\n').unwrap_or(&text);
+    let mut rows: Vec<Vec<String>> = match body.is_empty() {
+        true => Vec::new(),
+        false => body.split('\n').map(|line| fields(line, separator)).collect(),
+    };
+    for (position, content) in edits {
+        if rows.len() <= position.row() {
+            rows.resize(position.row() + 1, Vec::new());
+        }
+        let row = &mut rows[position.row()];
+        if row.len() <= position.column() {
+            row.resize(position.column() + 1, String::new());
+        }
+        let cell = Cell::new(content.clone().into_bytes(), position.row(), position.column(), Delimiter::EOF);
+        row[position.column()] = cell.csv(separator).to_string();
+    }
+
+    let mut edited = rows.iter().map(|row| row.join(&separator.to_string())).collect::<Vec<String>>().join("\n");
+    if body.len() < text.len() {
+        edited.push('\n');
+    }
+    Ok(edited)
+}
+
+/// Splits a line of CSV into its fields as written, quotes included.
+fn fields(line: &str, separator: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                fields.push(String::new());
+                continue;
+            },
+            _ => {},
+        }
+        fields.last_mut().unwrap().push(c);
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::File;
+    use std::io::Cursor;
+    use crate::calc::{Calc, Locale};
+    use crate::calc::repl::run_repl;
+    use crate::calc::xlsx::read_xlsx;
+    use crate::framework::TempDir;
+
+    fn run(calc: Calc, path: &str, input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run_repl(&calc, path, Cursor::new(input), &mut output).unwrap();
+        String::from_utf8(output).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn edits_and_saves_sheets() {
+        let dir = TempDir::new("repl-saves");
+        let path = dir.0.join("sheet.csv");
+        fs::write(&path, "1,2,=A1+B1").unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let lines = run(Calc::from_file(path.clone()), &path, "C1\n=SUM(A1:C1)*2\nset B1 5\nset A2 =C1*10\nwrite\nquit\nB1\n");
+        assert_eq!("> 3.00 =A1+B1", lines[1]);
+        assert_eq!("> 12.00", lines[2]);
+        assert_eq!("> 5.00", lines[3]);
+        assert_eq!("> 60.00 =C1*10", lines[4]);
+        assert_eq!(format!("> saved {}", path), lines[5]);
+        assert_eq!(7, lines.len(), "{:?}", lines);
+        assert_eq!("1,5,=A1+B1\n=C1*10", fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn saves_only_the_cells_set() {
+        let dir = TempDir::new("repl-edits");
+        let path = dir.0.join("sheet.csv");
+        let source = "1,\"a, b\"\n=TRANSPOSE(A1:B1),\n";
+        fs::write(&path, source).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let workbook = dir.0.join("sheet.xlsx");
+        let calc = Calc::from_file(path.clone()).with_overrides(vec!["A1=99".to_string()]);
+        let lines = run(calc, &path, &format!("write\nwrite sheet.ods\nwrite {}\nquit\n", workbook.display()));
+        assert_eq!("> cannot write sheet.ods, use a .csv or .xlsx file", lines[2]);
+        assert_eq!(source, fs::read_to_string(&path).unwrap());
+        let cells = read_xlsx(File::open(&workbook).unwrap(), None, Locale::default()).unwrap();
+        assert_eq!("1", cells[0].content);
+
+        let calc = Calc::from_file(path.clone());
+        run(calc, &path, "set B1 \"c\"\nwrite\nquit\n");
+        assert_eq!("1,\"\"\"c\"\"\"\n=TRANSPOSE(A1:B1),\n", fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn validates_cells_set() {
+        let dir = TempDir::new("repl-schema");
+        let schema = dir.0.join("schema.txt");
+        fs::write(&schema, "A number").unwrap();
+        let calc = Calc::from_string("Price\ntwo".to_string()).with_validation(Some(schema.to_str().unwrap().to_string()));
+
+        let lines = run(calc, "sheet.csv", "A2\nset A2 2\nset A2 three\nquit\n");
+        assert_eq!(vec!["sheet.csv loaded, type help for commands", "> #INVALID!", "> 2.00", "> #INVALID!", "> "], lines);
+    }
+}
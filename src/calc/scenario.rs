@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::error::Error;
+use rust_decimal::Decimal;
+use crate::calc::{Calc, Locale, Table};
+use crate::calc::engine::{Cell, CellRef, CSVEngine, Engine, ErrorKind, Number, Value};
+use crate::calc::operators::goal_seek;
+
+/// Reads an override such as `B2=0.07` or `B2==A1*2` into the cell and its new content.
+pub fn parse_assignment(text: &str) -> Result<(CellRef, String), Box<dyn Error>> {
+    let (cell, content) = text.split_once('=').ok_or_else(|| format!("{} is not of the form CELL=VALUE", text))?;
+    let cell = CellRef::from_name(cell).ok_or_else(|| format!("{} is not a cell reference", cell))?;
+    Ok((cell, content.to_string()))
+}
+
+/// Sets `input` to the value that makes the target of a goal such as `F20=0` reach it.
+pub fn seek(engine: &mut CSVEngine, goal: &str, input: &str, locale: Locale) -> Result<(), Box<dyn Error>> {
+    let (target, value) = parse_assignment(goal)?;
+    let value = locale.parse_number(&value, false).ok_or_else(|| format!("{} is not a number", value))?;
+    let input_cell = CellRef::from_name(input).ok_or_else(|| format!("{} is not a cell reference", input))?;
+    let x = goal_seek(engine, &target, value.to_f64(), &input_cell)
+        .map_err(|_| format!("no value of {} makes {} equal {}", input, target, locale.format(&Value::Number(value))))?;
+    engine.set_content(input_cell, locale.localize_formula(&x.to_string()));
+    Ok(())
+}
+
+/// Reads the cells collected by a sweep or simulation.
+pub fn parse_cells(cells: &[String]) -> Result<Vec<CellRef>, Box<dyn Error>> {
+    cells.iter()
+        .map(|c| CellRef::from_name(c).ok_or_else(|| format!("{} is not a cell reference", c).into()))
+        .collect()
+}
+
+/// Recomputes a sheet once per value of an input cell, collecting the values of result cells.
+pub struct Sweep {
+    input: CellRef,
+    values: Vec<Decimal>,
+}
+
+impl Sweep {
+    /// Reads a sweep such as `B2=0.05..0.10:0.01`, the step defaulting to 1.
+    pub fn parse(text: &str, locale: Locale) -> Result<Self, Box<dyn Error>> {
+        let (input, range) = parse_assignment(text)?;
+        let (range, step) = range.split_once(':').unwrap_or((&range, "1"));
+        let (start, end) = range.split_once("..").ok_or_else(|| format!("{} is not of the form CELL=START..END:STEP", text))?;
+        let number = |n: &str| locale.parse_number(n, true)
+            .and_then(|n| n.to_decimal().ok())
+            .ok_or_else(|| format!("{} is not a number", n));
+        let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+        if step <= Decimal::ZERO {
+            return Err(format!("the step of {} must be positive", text).into());
+        }
+
+        let mut values = Vec::new();
+        let mut value = start;
+        while value <= end {
+            if values.len() == 10000 {
+                return Err(format!("{} has more than 10000 values", text).into());
+            }
+            values.push(value.normalize());
+            value += step;
+        }
+        Ok(Sweep { input, values })
+    }
+
+    /// One row per input value, with the input followed by every collected cell.
+    pub fn table(&self, calc: &Calc, collect: &[CellRef]) -> Result<Table, Box<dyn Error>> {
+        let mut engine = calc.engine()?;
+        let header = [self.input].iter().chain(collect).map(|c| c.to_string()).collect();
+        let mut rows = Vec::new();
+        for input in &self.values {
+            let content = calc.locale.localize_formula(&input.to_string());
+            let number = if calc.decimal { Number::Decimal(*input) } else { Number::Float(Number::Decimal(*input).to_f64()) };
+            engine.set_content(self.input, content.clone());
+
+            let mut row = vec![(content, Value::Number(number))];
+            for cell in collect {
+                let value = engine.value_at(cell);
+                row.push((calc.locale.format(&value), value));
+            }
+            rows.push(row);
+        }
+        Ok(results(calc.locale, header, rows))
+    }
+}
+
+const PERCENTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// Recomputes a sheet with new random draws a number of times, and summarizes the
+/// distribution of the numbers each collected cell took.
+pub fn simulate(calc: &Calc, iterations: usize, collect: &[CellRef]) -> Result<Table, Box<dyn Error>> {
+    let mut engine = calc.engine()?;
+    let mut samples = vec![Vec::with_capacity(iterations); collect.len()];
+    for _ in 0..iterations {
+        engine.redraw();
+        for (cell, samples) in collect.iter().zip(&mut samples) {
+            if let Value::Number(n) = engine.value_at(cell) {
+                samples.push(n.to_f64());
+            }
+        }
+    }
+
+    let mut header: Vec<String> = ["Cell", "Samples", "Mean", "StDev", "Min"].iter().map(|h| h.to_string()).collect();
+    header.extend(PERCENTILES.iter().map(|p| format!("P{}", p * 100.0)));
+    header.push("Max".to_string());
+
+    let rows = collect.iter().zip(samples)
+        .map(|(cell, mut samples)| {
+            samples.sort_by(|a, b| a.total_cmp(b));
+            let mut stats = Vec::new();
+            if samples.is_empty() {
+                stats.resize(header.len() - 2, Value::Error(ErrorKind::Div0));
+            } else {
+                let n = samples.len() as f64;
+                let mean = samples.iter().sum::<f64>() / n;
+                let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+                stats.push(Value::Number(Number::Float(mean)));
+                stats.push(if samples.len() > 1 { Value::Number(Number::Float(variance.sqrt())) } else { Value::Error(ErrorKind::Div0) });
+                stats.push(Value::Number(Number::Float(samples[0])));
+                stats.extend(PERCENTILES.iter().map(|p| Value::Number(Number::Float(percentile(&samples, *p)))));
+                stats.push(Value::Number(Number::Float(samples[samples.len() - 1])));
+            }
+
+            let mut row = vec![
+                (cell.to_string(), Value::Text(cell.to_string())),
+                (samples.len().to_string(), Value::Number(Number::Float(samples.len() as f64))),
+            ];
+            row.extend(stats.into_iter().map(|v| (calc.locale.format(&v), v)));
+            row
+        })
+        .collect();
+    Ok(results(calc.locale, header, rows))
+}
+
+/// Percentile of sorted samples, interpolating between neighbours like `PERCENTILE.INC`.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+/// A table of a text header followed by rows of content and value.
+pub fn results(locale: Locale, header: Vec<String>, rows: Vec<Vec<(String, Value)>>) -> Table {
+    let mut contents = HashMap::new();
+    let mut values = HashMap::new();
+    for (column, title) in header.into_iter().enumerate() {
+        values.insert(CellRef::new(0, column), Value::Text(title.clone()));
+        contents.insert(CellRef::new(0, column), title);
+    }
+    for (row, cells) in rows.into_iter().enumerate() {
+        for (column, (content, value)) in cells.into_iter().enumerate() {
+            contents.insert(CellRef::new(row + 1, column), content);
+            values.insert(CellRef::new(row + 1, column), value);
+        }
+    }
+
+    let cells = Cell::grid(contents).into_iter()
+        .map(|mut cell| {
+            cell.value = values.remove(&cell.position);
+            cell
+        })
+        .collect();
+    Table { cells, locale, formats: HashMap::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{Calc, CsvPrinter, Locale};
+    use crate::calc::scenario::{parse_assignment, Sweep};
+    use crate::framework::{Loader, Printer};
+
+    #[test]
+    fn overrides_cells() {
+        let calc = Calc::from_string("0.05,100,=A1*B1".to_string())
+            .with_overrides(vec!["A1=0.07".to_string(), "B1==10*10*2".to_string()]);
+        let csv = CsvPrinter::new().print(calc.load().unwrap()).unwrap();
+        assert_eq!("0.07,200.00,14.00", String::from_utf8(csv).unwrap());
+        assert!(parse_assignment("A1").is_err());
+        assert!(parse_assignment("foo=1").is_err());
+    }
+
+    #[test]
+    fn seeks_goals() {
+        let calc = Calc::from_string("Rate,Payment\n0.1,=1000*A2-120".to_string())
+            .with_goal_seek(Some("B2=30".to_string()), Some("A2".to_string()));
+        let csv = CsvPrinter::new().print(calc.load().unwrap()).unwrap();
+        assert_eq!("Rate,Payment\n0.15,30.00", String::from_utf8(csv).unwrap());
+
+        let calc = Calc::from_string("1,=A1*A1+1".to_string())
+            .with_goal_seek(Some("B1=0".to_string()), Some("A1".to_string()));
+        assert!(calc.load().is_err());
+    }
+
+    #[test]
+    fn sweeps_inputs() {
+        let calc = Calc::from_string("0,100,=A1*B1,=C1>6".to_string())
+            .with_sweep(Some("A1=0.05..0.07:0.01".to_string()))
+            .with_collect(vec!["C1".to_string(), "D1".to_string()]);
+        let csv = CsvPrinter::new().print(calc.load().unwrap()).unwrap();
+        assert_eq!("A1,C1,D1\n0.05,5.00,FALSE\n0.06,6.00,FALSE\n0.07,7.00,TRUE", String::from_utf8(csv).unwrap());
+
+        assert!(Sweep::parse("A1=1..2:0", Locale::default()).is_err());
+        assert!(Sweep::parse("A1=1", Locale::default()).is_err());
+    }
+
+    #[test]
+    fn sweeps_in_locales() {
+        let de: Locale = "de".parse().unwrap();
+        let calc = Calc::from_string("0;=A1*2".to_string())
+            .with_locale(de)
+            .with_sweep(Some("A1=0,5..1".to_string()))
+            .with_collect(vec!["B1".to_string()]);
+        let csv = CsvPrinter::new().print(calc.load().unwrap()).unwrap();
+        assert_eq!("A1;B1\n0,5;1,00", String::from_utf8(csv).unwrap());
+    }
+
+    #[test]
+    fn simulates_draws() {
+        let calc = Calc::from_string("=RAND(),=A1*10,=A1+B1,x".to_string())
+            .with_seed(Some(7))
+            .with_simulation(Some(2000))
+            .with_collect(vec!["B1".to_string(), "C1".to_string(), "D1".to_string()]);
+        let table = calc.load().unwrap();
+        let rows = table.rows();
+        assert_eq!("Cell,Samples,Mean,StDev,Min,P5,P25,P50,P75,P95,Max", rows[0].iter().map(|c| c.content.as_str()).collect::<Vec<&str>>().join(","));
+
+        let stat = |row: usize, column: usize| rows[row][column].value.as_ref().unwrap().as_number().unwrap().to_f64();
+        assert_eq!(2000.0, stat(1, 1));
+        assert!((stat(1, 2) - 5.0).abs() < 0.2, "{}", stat(1, 2));
+        assert!((stat(1, 3) - 2.89).abs() < 0.1, "{}", stat(1, 3));
+        assert!(stat(1, 4) >= 0.0 && stat(1, 10) < 10.0);
+        // Every cell sees the same draw of A1 within an iteration.
+        assert!((stat(2, 2) - stat(1, 2) * 1.1).abs() < 1e-9);
+        assert_eq!(0.0, stat(3, 1));
+        assert_eq!("#DIV/0!", rows[3][2].content);
+
+        let again = calc.load().unwrap();
+        assert_eq!(table.cells().map(|c| c.content.clone()).collect::<Vec<String>>(), again.cells().map(|c| c.content.clone()).collect::<Vec<String>>());
+    }
+}
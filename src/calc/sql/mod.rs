@@ -0,0 +1,583 @@
+mod parser;
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+use regex::Regex;
+use crate::calc::{Calc, Locale, Table};
+use crate::calc::engine::{Cell, ErrorKind, Number, Value};
+use crate::calc::operators::holds;
+use crate::calc::scenario::results;
+use crate::framework::Loader;
+use parser::{Expr, is_aggregate, Item, JoinKind, Operator, parse, Select, Source};
+
+/// A `SELECT` over CSV files, with paths inside a directory. Files are computed like
+/// calc sheets and their header row names their columns.
+pub struct Query {
+    sql: String,
+    root: PathBuf,
+}
+
+impl Query {
+    pub fn new(sql: String, root: &Path) -> Self {
+        Query { sql, root: root.to_path_buf() }
+    }
+}
+
+impl Loader for Query {
+    type Result = Table;
+
+    fn load(&self) -> Result<Table, Box<dyn Error>> {
+        let select = parse(&self.sql)?;
+        run(&select, &self.root)
+    }
+}
+
+/// Content and value of every column of a row.
+type Row = Vec<(String, Value)>;
+
+/// Regexes of the `LIKE` patterns of a query, compiled once each.
+type Patterns = RefCell<HashMap<String, Option<Regex>>>;
+
+/// A column of the joined tables and a row of content and value for each of them.
+struct Relation {
+    columns: Vec<(String, String)>,
+    rows: Vec<Row>,
+}
+
+fn read(source: &Source, root: &Path) -> Result<Relation, Box<dyn Error>> {
+    if Path::new(&source.path).components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("{}: files must be inside the document directory", source.path).into());
+    }
+    let path = root.join(&source.path);
+    let table = Calc::from_file(path.to_string_lossy().to_string()).load()
+        .map_err(|e| format!("{}: {}", source.path, e))?;
+    let rows = table.rows();
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let cell = |row: &[&Cell], column: usize| row.iter()
+        .find(|c| c.position.column() == column)
+        .map(|c| (c.content.clone(), c.value.clone().unwrap_or(Value::Empty)))
+        .unwrap_or((String::new(), Value::Empty));
+
+    let columns = (0..width)
+        .map(|column| {
+            let name = rows.first().map(|header| cell(header, column).0).unwrap_or_default();
+            (source.alias.clone(), name.trim().to_string())
+        })
+        .collect();
+    let rows = rows.iter().skip(1).map(|row| (0..width).map(|column| cell(row, column)).collect()).collect();
+    Ok(Relation { columns, rows })
+}
+
+fn run(select: &Select, root: &Path) -> Result<Table, Box<dyn Error>> {
+    let patterns = Patterns::default();
+    let mut relation = read(&select.from, root)?;
+    for join in &select.joins {
+        let right = read(&join.source, root)?;
+        let mut columns = relation.columns.clone();
+        columns.extend(right.columns.iter().cloned());
+        let blank = vec![(String::new(), Value::Empty); right.columns.len()];
+
+        let mut rows = Vec::new();
+        let equi = join.on.as_ref().and_then(|on| equi_join(on, &columns, relation.columns.len()));
+        let index: HashMap<Key, Vec<usize>> = match equi {
+            Some((_, right_column)) => {
+                let mut index: HashMap<Key, Vec<usize>> = HashMap::new();
+                for (i, other) in right.rows.iter().enumerate() {
+                    if !matches!(other[right_column].1, Value::Empty) {
+                        index.entry(key(&other[right_column].1)).or_default().push(i);
+                    }
+                }
+                index
+            },
+            None => HashMap::new(),
+        };
+        for left in &relation.rows {
+            let candidates: Box<dyn Iterator<Item=&Row>> = match equi {
+                Some((left_column, _)) => Box::new(index.get(&key(&left[left_column].1)).into_iter().flatten().map(|i| &right.rows[*i])),
+                None => Box::new(right.rows.iter()),
+            };
+            let mut matched = false;
+            for other in candidates {
+                let row: Row = left.iter().chain(other).cloned().collect();
+                let keep = match &join.on {
+                    Some(on) => truth(&eval(on, &columns, &row, None, &patterns)?) == Some(true),
+                    None => true,
+                };
+                if keep {
+                    rows.push(row);
+                    matched = true;
+                }
+            }
+            if !matched && join.kind == JoinKind::Left {
+                rows.push(left.iter().chain(&blank).cloned().collect());
+            }
+        }
+        relation = Relation { columns, rows };
+    }
+
+    let columns = &relation.columns;
+    let mut rows = Vec::new();
+    for row in relation.rows {
+        let keep = match &select.filter {
+            Some(filter) => truth(&eval(filter, columns, &row, None, &patterns)?) == Some(true),
+            None => true,
+        };
+        if keep {
+            rows.push(row);
+        }
+    }
+
+    let aggregating = !select.group_by.is_empty()
+        || select.items.iter().any(|i| matches!(i, Item::Expr(e, _) if e.is_aggregate()))
+        || select.having.as_ref().is_some_and(Expr::is_aggregate);
+    let groups: Vec<Vec<Row>> = if aggregating {
+        group(&select.group_by, columns, rows, &patterns)?
+    } else {
+        rows.into_iter().map(|row| vec![row]).collect()
+    };
+
+    let mut header = Vec::new();
+    for item in &select.items {
+        match item {
+            Item::Wildcard(table) => header.extend(wildcard(columns, table.as_deref())?.into_iter().map(|c| columns[c].1.clone())),
+            Item::Expr(Expr::Column(_, name), None) => header.push(name.clone()),
+            Item::Expr(expr, alias) => header.push(alias.clone().unwrap_or_else(|| expr.to_string())),
+        }
+    }
+
+    let blank = vec![(String::new(), Value::Empty); columns.len()];
+    let mut output: Vec<(Row, Vec<Value>)> = Vec::new();
+    for rows in &groups {
+        let row = rows.first().unwrap_or(&blank);
+        let group = if aggregating { Some(rows.as_slice()) } else { None };
+        if let Some(having) = &select.having {
+            if truth(&eval(having, columns, row, group, &patterns)?) != Some(true) {
+                continue;
+            }
+        }
+
+        let mut values = Vec::new();
+        for item in &select.items {
+            match item {
+                Item::Wildcard(table) => values.extend(wildcard(columns, table.as_deref())?.into_iter().map(|c| row[c].clone())),
+                Item::Expr(Expr::Column(table, name), _) => values.push(row[column(columns, table.as_deref(), name)?].clone()),
+                Item::Expr(expr, _) => values.push(cell(eval(expr, columns, row, group, &patterns)?)),
+            }
+        }
+        let mut keys = Vec::new();
+        for (expr, _) in &select.order_by {
+            keys.push(match expr {
+                Expr::Column(None, name) if header.iter().any(|h| h.eq_ignore_ascii_case(name)) => {
+                    values[header.iter().position(|h| h.eq_ignore_ascii_case(name)).unwrap_or(0)].1.clone()
+                },
+                Expr::Literal(Value::Number(n)) => {
+                    let position = n.to_f64() as usize;
+                    values.get(position.wrapping_sub(1)).ok_or_else(|| format!("ORDER BY {} is not a result column", position))?.1.clone()
+                },
+                expr => eval(expr, columns, row, group, &patterns)?,
+            });
+        }
+        output.push((values, keys));
+    }
+
+    if select.distinct {
+        let mut seen = HashSet::new();
+        output.retain(|(values, _)| seen.insert(values.iter().map(|v| key(&v.1)).collect::<Vec<Key>>()));
+    }
+    // NULLs sort last either way, like blank cells when sorting a sheet.
+    output.sort_by(|(_, a), (_, b)| select.order_by.iter().zip(a.iter().zip(b))
+        .map(|((_, descending), (x, y))| match (x, y) {
+            (Value::Empty, _) | (_, Value::Empty) => x.sort_order(y),
+            _ if *descending => x.sort_order(y).reverse(),
+            _ => x.sort_order(y),
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal));
+
+    let rows = output.into_iter()
+        .skip(select.offset)
+        .take(select.limit.unwrap_or(usize::MAX))
+        .map(|(values, _)| values)
+        .collect();
+    Ok(results(Locale::default(), header, rows))
+}
+
+/// Content and value of a computed result cell.
+fn cell(value: Value) -> (String, Value) {
+    (Locale::default().format(&value), value)
+}
+
+/// Splits rows into groups of equal keys, sorted by key. Without keys every row is in a
+/// single group, even when there are none.
+fn group(keys: &[Expr], columns: &[(String, String)], rows: Vec<Row>, patterns: &Patterns) -> Result<Vec<Vec<Row>>, Box<dyn Error>> {
+    if keys.is_empty() {
+        return Ok(vec![rows]);
+    }
+    let mut groups: Vec<(Vec<Value>, Vec<Row>)> = Vec::new();
+    let mut index: HashMap<Vec<Key>, usize> = HashMap::new();
+    for row in rows {
+        let values = keys.iter().map(|k| eval(k, columns, &row, None, patterns)).collect::<Result<Vec<Value>, _>>()?;
+        match index.entry(values.iter().map(key).collect()) {
+            Entry::Occupied(group) => groups[*group.get()].1.push(row),
+            Entry::Vacant(group) => {
+                group.insert(groups.len());
+                groups.push((values, vec![row]));
+            },
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| a.iter().zip(b).map(|(x, y)| x.sort_order(y)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal));
+    Ok(groups.into_iter().map(|(_, rows)| rows).collect())
+}
+
+/// Columns of the left and right tables compared by a join condition such as `a.x = b.y`,
+/// so that matching rows can be looked up instead of scanned.
+fn equi_join(on: &Expr, columns: &[(String, String)], left: usize) -> Option<(usize, usize)> {
+    match on {
+        Expr::Binary(Operator::Equal, l, r) => match (l.as_ref(), r.as_ref()) {
+            (Expr::Column(a, x), Expr::Column(b, y)) => {
+                let (a, b) = (column(columns, a.as_deref(), x).ok()?, column(columns, b.as_deref(), y).ok()?);
+                match (a < left, b < left) {
+                    (true, false) => Some((a, b - left)),
+                    (false, true) => Some((b, a - left)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn wildcard(columns: &[(String, String)], table: Option<&str>) -> Result<Vec<usize>, String> {
+    let matching: Vec<usize> = (0..columns.len())
+        .filter(|c| table.is_none_or(|t| columns[*c].0.eq_ignore_ascii_case(t)))
+        .collect();
+    match (matching.is_empty(), table) {
+        (true, Some(table)) => Err(format!("unknown table {}", table)),
+        _ => Ok(matching),
+    }
+}
+
+fn column(columns: &[(String, String)], table: Option<&str>, name: &str) -> Result<usize, String> {
+    let matching: Vec<usize> = (0..columns.len())
+        .filter(|c| columns[*c].1.eq_ignore_ascii_case(name) && table.is_none_or(|t| columns[*c].0.eq_ignore_ascii_case(t)))
+        .collect();
+    let described = match table {
+        Some(table) => format!("{}.{}", table, name),
+        None => name.to_string(),
+    };
+    match matching.as_slice() {
+        [column] => Ok(*column),
+        [] => Err(format!("unknown column {}", described)),
+        _ => Err(format!("ambiguous column {}", described)),
+    }
+}
+
+/// Hashable form of a value, equal for values `=`, `DISTINCT` and `GROUP BY` treat as the same.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Empty,
+    Number(u64),
+    Quantity(u64, String),
+    Text(String),
+    Bool(bool),
+    Error(ErrorKind),
+}
+
+fn key(value: &Value) -> Key {
+    // Adding zero turns -0 into 0, which compare equal.
+    let bits = |n: &Number| (n.to_f64() + 0.0).to_bits();
+    match value {
+        Value::Empty => Key::Empty,
+        Value::Number(n) => Key::Number(bits(n)),
+        Value::Quantity(n, unit) => Key::Quantity(bits(n), unit.to_string()),
+        Value::Text(t) => Key::Text(t.clone()),
+        Value::Bool(b) => Key::Bool(*b),
+        Value::Error(e) => Key::Error(*e),
+    }
+}
+
+/// Whether two values are the same, as `=`, `DISTINCT` and `GROUP BY` compare them.
+fn same(a: &Value, b: &Value) -> bool {
+    key(a) == key(b)
+}
+
+/// Truth of a condition, `None` standing for unknown when it involves `NULL`.
+fn truth(value: &Value) -> Option<bool> {
+    match value {
+        Value::Empty => None,
+        v => holds(v.clone()).ok(),
+    }
+}
+
+fn boolean(truth: Option<bool>) -> Value {
+    truth.map(Value::Bool).unwrap_or(Value::Empty)
+}
+
+fn number(n: f64) -> Value {
+    match n.is_finite() {
+        true => Value::Number(Number::Float(n)),
+        false => Value::Error(ErrorKind::Num),
+    }
+}
+
+fn eval(expr: &Expr, columns: &[(String, String)], row: &[(String, Value)], group: Option<&[Row]>, patterns: &Patterns) -> Result<Value, Box<dyn Error>> {
+    let value = |e: &Expr| eval(e, columns, row, group, patterns);
+    Ok(match expr {
+        Expr::Column(table, name) => row[column(columns, table.as_deref(), name)?].1.clone(),
+        Expr::Literal(v) => v.clone(),
+        Expr::Negate(e) => match value(e)? {
+            Value::Empty => Value::Empty,
+            v => match v.as_number() {
+                Ok(n) => number(-n.to_f64()),
+                Err(e) => Value::Error(e),
+            },
+        },
+        Expr::Not(e) => boolean(truth(&value(e)?).map(|t| !t)),
+        Expr::Binary(Operator::And, l, r) => match (truth(&value(l)?), truth(&value(r)?)) {
+            (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+            (Some(true), Some(true)) => Value::Bool(true),
+            _ => Value::Empty,
+        },
+        Expr::Binary(Operator::Or, l, r) => match (truth(&value(l)?), truth(&value(r)?)) {
+            (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+            (Some(false), Some(false)) => Value::Bool(false),
+            _ => Value::Empty,
+        },
+        Expr::Binary(op, l, r) => binary(*op, value(l)?, value(r)?),
+        Expr::IsNull(e, negated) => Value::Bool(matches!(value(e)?, Value::Empty) != *negated),
+        Expr::Like(e, pattern, negated) => match (value(e)?, value(pattern)?) {
+            (Value::Empty, _) | (_, Value::Empty) => Value::Empty,
+            (text, pattern) => Value::Bool(like(&display(&text), &display(&pattern), patterns) != *negated),
+        },
+        Expr::In(e, list, negated) => {
+            let needle = value(e)?;
+            let mut found = Some(false);
+            for item in list {
+                match (&needle, value(item)?) {
+                    (Value::Empty, _) | (_, Value::Empty) => found = found.and(None),
+                    (a, b) if same(a, &b) => found = Some(true),
+                    _ => {},
+                }
+                if found == Some(true) {
+                    break;
+                }
+            }
+            boolean(found.map(|f| f != *negated))
+        },
+        Expr::Between(e, low, high, negated) => {
+            let (v, low, high) = (value(e)?, value(low)?, value(high)?);
+            let inside = match (compare(&v, &low), compare(&v, &high)) {
+                (Some(a), Some(b)) => Some(a.is_ge() && b.is_le()),
+                _ => None,
+            };
+            boolean(inside.map(|i| i != *negated))
+        },
+        Expr::CountAll => number(group.map_or(1, |g| g.len()) as f64),
+        Expr::Function(name, args, distinct) if is_aggregate(name) => {
+            let group = group.ok_or_else(|| format!("{} is only allowed in SELECT, HAVING and ORDER BY", name))?;
+            let arg = match args.as_slice() {
+                [arg] => arg,
+                _ => return Err(format!("{} takes one argument", name).into()),
+            };
+            let mut values = Vec::new();
+            for row in group {
+                let v = eval(arg, columns, row, None, patterns)?;
+                if matches!(v, Value::Empty) || (*distinct && values.iter().any(|o| same(o, &v))) {
+                    continue;
+                }
+                values.push(v);
+            }
+            aggregate(name, &values)
+        },
+        Expr::Function(name, args, _) => {
+            let args = args.iter().map(value).collect::<Result<Vec<Value>, _>>()?;
+            function(name, &args)?
+        },
+    })
+}
+
+fn aggregate(name: &str, values: &[Value]) -> Value {
+    if name == "COUNT" {
+        return number(values.len() as f64);
+    }
+    if let Some(Value::Error(e)) = values.iter().find(|v| matches!(v, Value::Error(_))) {
+        return Value::Error(*e);
+    }
+    if values.is_empty() {
+        return Value::Empty;
+    }
+    match name {
+        "MIN" => values.iter().min_by(|a, b| a.sort_order(b)).cloned().unwrap_or(Value::Empty),
+        "MAX" => values.iter().max_by(|a, b| a.sort_order(b)).cloned().unwrap_or(Value::Empty),
+        _ => {
+            let numbers: Vec<f64> = values.iter().filter_map(|v| v.as_number().ok()).map(|n| n.to_f64()).collect();
+            let sum: f64 = numbers.iter().sum();
+            match name {
+                "AVG" if numbers.is_empty() => Value::Empty,
+                "AVG" => number(sum / numbers.len() as f64),
+                _ => number(sum),
+            }
+        },
+    }
+}
+
+fn function(name: &str, args: &[Value]) -> Result<Value, String> {
+    let float = |v: &Value| v.as_number().map(|n| n.to_f64());
+    Ok(match (name, args) {
+        ("COALESCE", args) => args.iter().find(|a| !matches!(a, Value::Empty)).cloned().unwrap_or(Value::Empty),
+        ("IFNULL", [a, b]) => if matches!(a, Value::Empty) { b.clone() } else { a.clone() },
+        (_, [Value::Empty, ..]) => Value::Empty,
+        ("UPPER", [v]) => Value::Text(display(v).to_uppercase()),
+        ("LOWER", [v]) => Value::Text(display(v).to_lowercase()),
+        ("TRIM", [v]) => Value::Text(display(v).trim().to_string()),
+        ("LENGTH", [v]) => number(display(v).chars().count() as f64),
+        ("SUBSTR", [v, start, rest @ ..]) if rest.len() <= 1 => {
+            let text: Vec<char> = display(v).chars().collect();
+            let start = (float(start).unwrap_or(1.0).max(1.0) as usize - 1).min(text.len());
+            let end = match rest.first().map(float) {
+                Some(Ok(length)) => (start + length.max(0.0) as usize).min(text.len()),
+                _ => text.len(),
+            };
+            Value::Text(text[start..end].iter().collect())
+        },
+        ("ABS", [v]) => match float(v) {
+            Ok(n) => number(n.abs()),
+            Err(e) => Value::Error(e),
+        },
+        ("ROUND", [v, rest @ ..]) if rest.len() <= 1 => {
+            let digits = rest.first().map(float).unwrap_or(Ok(0.0));
+            match (float(v), digits) {
+                (Ok(n), Ok(digits)) => {
+                    let scale = 10f64.powi(digits as i32);
+                    number((n * scale).round() / scale)
+                },
+                (Err(e), _) | (_, Err(e)) => Value::Error(e),
+            }
+        },
+        ("UPPER" | "LOWER" | "TRIM" | "LENGTH" | "SUBSTR" | "ABS" | "ROUND" | "IFNULL", _) => {
+            return Err(format!("wrong number of arguments to {}", name));
+        },
+        _ => return Err(format!("unknown function {}", name)),
+    })
+}
+
+fn binary(op: Operator, l: Value, r: Value) -> Value {
+    if matches!(l, Value::Empty) || matches!(r, Value::Empty) {
+        return Value::Empty;
+    }
+    let arithmetic = |f: fn(f64, f64) -> f64| match (l.as_number(), r.as_number()) {
+        (Ok(a), Ok(b)) => number(f(a.to_f64(), b.to_f64())),
+        (Err(e), _) | (_, Err(e)) => Value::Error(e),
+    };
+    let comparison = |test: fn(Ordering) -> bool| match compare(&l, &r) {
+        Some(ordering) => Value::Bool(test(ordering)),
+        None => Value::Empty,
+    };
+    match op {
+        Operator::Add => arithmetic(|a, b| a + b),
+        Operator::Subtract => arithmetic(|a, b| a - b),
+        Operator::Multiply => arithmetic(|a, b| a * b),
+        Operator::Divide | Operator::Modulo if r.as_number().map(|n| n.to_f64()) == Ok(0.0) => Value::Error(ErrorKind::Div0),
+        Operator::Divide => arithmetic(|a, b| a / b),
+        Operator::Modulo => arithmetic(|a, b| a % b),
+        Operator::Concat => Value::Text(display(&l) + &display(&r)),
+        Operator::Equal => Value::Bool(same(&l, &r)),
+        Operator::NotEqual => Value::Bool(!same(&l, &r)),
+        Operator::Less => comparison(Ordering::is_lt),
+        Operator::LessEqual => comparison(Ordering::is_le),
+        Operator::Greater => comparison(Ordering::is_gt),
+        Operator::GreaterEqual => comparison(Ordering::is_ge),
+        Operator::And | Operator::Or => Value::Error(ErrorKind::Value),
+    }
+}
+
+/// Orders two values, or `None` when either is `NULL`. Text compares by character, after numbers.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Empty, _) | (_, Value::Empty) => None,
+        (Value::Text(x), Value::Text(y)) => Some(x.cmp(y)),
+        (x, y) => Some(x.sort_order(y)),
+    }
+}
+
+/// Text of a value as `||` and text functions see it, numbers without padding.
+fn display(value: &Value) -> String {
+    value.as_text().unwrap_or_else(|e| e.to_string())
+}
+
+/// Matches SQL `LIKE` patterns, where `%` is any text and `_` any character, ignoring case.
+fn like(text: &str, pattern: &str, patterns: &Patterns) -> bool {
+    let mut patterns = patterns.borrow_mut();
+    let regex = patterns.entry(pattern.to_string()).or_insert_with(|| {
+        let mut regex = String::from("(?is)^");
+        for c in pattern.chars() {
+            match c {
+                '%' => regex += ".*",
+                '_' => regex += ".",
+                c => regex += &regex::escape(&c.to_string()),
+            }
+        }
+        regex.push('$');
+        Regex::new(&regex).ok()
+    });
+    regex.as_ref().is_some_and(|r| r.is_match(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::CsvPrinter;
+    use crate::calc::sql::Query;
+    use crate::framework::{Loader, Printer, TempDir};
+
+    fn query(sql: &str) -> String {
+        let dir = TempDir::new("sql");
+        let root = &dir.0;
+        std::fs::write(root.join("sales.csv"), "Region,Product,Amount\nN,apple,10\nS,pear,=2*15\nN,pear,5\nE,apple,\nS,fig,7").unwrap();
+        std::fs::write(root.join("regions.csv"), "Code,Name\nN,North\nS,South\nW,West").unwrap();
+        match Query::new(sql.to_string(), root).load() {
+            Ok(table) => String::from_utf8(CsvPrinter::new().print(table).unwrap()).unwrap(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    #[test]
+    fn selects_rows() {
+        assert_eq!("Region,Amount\nS,30.00\nN,10", query("SELECT Region, Amount FROM sales WHERE Amount > 5 AND product <> 'fig' ORDER BY Amount DESC"));
+        assert_eq!("product,double\napple,20.00\napple,\nfig,14.00", query("select product, amount * 2 as double from 'sales.csv' where product like 'A%' or product in ('fig') order by 1"));
+        assert_eq!("Region\nE\nN\nS", query("SELECT DISTINCT Region FROM sales ORDER BY Region"));
+        assert_eq!("Product\napple", query("SELECT Product FROM sales WHERE Amount IS NULL"));
+        assert_eq!("Region,Product,Amount\nS,pear,30.00", query("SELECT * FROM sales ORDER BY Amount DESC LIMIT 1"));
+        assert_eq!("Product\npear\nfig", query("SELECT Product FROM sales WHERE Amount BETWEEN 5 AND 10 LIMIT 5 OFFSET 1"));
+    }
+
+    #[test]
+    fn groups_rows() {
+        assert_eq!("Region,total,COUNT(*),AVG(Amount)\nN,15.00,2.00,7.50\nS,37.00,2.00,18.50",
+            query("SELECT Region, SUM(Amount) AS total, COUNT(*), AVG(Amount) FROM sales GROUP BY Region HAVING SUM(Amount) > 0 ORDER BY total"));
+        assert_eq!("COUNT(Amount),COUNT(DISTINCT Product),MAX(Product)\n4.00,3.00,pear", query("SELECT COUNT(Amount), COUNT(DISTINCT Product), MAX(Product) FROM sales"));
+        assert_eq!("SUM(Amount)\n", query("SELECT SUM(Amount) FROM sales WHERE Amount > 100"));
+    }
+
+    #[test]
+    fn joins_files() {
+        assert_eq!("Name,sum\nNorth,15.00\nSouth,37.00",
+            query("SELECT r.Name, SUM(s.Amount) sum FROM sales s JOIN regions r ON r.Code = s.Region GROUP BY r.Name"));
+        assert_eq!("Region,name\nE,?\nN,North",
+            query("SELECT DISTINCT s.Region, COALESCE(r.Name, '?') AS name FROM sales s LEFT JOIN regions r ON r.Code = s.Region WHERE s.Region < 'S' ORDER BY 1"));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!("error: unknown column Price", query("SELECT Price FROM sales"));
+        assert_eq!("error: ambiguous column Code", query("SELECT Code FROM regions a CROSS JOIN regions b"));
+        assert!(query("SELECT * FROM missing").starts_with("error: missing.csv: "));
+        assert_eq!("error: unknown function MEDIAN", query("SELECT MEDIAN(Amount) FROM sales"));
+        assert_eq!("error: ../sales.csv: files must be inside the document directory", query("SELECT * FROM '../sales.csv'"));
+        assert!(query("SELECT * FROM '/etc/hosts'").ends_with("files must be inside the document directory"));
+    }
+}
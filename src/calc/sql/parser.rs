@@ -0,0 +1,630 @@
+use std::fmt::{Display, Formatter};
+use logos::Logos;
+use crate::calc::engine::{Number, Value};
+
+#[derive(Logos, Debug, PartialEq, Clone, Copy)]
+enum Token {
+    #[token("(")]
+    Open,
+    #[token(")")]
+    Close,
+    #[token(",")]
+    Comma,
+    #[token(".")]
+    Dot,
+    #[token(";")]
+    Semicolon,
+
+    #[token("*")]
+    Star,
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("/")]
+    Slash,
+    #[token("%")]
+    Percent,
+    #[token("||")]
+    Concat,
+
+    #[token("=")]
+    #[token("==")]
+    Equal,
+    #[token("<>")]
+    #[token("!=")]
+    NotEqual,
+    #[token("<")]
+    Less,
+    #[token("<=")]
+    LessEqual,
+    #[token(">")]
+    Greater,
+    #[token(">=")]
+    GreaterEqual,
+
+    #[regex(r"[A-Za-z_][A-Za-z0-9_]*")]
+    Word,
+    #[regex(r#""([^"]|"")*""#)]
+    #[regex(r"`[^`]*`")]
+    QuotedWord,
+    #[regex(r"'([^']|'')*'")]
+    Text,
+    #[regex(r"\d+(\.\d*)?([eE][+-]?\d+)?")]
+    Number,
+
+    #[error]
+    #[regex(r"[ \t\r\n\f]+", logos::skip)]
+    #[regex(r"--[^\n]*", logos::skip)]
+    Error,
+}
+
+const RESERVED: [&str; 27] = [
+    "SELECT", "DISTINCT", "FROM", "WHERE", "GROUP", "BY", "HAVING", "ORDER", "LIMIT", "OFFSET",
+    "JOIN", "INNER", "LEFT", "OUTER", "CROSS", "ON", "AS", "AND", "OR", "NOT", "IS", "NULL",
+    "LIKE", "IN", "BETWEEN", "ASC", "DESC",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// A column, optionally qualified by the name of its table.
+    Column(Option<String>, String),
+    Literal(Value),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    /// A function call, with `DISTINCT` for aggregates of distinct values.
+    Function(String, Vec<Expr>, bool),
+    CountAll,
+    IsNull(Box<Expr>, bool),
+    Like(Box<Expr>, Box<Expr>, bool),
+    In(Box<Expr>, Vec<Expr>, bool),
+    Between(Box<Expr>, Box<Expr>, Box<Expr>, bool),
+}
+
+pub enum Item {
+    /// `*`, or `table.*` for the columns of one table.
+    Wildcard(Option<String>),
+    Expr(Expr, Option<String>),
+}
+
+/// A CSV file read as a table, named by its alias or file name.
+pub struct Source {
+    pub path: String,
+    pub alias: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
+}
+
+pub struct Join {
+    pub kind: JoinKind,
+    pub source: Source,
+    pub on: Option<Expr>,
+}
+
+pub struct Select {
+    pub distinct: bool,
+    pub items: Vec<Item>,
+    pub from: Source,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub having: Option<Expr>,
+    /// Sort keys, each descending or not.
+    pub order_by: Vec<(Expr, bool)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Expr {
+    pub fn is_aggregate(&self) -> bool {
+        match self {
+            Expr::CountAll => true,
+            Expr::Function(name, args, _) => is_aggregate(name) || args.iter().any(Expr::is_aggregate),
+            Expr::Column(..) | Expr::Literal(_) => false,
+            Expr::Negate(e) | Expr::Not(e) | Expr::IsNull(e, _) => e.is_aggregate(),
+            Expr::Binary(_, l, r) | Expr::Like(l, r, _) => l.is_aggregate() || r.is_aggregate(),
+            Expr::In(e, list, _) => e.is_aggregate() || list.iter().any(Expr::is_aggregate),
+            Expr::Between(e, low, high, _) => e.is_aggregate() || low.is_aggregate() || high.is_aggregate(),
+        }
+    }
+}
+
+pub fn is_aggregate(name: &str) -> bool {
+    matches!(name, "COUNT" | "SUM" | "AVG" | "MIN" | "MAX")
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, &'a str)>,
+    position: usize,
+}
+
+/// Parses a single `SELECT` statement.
+pub fn parse(sql: &str) -> Result<Select, String> {
+    let mut lexer = Token::lexer(sql);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        if token == Token::Error {
+            return Err(format!("unexpected {}", lexer.slice()));
+        }
+        tokens.push((token, lexer.slice()));
+    }
+
+    let mut parser = Parser { tokens, position: 0 };
+    let select = parser.select()?;
+    parser.accept(Token::Semicolon);
+    match parser.peek() {
+        Some((_, text)) => Err(format!("unexpected {}", text)),
+        None => Ok(select),
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<(Token, &'a str)> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<(Token, &'a str)> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, token: Token) -> bool {
+        match self.peek() {
+            Some((t, _)) if t == token => {
+                self.position += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token: Token, expected: &str) -> Result<(), String> {
+        match self.accept(token) {
+            true => Ok(()),
+            false => Err(self.unexpected(expected)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some((Token::Word, word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.unexpected(keyword)),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        match self.peek() {
+            Some((_, text)) => format!("expected {}, found {}", expected, text),
+            None => format!("expected {}, found the end of the query", expected),
+        }
+    }
+
+    /// A name that is not a reserved word, quoted or not.
+    fn name(&mut self) -> Option<String> {
+        match self.peek() {
+            Some((Token::Word, word)) if !RESERVED.iter().any(|r| word.eq_ignore_ascii_case(r)) => {
+                self.position += 1;
+                Some(word.to_string())
+            },
+            Some((Token::QuotedWord, quoted)) => {
+                self.position += 1;
+                Some(unquote(quoted))
+            },
+            _ => None,
+        }
+    }
+
+    fn alias(&mut self) -> Result<Option<String>, String> {
+        if self.keyword("AS") {
+            return self.name().map(Some).ok_or_else(|| self.unexpected("a name"));
+        }
+        Ok(self.name())
+    }
+
+    fn select(&mut self) -> Result<Select, String> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.keyword("DISTINCT");
+        let mut items = vec![self.item()?];
+        while self.accept(Token::Comma) {
+            items.push(self.item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.source()?;
+        let mut joins = Vec::new();
+        loop {
+            let kind = if self.keyword("JOIN") {
+                JoinKind::Inner
+            } else if self.keyword("INNER") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Inner
+            } else if self.keyword("LEFT") {
+                self.keyword("OUTER");
+                self.expect_keyword("JOIN")?;
+                JoinKind::Left
+            } else if self.keyword("CROSS") {
+                self.expect_keyword("JOIN")?;
+                JoinKind::Cross
+            } else {
+                break;
+            };
+            let source = self.source()?;
+            let on = match kind {
+                JoinKind::Cross => None,
+                _ => {
+                    self.expect_keyword("ON")?;
+                    Some(self.expression()?)
+                },
+            };
+            joins.push(Join { kind, source, on });
+        }
+
+        let filter = if self.keyword("WHERE") { Some(self.expression()?) } else { None };
+        let mut group_by = Vec::new();
+        if self.keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.expression()?);
+            while self.accept(Token::Comma) {
+                group_by.push(self.expression()?);
+            }
+        }
+        let having = if self.keyword("HAVING") { Some(self.expression()?) } else { None };
+        let mut order_by = Vec::new();
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.expression()?;
+                let descending = self.keyword("DESC");
+                if !descending {
+                    self.keyword("ASC");
+                }
+                order_by.push((expr, descending));
+                if !self.accept(Token::Comma) {
+                    break;
+                }
+            }
+        }
+        let limit = if self.keyword("LIMIT") { Some(self.count()?) } else { None };
+        let offset = if self.keyword("OFFSET") { self.count()? } else { 0 };
+
+        Ok(Select { distinct, items, from, joins, filter, group_by, having, order_by, limit, offset })
+    }
+
+    fn count(&mut self) -> Result<usize, String> {
+        match self.next() {
+            Some((Token::Number, number)) => number.parse().map_err(|_| format!("{} is not a whole number", number)),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a number"))
+            },
+        }
+    }
+
+    fn item(&mut self) -> Result<Item, String> {
+        if self.accept(Token::Star) {
+            return Ok(Item::Wildcard(None));
+        }
+        if let (Some((Token::Word | Token::QuotedWord, table)), Some((Token::Dot, _)), Some((Token::Star, _))) =
+            (self.peek(), self.tokens.get(self.position + 1).copied(), self.tokens.get(self.position + 2).copied()) {
+            self.position += 3;
+            return Ok(Item::Wildcard(Some(unquote(table))));
+        }
+        let expr = self.expression()?;
+        Ok(Item::Expr(expr, self.alias()?))
+    }
+
+    /// A file given as a quoted path, or a bare name standing for `name.csv`.
+    fn source(&mut self) -> Result<Source, String> {
+        let path = match self.peek() {
+            Some((Token::Text, text)) => {
+                self.position += 1;
+                unquote(text)
+            },
+            _ => match self.name() {
+                Some(name) if name.contains('.') => name,
+                Some(name) => format!("{}.csv", name),
+                None => return Err(self.unexpected("a file")),
+            },
+        };
+        let alias = match self.alias()? {
+            Some(alias) => alias,
+            None => std::path::Path::new(&path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        };
+        Ok(Source { path, alias })
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.keyword("OR") {
+            left = Expr::Binary(Operator::Or, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.keyword("AND") {
+            left = Expr::Binary(Operator::And, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.concat()?;
+        let op = match self.peek() {
+            Some((Token::Equal, _)) => Operator::Equal,
+            Some((Token::NotEqual, _)) => Operator::NotEqual,
+            Some((Token::Less, _)) => Operator::Less,
+            Some((Token::LessEqual, _)) => Operator::LessEqual,
+            Some((Token::Greater, _)) => Operator::Greater,
+            Some((Token::GreaterEqual, _)) => Operator::GreaterEqual,
+            _ => {
+                if self.keyword("IS") {
+                    let negated = self.keyword("NOT");
+                    self.expect_keyword("NULL")?;
+                    return Ok(Expr::IsNull(Box::new(left), negated));
+                }
+                let negated = self.keyword("NOT");
+                if self.keyword("LIKE") {
+                    return Ok(Expr::Like(Box::new(left), Box::new(self.concat()?), negated));
+                }
+                if self.keyword("IN") {
+                    self.expect(Token::Open, "(")?;
+                    let mut list = vec![self.expression()?];
+                    while self.accept(Token::Comma) {
+                        list.push(self.expression()?);
+                    }
+                    self.expect(Token::Close, ")")?;
+                    return Ok(Expr::In(Box::new(left), list, negated));
+                }
+                if self.keyword("BETWEEN") {
+                    let low = self.concat()?;
+                    self.expect_keyword("AND")?;
+                    let high = self.concat()?;
+                    return Ok(Expr::Between(Box::new(left), Box::new(low), Box::new(high), negated));
+                }
+                if negated {
+                    return Err(self.unexpected("LIKE, IN or BETWEEN"));
+                }
+                return Ok(left);
+            },
+        };
+        self.position += 1;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(self.concat()?)))
+    }
+
+    fn concat(&mut self) -> Result<Expr, String> {
+        let mut left = self.additive()?;
+        while self.accept(Token::Concat) {
+            left = Expr::Binary(Operator::Concat, Box::new(left), Box::new(self.additive()?));
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some((Token::Plus, _)) => Operator::Add,
+                Some((Token::Minus, _)) => Operator::Subtract,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some((Token::Star, _)) => Operator::Multiply,
+                Some((Token::Slash, _)) => Operator::Divide,
+                Some((Token::Percent, _)) => Operator::Modulo,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.accept(Token::Minus) {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.accept(Token::Plus) {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some((Token::Number, number)) => {
+                self.position += 1;
+                let number = number.parse::<f64>().map_err(|_| format!("{} is not a number", number))?;
+                Ok(Expr::Literal(Value::Number(Number::Float(number))))
+            },
+            Some((Token::Text, text)) => {
+                self.position += 1;
+                Ok(Expr::Literal(Value::Text(unquote(text))))
+            },
+            Some((Token::Open, _)) => {
+                self.position += 1;
+                let inner = self.expression()?;
+                self.expect(Token::Close, ")")?;
+                Ok(inner)
+            },
+            Some((Token::Word, word)) if word.eq_ignore_ascii_case("NULL") => {
+                self.position += 1;
+                Ok(Expr::Literal(Value::Empty))
+            },
+            Some((Token::Word, word)) if word.eq_ignore_ascii_case("TRUE") || word.eq_ignore_ascii_case("FALSE") => {
+                self.position += 1;
+                Ok(Expr::Literal(Value::Bool(word.eq_ignore_ascii_case("TRUE"))))
+            },
+            Some((Token::Word, word)) if matches!(self.tokens.get(self.position + 1), Some((Token::Open, _))) => {
+                self.position += 2;
+                self.call(word.to_uppercase())
+            },
+            _ => {
+                let name = self.name().ok_or_else(|| self.unexpected("an expression"))?;
+                if self.accept(Token::Dot) {
+                    let column = self.name().ok_or_else(|| self.unexpected("a column"))?;
+                    return Ok(Expr::Column(Some(name), column));
+                }
+                Ok(Expr::Column(None, name))
+            },
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr, String> {
+        if name == "COUNT" && self.accept(Token::Star) {
+            self.expect(Token::Close, ")")?;
+            return Ok(Expr::CountAll);
+        }
+        let distinct = self.keyword("DISTINCT");
+        let mut args = Vec::new();
+        if !self.accept(Token::Close) {
+            args.push(self.expression()?);
+            while self.accept(Token::Comma) {
+                args.push(self.expression()?);
+            }
+            self.expect(Token::Close, ")")?;
+        }
+        Ok(Expr::Function(name, args, distinct))
+    }
+}
+
+/// Strips the quotes of a quoted name or text, undoing doubled quotes.
+fn unquote(quoted: &str) -> String {
+    let quote = &quoted[..1];
+    match quote {
+        "'" | "\"" | "`" => quoted[1..quoted.len() - 1].replace(&quote.repeat(2), quote),
+        _ => quoted.to_string(),
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Modulo => "%",
+            Operator::Concat => "||",
+            Operator::Equal => "=",
+            Operator::NotEqual => "<>",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Writes the expression back as SQL, to name result columns without an alias.
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |items: &[Expr]| items.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(", ");
+        let not = |negated: &bool| if *negated { "NOT " } else { "" };
+        match self {
+            Expr::Column(Some(table), column) => write!(f, "{}.{}", table, column),
+            Expr::Column(None, column) => write!(f, "{}", column),
+            Expr::Literal(Value::Empty) => write!(f, "NULL"),
+            Expr::Literal(Value::Text(t)) => write!(f, "'{}'", t.replace('\'', "''")),
+            Expr::Literal(Value::Number(n)) => write!(f, "{}", n),
+            Expr::Literal(v) => write!(f, "{}", v),
+            Expr::Negate(e) => write!(f, "-{}", e),
+            Expr::Not(e) => write!(f, "NOT {}", e),
+            Expr::Binary(op, l, r) => write!(f, "{} {} {}", l, op, r),
+            Expr::Function(name, args, true) => write!(f, "{}(DISTINCT {})", name, list(args)),
+            Expr::Function(name, args, false) => write!(f, "{}({})", name, list(args)),
+            Expr::CountAll => write!(f, "COUNT(*)"),
+            Expr::IsNull(e, negated) => write!(f, "{} IS {}NULL", e, not(negated)),
+            Expr::Like(e, pattern, negated) => write!(f, "{} {}LIKE {}", e, not(negated), pattern),
+            Expr::In(e, items, negated) => write!(f, "{} {}IN ({})", e, not(negated), list(items)),
+            Expr::Between(e, low, high, negated) => write!(f, "{} {}BETWEEN {} AND {}", e, not(negated), low, high),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::sql::parser::{Item, JoinKind, parse};
+
+    #[test]
+    fn parses_selects() {
+        let select = parse("
+            SELECT s.region, SUM(s.amount * 2) AS total, COUNT(*)
+            FROM 'exports/sales.csv' s
+            LEFT JOIN regions r ON r.code = s.region -- lookup
+            WHERE s.amount BETWEEN 1 AND 10 AND s.note NOT LIKE '%test%'
+            GROUP BY s.region HAVING COUNT(*) > 1
+            ORDER BY total DESC, 1 LIMIT 5 OFFSET 2;").unwrap();
+        let names: Vec<String> = select.items.iter().map(|i| match i {
+            Item::Expr(e, alias) => alias.clone().unwrap_or_else(|| e.to_string()),
+            Item::Wildcard(_) => "*".to_string(),
+        }).collect();
+        assert_eq!(vec!["s.region", "total", "COUNT(*)"], names);
+        assert_eq!(("exports/sales.csv", "s"), (select.from.path.as_str(), select.from.alias.as_str()));
+        assert_eq!(("regions.csv", "r", JoinKind::Left), (select.joins[0].source.path.as_str(), select.joins[0].source.alias.as_str(), select.joins[0].kind));
+        assert_eq!("s.amount BETWEEN 1 AND 10 AND s.note NOT LIKE '%test%'", select.filter.unwrap().to_string());
+        assert_eq!((Some(5), 2, 2), (select.limit, select.offset, select.order_by.len()));
+        assert!(select.order_by[0].1);
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(Err("expected FROM, found the end of the query".to_string()), parse("SELECT a").map(|_| ()));
+        assert_eq!(Err("expected an expression, found FROM".to_string()), parse("SELECT FROM t").map(|_| ()));
+        assert_eq!(Err("unexpected extra".to_string()), parse("SELECT a FROM t LIMIT 1 extra").map(|_| ()));
+        assert_eq!(Err("unexpected ?".to_string()), parse("SELECT ? FROM t").map(|_| ()));
+    }
+}
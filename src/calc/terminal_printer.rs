@@ -0,0 +1,131 @@
+use std::error::Error;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use crate::calc::Table;
+use crate::calc::engine::{Cell, Value};
+use crate::framework::Printer;
+
+const RED: &str = "\x1b[31m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/// Prints a table as aligned, box drawn text for reading in a terminal.
+pub struct TerminalPrinter {
+    color: bool,
+    max_width: usize,
+}
+
+impl TerminalPrinter {
+    pub fn new(color: bool, max_width: usize) -> Self {
+        TerminalPrinter { color, max_width: max_width.max(1) }
+    }
+
+    fn field(&self, cell: Option<&Cell>, width: usize) -> String {
+        let content = cell.map(|c| self.truncate(&c.content)).unwrap_or_default();
+        let value = cell.and_then(|c| c.value.as_ref());
+        let padding = " ".repeat(width - content.width());
+        let field = match value {
+            Some(Value::Number(_) | Value::Quantity(..)) => format!("{}{}", padding, content),
+            _ => format!("{}{}", content, padding),
+        };
+
+        match value {
+            Some(Value::Error(_)) if self.color => format!("{}{}{}", BOLD_RED, field, RESET),
+            Some(Value::Number(n) | Value::Quantity(n, _)) if self.color && n.to_f64() < 0.0 => format!("{}{}{}", RED, field, RESET),
+            _ => field,
+        }
+    }
+
+    fn truncate(&self, content: &str) -> String {
+        let content = content.replace(['\n', '\r'], " ");
+        if content.width() <= self.max_width {
+            return content;
+        }
+        let mut truncated = String::new();
+        let mut width = 0;
+        for c in content.chars() {
+            width += c.width().unwrap_or(0);
+            if width >= self.max_width {
+                break;
+            }
+            truncated.push(c);
+        }
+        truncated.push('…');
+        truncated
+    }
+}
+
+impl Printer<Table> for TerminalPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let rows = table.rows();
+        let columns = rows.iter().flat_map(|r| r.iter().map(|c| c.position.column() + 1)).max().unwrap_or(0);
+        if columns == 0 {
+            return Ok(Vec::new());
+        }
+
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| rows.iter()
+                .filter_map(|r| cell(r, column))
+                .map(|c| self.truncate(&c.content).width())
+                .max()
+                .unwrap_or(0))
+            .collect();
+
+        let line = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            format!("{}{}{}\n", left, segments.join(middle), right)
+        };
+
+        let mut output = line("┌", "┬", "┐");
+        for (index, row) in rows.iter().enumerate() {
+            if index == 1 {
+                output += &line("├", "┼", "┤");
+            }
+            let fields: Vec<String> = widths.iter()
+                .enumerate()
+                .map(|(column, width)| self.field(cell(row, column), *width))
+                .collect();
+            output += &format!("│ {} │\n", fields.join(" │ "));
+        }
+        output += &line("└", "┴", "┘");
+
+        Ok(output.into_bytes())
+    }
+
+    fn extension() -> &'static str {
+        "txt"
+    }
+}
+
+fn cell<'a>(row: &[&'a Cell], column: usize) -> Option<&'a Cell> {
+    row.iter().find(|c| c.position.column() == column).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{Calc, TerminalPrinter};
+    use crate::framework::{Loader, Printer};
+
+    #[test]
+    fn aligns_columns() {
+        let table = Calc::from_string("Item,Price\nbananas,2\nkiwi,=B2*-10".to_string()).load().unwrap();
+        let output = String::from_utf8(TerminalPrinter::new(false, 40).print(table).unwrap()).unwrap();
+        assert_eq!(
+            "┌─────────┬────────┐\n\
+             │ Item    │ Price  │\n\
+             ├─────────┼────────┤\n\
+             │ bananas │      2 │\n\
+             │ kiwi    │ -20.00 │\n\
+             └─────────┴────────┘\n",
+            output
+        );
+    }
+
+    #[test]
+    fn truncates_and_colors() {
+        let table = Calc::from_string("a long header,=1/0,=-1".to_string()).load().unwrap();
+        let output = String::from_utf8(TerminalPrinter::new(true, 7).print(table).unwrap()).unwrap();
+        assert!(output.contains("│ a long… │"), "{}", output);
+        assert!(output.contains("\x1b[1;31m#DIV/0!\x1b[0m"), "{}", output);
+        assert!(output.contains("\x1b[31m-1.00\x1b[0m"), "{}", output);
+    }
+}
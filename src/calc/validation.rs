@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use regex::Regex;
+use crate::calc::Locale;
+use crate::calc::engine::{Cell, CellRef};
+use crate::calc::format::parse_columns;
+
+enum Check {
+    Required,
+    Number,
+    Date,
+    Text,
+    Min(Bound),
+    Max(Bound),
+    OneOf(Vec<String>),
+    Matches(Regex, String),
+}
+
+enum Bound {
+    Number(f64),
+    Date(String),
+}
+
+struct Rule {
+    columns: Vec<usize>,
+    check: Check,
+}
+
+/// A cell breaking a validation rule.
+pub struct Violation {
+    pub cell: CellRef,
+    pub message: String,
+}
+
+/// Column validation rules, one per line:
+///
+/// ```text
+/// A required
+/// B number
+/// B min 0
+/// C date
+/// D one-of open closed
+/// E matches [A-Z]{3}-\d+
+/// ```
+///
+/// Rules check the content of every cell below the header row before evaluation.
+/// Formulas are not checked and blank cells only break `required`.
+pub struct ValidationRules {
+    rules: Vec<Rule>,
+    locale: Locale,
+}
+
+impl ValidationRules {
+    pub fn parse(text: &str, locale: Locale) -> Result<Self, Box<dyn Error>> {
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = parse_rule(line, locale).map_err(|e| format!("validation line {}: {}", number + 1, e))?;
+            rules.push(rule);
+        }
+        Ok(ValidationRules { rules, locale })
+    }
+
+    /// Every rule broken by a cell, in reading order.
+    pub fn check(&self, cells: &[Cell]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for cell in cells.iter().filter(|c| c.position.row() > 0 && !c.content.starts_with('=')) {
+            let mut messages: Vec<String> = Vec::new();
+            for rule in self.rules.iter().filter(|r| r.columns.contains(&cell.position.column())) {
+                match self.message(&rule.check, cell.content.trim()) {
+                    Some(message) if !messages.contains(&message) => messages.push(message),
+                    _ => {}
+                }
+            }
+            if !messages.is_empty() {
+                violations.push(Violation { cell: cell.position, message: messages.join("; ") });
+            }
+        }
+
+        // Required cells may be missing altogether from short rows.
+        let rows = cells.iter().map(|c| c.position.row()).max().unwrap_or(0);
+        let filled: HashSet<CellRef> = cells.iter().map(|c| c.position).collect();
+        for rule in self.rules.iter().filter(|r| matches!(r.check, Check::Required)) {
+            for column in &rule.columns {
+                for row in 1..=rows {
+                    let position = CellRef::new(row, *column);
+                    if !filled.contains(&position) {
+                        violations.push(Violation { cell: position, message: "is required".to_string() });
+                    }
+                }
+            }
+        }
+        violations.sort_by_key(|v| v.cell);
+        violations
+    }
+
+    fn message(&self, check: &Check, content: &str) -> Option<String> {
+        if content.is_empty() {
+            return match check {
+                Check::Required => Some("is required".to_string()),
+                _ => None,
+            };
+        }
+        let number = self.locale.parse_number(content, false).map(|n| n.to_f64());
+        match check {
+            Check::Required => None,
+            Check::Number if number.is_none() => Some(format!("\"{}\" is not a number", content)),
+            Check::Date if !is_date(content) => Some(format!("\"{}\" is not a date (YYYY-MM-DD)", content)),
+            Check::Text if number.is_some() => Some(format!("\"{}\" is not text", content)),
+            Check::Min(bound) | Check::Max(bound) => {
+                let (below, limit) = match check {
+                    Check::Min(_) => (true, "the minimum"),
+                    _ => (false, "the maximum"),
+                };
+                let outside = |ordering: std::cmp::Ordering| if below { ordering.is_lt() } else { ordering.is_gt() };
+                match (bound, number) {
+                    (Bound::Number(b), Some(n)) if outside(n.total_cmp(b)) => Some(format!("{} is {} {} {}", content, if below { "below" } else { "above" }, limit, b)),
+                    (Bound::Number(_), None) => Some(format!("\"{}\" is not a number", content)),
+                    (Bound::Date(_), _) if !is_date(content) => Some(format!("\"{}\" is not a date (YYYY-MM-DD)", content)),
+                    (Bound::Date(d), _) if outside(content.cmp(d.as_str())) => Some(format!("{} is {} {} {}", content, if below { "before" } else { "after" }, limit, d)),
+                    _ => None,
+                }
+            },
+            Check::OneOf(values) if !values.iter().any(|v| v == content) => Some(format!("\"{}\" is not one of {}", content, values.join(", "))),
+            Check::Matches(regex, pattern) if !regex.is_match(content) => Some(format!("\"{}\" does not match {}", content, pattern)),
+            _ => None,
+        }
+    }
+}
+
+fn parse_rule(line: &str, locale: Locale) -> Result<Rule, String> {
+    let mut parts = line.splitn(3, char::is_whitespace).map(str::trim).filter(|p| !p.is_empty());
+    let columns = parse_columns(parts.next().ok_or("missing column")?)?;
+    let kind = parts.next().ok_or("missing rule")?;
+    let argument = parts.next().unwrap_or("").trim();
+    let required = |argument: &str| if argument.is_empty() { Err(format!("{} needs a value", kind)) } else { Ok(argument.to_string()) };
+
+    let bound = |argument: &str| -> Result<Bound, String> {
+        let argument = required(argument)?;
+        match locale.parse_number(&argument, false) {
+            Some(n) => Ok(Bound::Number(n.to_f64())),
+            None if is_date(&argument) => Ok(Bound::Date(argument)),
+            None => Err(format!("{} needs a number or a date, found {}", kind, argument)),
+        }
+    };
+    let check = match kind {
+        "required" => Check::Required,
+        "number" => Check::Number,
+        "date" => Check::Date,
+        "text" => Check::Text,
+        "min" => Check::Min(bound(argument)?),
+        "max" => Check::Max(bound(argument)?),
+        "one-of" => Check::OneOf(required(argument)?.split_whitespace().map(String::from).collect()),
+        "matches" => {
+            let pattern = required(argument)?;
+            Check::Matches(Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| e.to_string())?, pattern)
+        },
+        k => return Err(format!("unknown rule {}", k)),
+    };
+    Ok(Rule { columns, check })
+}
+
+/// Whether the text is a calendar date written as `YYYY-MM-DD`.
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    let (year, month, day) = match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => match (y.parse::<u32>(), m.parse::<u32>(), d.parse::<u32>()) {
+            (Ok(y), Ok(m), Ok(d)) => (y, m, d),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// Every violation of a sheet, printed one per line.
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "INVALID {}: {}", violation.cell, violation.message)?;
+        }
+        writeln!(f, "{} invalid cells", self.violations.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use crate::calc::{Calc, CsvPrinter, Locale};
+    use crate::calc::engine::{Cell, CellRef};
+    use crate::calc::reader::CSVReader;
+    use crate::calc::validation::{is_date, ValidationRules};
+    use crate::framework::{Loader, Printer, TempDir};
+
+    fn cells(csv: &str) -> Vec<Cell> {
+        CSVReader::new(csv.as_bytes().bytes(), b',').collect()
+    }
+
+    #[test]
+    fn checks_columns() {
+        let rules = ValidationRules::parse("\
+# comment
+A required
+B number
+B min 0
+B max 10
+C date
+C max 2024-12-31
+D one-of open closed
+E matches [A-Z]{2}-\\d+
+", Locale::default()).unwrap();
+
+        let violations = rules.check(&cells("a,b,c,d,e\nx,5,2024-02-29,open,AB-1\n,11,2023-02-29,shut,ab-1\ny,-1,2025-01-01,,\nz,=1/0,,closed,XY-12\n,n"));
+        let messages: Vec<String> = violations.iter().map(|v| format!("{} {}", v.cell, v.message)).collect();
+        assert_eq!(vec![
+            "A3 is required",
+            "B3 11 is above the maximum 10",
+            "C3 \"2023-02-29\" is not a date (YYYY-MM-DD)",
+            "D3 \"shut\" is not one of open, closed",
+            "E3 \"ab-1\" does not match [A-Z]{2}-\\d+",
+            "B4 -1 is below the minimum 0",
+            "C4 2025-01-01 is after the maximum 2024-12-31",
+            "A6 is required",
+            "B6 \"n\" is not a number",
+        ], messages);
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        assert!(ValidationRules::parse("A between 1 2", Locale::default()).is_err());
+        assert!(ValidationRules::parse("A min", Locale::default()).is_err());
+        assert!(ValidationRules::parse("A matches (", Locale::default()).is_err());
+    }
+
+    #[test]
+    fn validates_dates() {
+        assert!(is_date("2024-02-29"));
+        assert!(!is_date("2100-02-29"));
+        assert!(!is_date("2024-1-01"));
+        assert!(!is_date("2024-04-31"));
+    }
+
+    #[test]
+    fn invalid_cells_are_errors() {
+        let dir = TempDir::new("validation");
+        let path = dir.0.join("schema.txt");
+        std::fs::write(&path, "B number").unwrap();
+        let calc = Calc::from_string("Item,Price\nA,2\nB,two\nTotal,=SUM(B2:B3)".to_string())
+            .with_validation(Some(path.to_str().unwrap().to_string()));
+
+        let csv = CsvPrinter::new().print(calc.load().unwrap()).unwrap();
+        assert_eq!("Item,Price\nA,2\nB,#INVALID!\nTotal,#INVALID!", String::from_utf8(csv).unwrap());
+        let report = calc.validate().unwrap();
+        assert_eq!("INVALID B3: \"two\" is not a number\n1 invalid cells\n", report.to_string());
+
+        let table = calc.load().unwrap();
+        let format = table.format(&CellRef::parse("B3")).unwrap();
+        assert_eq!(vec!["invalid".to_string()], format.classes);
+        assert_eq!(Some("\"two\" is not a number".to_string()), format.title);
+    }
+}
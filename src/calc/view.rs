@@ -0,0 +1,247 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::collections::HashMap;
+use regex::{Captures, Regex};
+use crate::calc::{Calc, Table};
+use crate::calc::engine::{Cell, CellRef, Delimiter, move_references, shift_references, Value};
+use crate::calc::format::parse_columns;
+use crate::calc::operators::holds;
+
+/// The rows and columns of a computed table to output. Rows below the header are filtered,
+/// sorted and limited, in that order, then narrowed to some columns. Columns are named by
+/// their letter in the computed table.
+#[derive(Default)]
+pub struct View {
+    /// Columns to sort by, each optionally followed by `desc`, such as `C desc,A`.
+    pub sort_by: Option<String>,
+    /// Condition a row must meet, such as `C>100`, where bare column letters stand for the
+    /// cell of that column in the row.
+    pub filter: Option<String>,
+    /// Columns to keep, in output order, such as `A,C,F` or `A:C`.
+    pub columns: Option<String>,
+    /// Most rows to keep below the header.
+    pub limit: Option<usize>,
+}
+
+impl View {
+    pub fn is_empty(&self) -> bool {
+        self.sort_by.is_none() && self.filter.is_none() && self.columns.is_none() && self.limit.is_none()
+    }
+
+    pub fn apply(&self, calc: &Calc, table: Table) -> Result<Table, Box<dyn Error>> {
+        let sort = self.sort_by.as_deref().map(parse_sort).transpose()?.unwrap_or_default();
+        let columns = self.columns.as_deref().map(parse_columns).transpose()?;
+        let filter = match &self.filter {
+            Some(filter) => Some((bare_columns(filter), calc.evaluator(&table)?)),
+            None => None,
+        };
+
+        let Table { cells, locale, formats } = table;
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        for cell in cells {
+            match rows.last_mut() {
+                Some(row) if row[0].position.row() == cell.position.row() => row.push(cell),
+                _ => rows.push(vec![cell]),
+            }
+        }
+        let mut body = if rows.is_empty() { Vec::new() } else { rows.split_off(1) };
+
+        if let Some((condition, engine)) = filter {
+            body.retain(|row| {
+                let offset = row[0].position.row() as isize - 1;
+                matches!(holds(engine.evaluate(&shift_references(&condition, offset, 0))), Ok(true))
+            });
+        }
+        body.sort_by(|a, b| sort.iter()
+            .map(|(column, descending)| compare(value(a, *column), value(b, *column), *descending))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal));
+        if let Some(limit) = self.limit {
+            body.truncate(limit);
+        }
+        rows.extend(body);
+
+        let count = rows.len();
+        let mut cells = Vec::new();
+        let mut origins = Vec::new();
+        let mut positions = HashMap::new();
+        let mut moved = HashMap::new();
+        for (r, row) in rows.into_iter().enumerate() {
+            let mut row: Vec<(Option<CellRef>, Cell)> = match &columns {
+                Some(columns) => columns.iter()
+                    .map(|c| match row.iter().find(|cell| cell.position.column() == *c) {
+                        Some(cell) => (Some(cell.position), cell.clone()),
+                        None => {
+                            let mut blank = Cell::new(Vec::new(), r, *c, Delimiter::Comma);
+                            blank.value = Some(Value::Empty);
+                            (None, blank)
+                        }
+                    })
+                    .collect(),
+                None => row.into_iter().map(|cell| (Some(cell.position), cell)).collect(),
+            };
+            let width = row.len();
+            for (c, (origin, cell)) in row.iter_mut().enumerate() {
+                let position = CellRef::new(r, c);
+                if let Some(origin) = origin {
+                    positions.insert(*origin, position);
+                    if let Some(format) = formats.get(origin) {
+                        moved.insert(position, format.clone());
+                    }
+                }
+                cell.position = position;
+                cell.delimiter = match (r + 1 == count, c + 1 == width) {
+                    (true, true) => Delimiter::EOF,
+                    (false, true) => Delimiter::NewLine,
+                    _ => Delimiter::Comma,
+                };
+            }
+            for (origin, cell) in row {
+                origins.push(origin);
+                cells.push(cell);
+            }
+        }
+
+        // Formulas follow the cells they read, and are dropped when those are left out.
+        for (cell, origin) in cells.iter_mut().zip(origins) {
+            if let (Some(formula), Some(origin)) = (&cell.formula, origin) {
+                cell.formula = move_references(formula, origin, |p| positions.get(&p).copied());
+            }
+        }
+        Ok(Table { cells, locale, formats: moved })
+    }
+}
+
+fn parse_sort(text: &str) -> Result<Vec<(usize, bool)>, String> {
+    text.split(',')
+        .map(|key| {
+            let mut parts = key.split_whitespace();
+            let column = parse_columns(parts.next().unwrap_or(""))?;
+            let descending = match parts.next().map(|d| d.to_lowercase()).as_deref() {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(d) => return Err(format!("expected asc or desc, found {}", d)),
+            };
+            match column.as_slice() {
+                [column] => Ok((*column, descending)),
+                _ => Err(format!("invalid sort column {}", key.trim())),
+            }
+        })
+        .collect()
+}
+
+/// Turns bare column letters into references to the first data row, so conditions
+/// move down with each row like conditional formatting rules.
+fn bare_columns(filter: &str) -> String {
+    let word = Regex::new(r"\$?[A-Za-z][A-Za-z0-9.]*\$?\d*\(?").unwrap();
+    filter.split('"')
+        .enumerate()
+        .map(|(i, part)| if i % 2 == 1 {
+            part.to_string()
+        } else {
+            word.replace_all(part, |c: &Captures| {
+                let word = &c[0];
+                let letters = word.trim_start_matches('$');
+                if letters.chars().all(|c| c.is_ascii_uppercase()) && letters != "TRUE" && letters != "FALSE" {
+                    format!("{}2", word)
+                } else {
+                    word.to_string()
+                }
+            }).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\"")
+}
+
+fn value(row: &[Cell], column: usize) -> Option<&Value> {
+    row.iter().find(|c| c.position.column() == column).and_then(|c| c.value.as_ref())
+}
+
+/// Orders values like spreadsheet sorting, with blank cells last either way.
+fn compare(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let (a, b) = (a.unwrap_or(&Value::Empty), b.unwrap_or(&Value::Empty));
+    match (a, b) {
+        (Value::Empty, _) | (_, Value::Empty) => a.sort_order(b),
+        _ if descending => a.sort_order(b).reverse(),
+        _ => a.sort_order(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::calc::{Calc, CsvPrinter, Locale, XlsxPrinter};
+    use crate::calc::engine::CellRef;
+    use crate::calc::view::bare_columns;
+    use crate::calc::xlsx::read_xlsx;
+    use crate::framework::{Loader, Printer, TempDir};
+
+    const SALES: &str = "Region,Units,Price,Total\nNorth,10,5,=B2*C2\nSouth,30,2,=B3*C3\nEast,,4,=B4*C4\nWest,20,8,=B5*C5";
+
+    fn print(calc: Calc) -> String {
+        String::from_utf8(CsvPrinter::new().print(calc.load().unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sorts_rows() {
+        let calc = Calc::from_string(SALES.to_string()).with_sort_by(Some("D desc,A".to_string()));
+        assert_eq!("Region,Units,Price,Total\nWest,20,8,160.00\nSouth,30,2,60.00\nNorth,10,5,50.00\nEast,,4,0.00", print(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_sort_by(Some("B".to_string()));
+        assert_eq!("Region,Units,Price,Total\nNorth,10,5,50.00\nWest,20,8,160.00\nSouth,30,2,60.00\nEast,,4,0.00", print(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_sort_by(Some("B up".to_string()));
+        assert!(calc.load().is_err());
+    }
+
+    #[test]
+    fn filters_rows() {
+        let calc = Calc::from_string(SALES.to_string()).with_filter(Some("D>=60".to_string()));
+        assert_eq!("Region,Units,Price,Total\nSouth,30,2,60.00\nWest,20,8,160.00", print(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_filter(Some("A&\"!\"=\"West!\"".to_string()));
+        assert_eq!("Region,Units,Price,Total\nWest,20,8,160.00", print(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_filter(Some("ISBLANK(B)".to_string()));
+        assert_eq!("Region,Units,Price,Total\nEast,,4,0.00", print(calc));
+    }
+
+    #[test]
+    fn selects_columns_and_limits_rows() {
+        let calc = Calc::from_string(SALES.to_string())
+            .with_sort_by(Some("D desc".to_string()))
+            .with_columns(Some("D,A".to_string()))
+            .with_limit(Some(2));
+        assert_eq!("Total,Region\n160.00,West\n60.00,South", print(calc));
+
+        let dir = TempDir::new("view");
+        let rules = dir.0.join("rules.txt");
+        std::fs::write(&rules, "D class big =D2>100").unwrap();
+        let table = Calc::from_string(SALES.to_string())
+            .with_rules(Some(rules.to_str().unwrap().to_string()))
+            .with_sort_by(Some("D desc".to_string()))
+            .with_columns(Some("D".to_string()))
+            .load().unwrap();
+        assert_eq!(vec!["big".to_string()], table.format(&CellRef::new(1, 0)).unwrap().classes);
+        assert!(table.format(&CellRef::new(2, 0)).is_none());
+    }
+
+    #[test]
+    fn moves_formulas_with_their_cells() {
+        let sheet = |calc: Calc| {
+            let bytes = XlsxPrinter::new().print(calc.load().unwrap()).unwrap();
+            let cells = read_xlsx(Cursor::new(bytes), None, Locale::default()).unwrap();
+            cells.iter().map(|c| c.to_string()).collect::<String>()
+        };
+        let calc = Calc::from_string(SALES.to_string()).with_sort_by(Some("D desc".to_string()));
+        assert_eq!("Region,Units,Price,Total\nWest,20,8,=B2*C2\nSouth,30,2,=B3*C3\nNorth,10,5,=B4*C4\nEast,,4,=B5*C5", sheet(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_columns(Some("D,B,C".to_string())).with_limit(Some(1));
+        assert_eq!("Total,Units,Price\n=B2*C2,10,5", sheet(calc));
+        let calc = Calc::from_string(SALES.to_string()).with_columns(Some("D,A".to_string())).with_limit(Some(1));
+        assert_eq!("Total,Region\n50,North", sheet(calc));
+        let calc = Calc::from_string("A,B\n2,=RC[-1]*2\n1,=R2C1".to_string()).with_sort_by(Some("A".to_string()));
+        assert_eq!("A,B\n1,=A3\n2,=A3*2", sheet(calc));
+    }
+
+    #[test]
+    fn rewrites_bare_columns() {
+        assert_eq!("C2>100", bare_columns("C>100"));
+        assert_eq!("AND(B2>$C$1,A2=\"A\",TRUE)", bare_columns("AND(B>$C$1,A=\"A\",TRUE)"));
+    }
+}
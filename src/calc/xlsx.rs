@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Cursor, Read, Seek, Write};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+use crate::calc::{Locale, Table};
+use crate::calc::engine::{a1_formula, Cell, CellRef, ErrorKind, shift_references, Value};
+use crate::framework::Printer;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="1"><font><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/></cellXfs></styleSheet>"#;
+
+/// Reads the first worksheet of an XLSX workbook, or the one named `sheet`, as CSV cells.
+/// Formulas are kept in the syntax of `locale` so the engine evaluates them like CSV input.
+pub fn read_xlsx<R: Read + Seek>(reader: R, sheet: Option<&str>, locale: Locale) -> Result<Vec<Cell>, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(reader)?;
+    let strings = match read_entry(&mut archive, "xl/sharedStrings.xml") {
+        Ok(xml) => shared_strings(&xml)?,
+        Err(_) => Vec::new(),
+    };
+    let workbook = read_entry(&mut archive, "xl/workbook.xml")?;
+    let relationships = read_entry(&mut archive, "xl/_rels/workbook.xml.rels")?;
+    let path = sheet_path(&workbook, &relationships, sheet)?;
+    let contents = sheet_contents(&read_entry(&mut archive, &path)?, &strings, locale)?;
+    Ok(Cell::grid(contents))
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String, Box<dyn Error>> {
+    let mut content = String::new();
+    archive.by_name(name)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.to_string())
+}
+
+fn shared_strings(xml: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut reader = Reader::from_str(xml);
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"si" => current.clear(),
+                b"t" => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"si" => strings.push(current.clone()),
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                _ => {}
+            },
+            Event::Text(t) if in_text && !in_phonetic => current += &t.unescape()?,
+            Event::Eof => return Ok(strings),
+            _ => {}
+        }
+    }
+}
+
+fn sheet_path(workbook: &str, relationships: &str, name: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let mut reader = Reader::from_str(workbook);
+    let mut id = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e)
+                if e.local_name().as_ref() == b"sheet" && (name.is_none() || attribute(&e, "name").as_deref() == name) => {
+                id = attribute(&e, "r:id");
+                break;
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let id = id.ok_or_else(|| match name {
+        Some(name) => format!("no worksheet named {}", name),
+        None => "workbook has no worksheets".to_string(),
+    })?;
+
+    let mut reader = Reader::from_str(relationships);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if attribute(&e, "Id").as_ref() == Some(&id) => {
+                let target = attribute(&e, "Target").unwrap_or_default();
+                return Ok(match target.strip_prefix('/') {
+                    Some(absolute) => absolute.to_string(),
+                    None => format!("xl/{}", target),
+                });
+            },
+            Event::Eof => return Err(format!("missing worksheet {}", id).into()),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct XlsxCell {
+    position: Option<CellRef>,
+    kind: Option<String>,
+    value: String,
+    formula: Option<String>,
+    shared: Option<String>,
+}
+
+fn sheet_contents(xml: &str, strings: &[String], locale: Locale) -> Result<HashMap<CellRef, String>, Box<dyn Error>> {
+    let mut reader = Reader::from_str(xml);
+    let mut contents = HashMap::new();
+    let mut shared: HashMap<String, (CellRef, String)> = HashMap::new();
+    let mut cell = XlsxCell::default();
+    let mut element: Vec<u8> = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"c" => {
+                cell = XlsxCell {
+                    position: attribute(&e, "r").map(|r| CellRef::parse(&r)),
+                    kind: attribute(&e, "t"),
+                    ..XlsxCell::default()
+                };
+            },
+            Event::Start(e) => {
+                if e.local_name().as_ref() == b"f" {
+                    cell.formula = Some(String::new());
+                    cell.shared = attribute(&e, "si");
+                }
+                element = e.local_name().as_ref().to_vec();
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"f" => {
+                cell.shared = attribute(&e, "si");
+            },
+            Event::Text(t) => match element.as_slice() {
+                b"v" | b"t" => cell.value += &t.unescape()?,
+                b"f" => cell.formula.get_or_insert_with(String::new).push_str(&t.unescape()?),
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"c" => {
+                if let Some(position) = cell.position {
+                    let content = cell_content(&cell, position, strings, &mut shared, locale);
+                    contents.insert(position, content);
+                }
+            },
+            Event::End(_) => element.clear(),
+            Event::Eof => return Ok(contents),
+            _ => {}
+        }
+    }
+}
+
+fn cell_content(
+    cell: &XlsxCell,
+    position: CellRef,
+    strings: &[String],
+    shared: &mut HashMap<String, (CellRef, String)>,
+    locale: Locale,
+) -> String {
+    let formula = match (&cell.formula, &cell.shared) {
+        (Some(formula), Some(index)) if !formula.is_empty() => {
+            shared.insert(index.clone(), (position, formula.clone()));
+            Some(formula.clone())
+        },
+        (_, Some(index)) => shared.get(index).map(|(origin, formula)| shift_references(
+            formula,
+            position.row() as isize - origin.row() as isize,
+            position.column() as isize - origin.column() as isize,
+        )),
+        (Some(formula), None) if !formula.is_empty() => Some(formula.clone()),
+        _ => None,
+    };
+    if let Some(formula) = formula {
+        let formula = formula.replace("_xlfn.", "").replace("_xlws.", "");
+        return format!("={}", locale.localize_formula(&formula));
+    }
+
+    match cell.kind.as_deref() {
+        Some("s") => cell.value.trim().parse::<usize>().ok()
+            .and_then(|i| strings.get(i).cloned())
+            .unwrap_or_default(),
+        Some("b") => if cell.value.trim() == "1" { "TRUE" } else { "FALSE" }.to_string(),
+        Some("inlineStr") | Some("str") | Some("e") => cell.value.clone(),
+        _ => cell.value.replace('.', &locale.decimal.to_string()),
+    }
+}
+
+/// Prints a table as an XLSX workbook keeping both formulas and their computed values.
+pub struct XlsxPrinter;
+
+impl XlsxPrinter {
+    pub fn new() -> Self {
+        XlsxPrinter
+    }
+}
+
+impl Printer<Table> for XlsxPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let entries = [
+            ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+            ("_rels/.rels", RELS.to_string()),
+            ("xl/workbook.xml", WORKBOOK.to_string()),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.to_string()),
+            ("xl/styles.xml", STYLES.to_string()),
+            ("xl/worksheets/sheet1.xml", worksheet(&table)),
+        ];
+        for (name, content) in entries {
+            zip.start_file(name, FileOptions::default())?;
+            zip.write_all(content.as_bytes())?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn extension() -> &'static str {
+        "xlsx"
+    }
+}
+
+fn worksheet(table: &Table) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#);
+    for row in table.rows() {
+        xml += &format!("<row r=\"{}\">", row[0].position.row() + 1);
+        for cell in row {
+            xml += &xlsx_cell(cell, table.locale());
+        }
+        xml += "</row>";
+    }
+    xml += "</sheetData></worksheet>";
+    xml
+}
+
+fn xlsx_cell(cell: &Cell, locale: Locale) -> String {
+    let reference = format!("{}{}", CellRef::column_name(cell.position.column()), cell.position.row() + 1);
+    let value = cell.value.clone().unwrap_or(Value::Empty);
+    let kind = match (&value, &cell.formula) {
+        (Value::Empty, None) => return String::new(),
+        (Value::Text(_) | Value::Quantity(..), None) => " t=\"inlineStr\"",
+        (Value::Text(_) | Value::Quantity(..), Some(_)) => " t=\"str\"",
+        (Value::Bool(_), _) => " t=\"b\"",
+        (Value::Error(e), _) if excel_error(e) => " t=\"e\"",
+        (Value::Error(_), None) => " t=\"inlineStr\"",
+        (Value::Error(_), Some(_)) => " t=\"str\"",
+        _ => "",
+    };
+    let formula = match &cell.formula {
+        Some(formula) => format!("<f>{}</f>", escape(&a1_formula(&locale.canonical_formula(&formula[1..]), cell.position))),
+        None => String::new(),
+    };
+    let value = match value {
+        Value::Empty => String::new(),
+        Value::Text(t) if cell.formula.is_none() => format!("<is><t xml:space=\"preserve\">{}</t></is>", escape(&t)),
+        Value::Text(t) => format!("<v>{}</v>", escape(&t)),
+        Value::Quantity(..) if cell.formula.is_none() => format!("<is><t xml:space=\"preserve\">{}</t></is>", escape(&cell.content)),
+        Value::Quantity(..) => format!("<v>{}</v>", escape(&cell.content)),
+        Value::Bool(b) => format!("<v>{}</v>", b as u8),
+        Value::Number(n) => format!("<v>{}</v>", n),
+        Value::Error(e) if !excel_error(&e) && cell.formula.is_none() => format!("<is><t xml:space=\"preserve\">{}</t></is>", escape(&e.to_string())),
+        Value::Error(e) => format!("<v>{}</v>", escape(&e.to_string())),
+    };
+    format!("<c r=\"{}\"{}>{}{}</c>", reference, kind, formula, value)
+}
+
+/// Whether Excel knows an error, so that it can be written as one rather than as text.
+fn excel_error(error: &ErrorKind) -> bool {
+    !matches!(error, ErrorKind::Circ | ErrorKind::Invalid | ErrorKind::Unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+    use crate::calc::{Calc, Locale, XlsxPrinter};
+    use crate::calc::engine::Cell;
+    use crate::calc::xlsx::{read_xlsx, worksheet};
+    use crate::framework::{Loader, Printer};
+
+    fn csv(cells: Vec<Cell>) -> String {
+        cells.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_workbooks() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let entries = [
+            ("xl/workbook.xml", r#"<workbook xmlns:r="r"><sheets><sheet name="Notes" r:id="rId2"/><sheet name="Data" r:id="rId1"/></sheets></workbook>"#),
+            ("xl/_rels/workbook.xml.rels", r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Target="/xl/worksheets/sheet2.xml"/></Relationships>"#),
+            ("xl/sharedStrings.xml", r#"<sst><si><t>Price</t></si><si><r><t>To</t></r><r><t>tal</t></r></si></sst>"#),
+            ("xl/worksheets/sheet1.xml", r#"<worksheet><sheetData>
+                <row r="1"><c r="A1" t="s"><v>0</v></c><c r="C1" t="b"><v>1</v></c></row>
+                <row r="2"><c r="A2"><v>1.5</v></c><c r="B2"><f t="shared" ref="B2:B3" si="0">A2*2</f><v>3</v></c></row>
+                <row r="3"><c r="A3"><v>2</v></c><c r="B3"><f t="shared" si="0"/><v>4</v></c></row>
+                <row r="4"><c r="A4" t="s"><v>1</v></c><c r="B4"><f>_xlfn.SUM(B2:B3)</f></c></row>
+            </sheetData></worksheet>"#),
+            ("xl/worksheets/sheet2.xml", r#"<worksheet><sheetData><row r="1"><c r="A1" t="inlineStr"><is><t>note</t></is></c></row></sheetData></worksheet>"#),
+        ];
+        for (name, content) in entries {
+            zip.start_file(name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+
+        let cells = read_xlsx(Cursor::new(bytes.clone()), Some("Data"), Locale::default()).unwrap();
+        assert_eq!("Price,,TRUE\n1.5,=A2*2,\n2,=A3*2,\nTotal,=SUM(B2:B3),", csv(cells));
+
+        let de: Locale = "de".parse().unwrap();
+        let cells = read_xlsx(Cursor::new(bytes.clone()), Some("Data"), de).unwrap();
+        assert_eq!("1,5", cells[3].content);
+
+        let cells = read_xlsx(Cursor::new(bytes.clone()), None, Locale::default()).unwrap();
+        assert_eq!("note", csv(cells));
+
+        assert!(read_xlsx(Cursor::new(bytes), Some("Nope"), Locale::default()).is_err());
+    }
+
+    #[test]
+    fn writes_formulas_and_values() {
+        let table = Calc::from_string("Item,Price\n<a>,1.5\nTotal,=SUM(B2)*2".to_string()).load().unwrap();
+        let bytes = XlsxPrinter::new().print(table).unwrap();
+
+        let cells = read_xlsx(Cursor::new(bytes.clone()), None, Locale::default()).unwrap();
+        assert_eq!("Item,Price\n<a>,1.5\nTotal,=SUM(B2)*2", csv(cells));
+
+        let table = Calc::from_string("Item,Price\n<a>,1.5\nTotal,=SUM(R[-1]C)*2".to_string()).load().unwrap();
+        let sheet = worksheet(&table);
+        assert!(sheet.contains(r#"<c r="A2" t="inlineStr"><is><t xml:space="preserve">&lt;a&gt;</t></is></c>"#), "{}", sheet);
+        assert!(sheet.contains(r#"<c r="B3"><f>SUM(B2)*2</f><v>3</v></c>"#), "{}", sheet);
+
+        let table = Calc::from_string("=A1,#DIV/0!,\"=CONVERT(1,\"\"m\"\",\"\"s\"\")\"".to_string()).load().unwrap();
+        let sheet = worksheet(&table);
+        assert!(sheet.contains(r#"<c r="A1" t="str"><f>A1</f><v>#CIRC!</v></c>"#), "{}", sheet);
+        assert!(sheet.contains(r#"<c r="B1" t="e"><v>#DIV/0!</v></c>"#), "{}", sheet);
+        assert!(sheet.contains(r#"<c r="C1" t="str">"#), "{}", sheet);
+    }
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
 use std::ops::{Deref};
 use pulldown_cmark::{CodeBlockKind, Event, LinkType, Tag};
+use crate::calc::Table;
 use crate::doc::Document;
 use crate::Printer;
 
@@ -125,12 +126,59 @@ impl Printer<Document> for MarkdownPrinter {
     }
 }
 
+/// Prints a table as a GFM pipe table, right aligning numeric columns.
+impl Printer<Table> for MarkdownPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let numeric = table.numeric_columns();
+        let rows: Vec<Vec<String>> = table.rows().iter()
+            .map(|row| {
+                let mut fields = vec![String::new(); numeric.len()];
+                for cell in row {
+                    fields[cell.position.column()] = cell.content.replace('|', "\\|").replace('\n', "<br>");
+                }
+                fields
+            })
+            .collect();
+        let widths: Vec<usize> = (0..numeric.len())
+            .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0).max(3))
+            .collect();
+
+        let mut output = String::new();
+        for (index, row) in rows.iter().enumerate() {
+            let fields: Vec<String> = row.iter().zip(&widths).zip(&numeric)
+                .map(|((field, width), numeric)| match numeric {
+                    true => format!("{:>width$}", field, width = width),
+                    false => format!("{:<width$}", field, width = width),
+                })
+                .collect();
+            output += &format!("| {} |\n", fields.join(" | "));
+
+            if index == 0 {
+                let rules: Vec<String> = widths.iter().zip(&numeric)
+                    .map(|(width, numeric)| match numeric {
+                        true => format!("{}:", "-".repeat(width - 1)),
+                        false => "-".repeat(*width),
+                    })
+                    .collect();
+                output += &format!("| {} |\n", rules.join(" | "));
+            }
+        }
+
+        Ok(output.into_bytes())
+    }
+
+    fn extension() -> &'static str {
+        "md"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pulldown_cmark::Event;
+    use crate::calc::Calc;
     use crate::doc::Document;
     use crate::doc::markdown_printer::MarkdownPrinter;
-    use crate::Printer;
+    use crate::{Loader, Printer};
 
     #[test]
     fn copies_input() {
@@ -175,4 +223,11 @@ mod tests {
             assert_eq!(original_elements, new_elements)
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn prints_tables() {
+        let table = Calc::from_string("Item,Price\na|b,2\nlonger,=B2*2".to_string()).load().unwrap();
+        let output = String::from_utf8(MarkdownPrinter::new().print(table).unwrap()).unwrap();
+        assert_eq!("| Item   | Price |\n| ------ | ----: |\n| a\\|b   |     2 |\n| longer |  4.00 |\n", output);
+    }
+}
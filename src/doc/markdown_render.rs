@@ -8,7 +8,9 @@ use syntect::parsing::SyntaxSet;
 use syntect::html::{ClassedHTMLGenerator, ClassStyle};
 use syntect::util::LinesWithEndings;
 use crate::framework::{print_to_vec};
-use crate::calc::{Calc, CsvPrinter};
+use crate::calc::{Calc, CsvPrinter, Query};
+use crate::framework::Loader;
+use crate::html::render_table;
 
 pub trait MdExtension {
     fn extend(&self, index: usize, events: &mut [Event]) -> Result<(), Box<dyn Error>>;
@@ -38,6 +40,7 @@ impl<'a> MarkdownEngine<'a> {
 
 pub fn render_markdown<'a>(input: &'a str, root: Option<&Path>) -> Result<IntoIter<Event<'a>>, Box<dyn Error>> {
     let mut r = MarkdownEngine::new(input);
+    r.apply(SqlQuery(root))?;
     r.apply(SyntaxHighlight)?;
     r.apply(CSVCalc)?;
     if let Some(root) = root {
@@ -96,6 +99,26 @@ impl MdExtension for CSVCalc {
     }
 }
 
+struct SqlQuery<'a>(Option<&'a Path>);
+impl<'a> MdExtension for SqlQuery<'a> {
+    fn extend(&self, i: usize, events: &mut [Event]) -> Result<(), Box<dyn Error>> {
+        let sql = CowStr::from("sql");
+        if matches!(&events[i], Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(x))) if x == &sql) {
+            let text: String = match &events[i+1] {
+                Event::Text(text) => text.clone().into_string(),
+                _ => return Err("empty sql code block".into())
+            };
+
+            let table = Query::new(text, self.0.unwrap_or(Path::new(""))).load()?;
+
+            events[i] = Event::Start(Tag::Paragraph);
+            events[i+1] = Event::Html(CowStr::from(render_table(&table)?));
+            events[i+2] = Event::End(Tag::Paragraph);
+        }
+        Ok(())
+    }
+}
+
 struct EmbedImages<'a>(&'a Path);
 impl<'a> MdExtension for EmbedImages<'a> {
     fn extend(&self, i: usize, events: &mut [Event]) -> Result<(), Box<dyn Error>> {
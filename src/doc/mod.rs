@@ -56,6 +56,10 @@ impl<'a> Loader for Doc<'a> {
 mod tests {
     use pulldown_cmark::html;
     use crate::doc::{Document};
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+    use crate::framework::{Printer, TempDir};
+    use crate::odf::OdfPrinter;
 
     impl Document {
         pub fn new(source: &str) -> Self {
@@ -88,6 +92,29 @@ NUM, NUM
         ".trim()));
     }
 
+    #[test]
+    fn runs_sql() {
+        let root = TempDir::new("doc-sql");
+        std::fs::write(root.0.join("people.csv"), "Name,Age\nAda,36\nAlan,41").unwrap();
+        let doc = Document { source: String::from("```sql\nSELECT Name FROM people WHERE Age > 40\n```"), path: Some(root.0.clone()) };
+        let mut buf = Vec::new();
+        html::write_html(&mut buf, doc.elements().unwrap()).unwrap();
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.starts_with("<p><table class=\"sheet\">"), "{}", html);
+        assert!(html.contains(">Alan</td>") && !html.contains("Ada"), "{}", html);
+
+        let package = OdfPrinter::new().print(doc).unwrap();
+        let mut content = String::new();
+        ZipArchive::new(Cursor::new(package)).unwrap().by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert!(content.contains("<office:text><table:table>"), "{}", content);
+        assert!(content.contains("<table:table-cell office:value-type=\"string\"><text:p>Alan</text:p></table:table-cell>"), "{}", content);
+    }
+
+    #[test]
+    fn rejects_empty_sql() {
+        assert!(Document::new("```sql\n```").elements().is_err());
+    }
+
     fn render(input: &str) -> String {
         let doc = Document::new(input);
         let mut buf = Vec::new();
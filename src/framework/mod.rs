@@ -36,6 +36,14 @@ pub fn print_to_file<T>(loader: impl Loader<Result=T>, printer: impl Printer<T>,
     Ok(())
 }
 
+pub fn print_to_stdout<T>(loader: impl Loader<Result=T>, printer: impl Printer<T>) -> Result<(), Box<dyn Error>> {
+    let printed = printer.print(loader.load()?)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&printed)?;
+    stdout.flush()?;
+    Ok(())
+}
+
 pub fn print_to_web<T>(loader: impl Loader<Result=T>, printer: impl Printer<T>, port: u32, paths: Vec<String>) -> Result<(), Box<dyn Error>>  {
     let addr = format!("localhost:{}", port);
     let server = Server::http(&addr).unwrap();
@@ -109,6 +117,29 @@ fn event_stream(paths: Vec<String>, request: Request) -> Result<(), Box<dyn Erro
     }
 }
 
+/// A directory for the files of a test, removed once the test is done, even when it fails.
+#[cfg(test)]
+pub struct TempDir(pub std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Creates a directory named after `name`, unique to this process and call.
+    pub fn new(name: &str) -> Self {
+        static COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let dir = TempDir(std::env::temp_dir().join(format!("doffice-{}-{}-{}", name, std::process::id(), count)));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        dir
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{Read};
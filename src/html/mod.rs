@@ -4,10 +4,10 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::fmt::Write as FmtWrite;
-use std::io::Write as IoWrite;
-use handlebars::{Handlebars, TemplateError};
+use handlebars::{html_escape, Handlebars, TemplateError};
 use pulldown_cmark::html;
 use crate::calc::Table;
+use crate::calc::engine::{Cell, Value};
 use crate::doc::Document;
 use crate::framework::Printer;
 use crate::show::Presentation;
@@ -28,13 +28,7 @@ impl HtmlPrinter {
 
 impl Printer<Table> for HtmlPrinter {
     fn print(&self, value: Table) -> Result<Vec<u8>, Box<dyn Error>> {
-
-        let mut buffer = Vec::new();
-        for cell in value.cells() {
-            write!(buffer, "{}", cell)?;
-        }
-
-        let table = String::from_utf8(buffer)?;
+        let table = render_table(&value)?;
 
         let mut data = HashMap::new();
         data.insert("table", table.as_str());
@@ -143,6 +137,57 @@ impl Printer<Presentation> for HtmlPrinter {
     }
 }
 
+pub fn render_table(table: &Table) -> Result<String, Box<dyn Error>> {
+    let rows = table.rows();
+    let numeric = table.numeric_columns();
+
+    let mut html = String::from("<table class=\"sheet\">\n");
+    let mut body = rows.iter();
+    if let Some(header) = body.next() {
+        html += "<thead>\n";
+        write_row(&mut html, table, header, "th", &numeric)?;
+        html += "</thead>\n";
+    }
+    html += "<tbody>\n";
+    for row in body {
+        write_row(&mut html, table, row, "td", &numeric)?;
+    }
+    html += "</tbody>\n</table>";
+    Ok(html)
+}
+
+fn write_row(html: &mut String, table: &Table, row: &[&Cell], tag: &str, numeric: &[bool]) -> Result<(), Box<dyn Error>> {
+    *html += "<tr>";
+    for cell in row {
+        let mut classes = vec![match &cell.value {
+            Some(Value::Number(_) | Value::Quantity(..)) => "number",
+            Some(Value::Bool(_)) => "bool",
+            Some(Value::Error(_)) => "error",
+            Some(Value::Empty) | None => "empty",
+            Some(Value::Text(_)) => "text",
+        }];
+        if numeric[cell.position.column()] {
+            classes.push("numeric");
+        }
+
+        let format = table.format(&cell.position);
+        if let Some(format) = format {
+            classes.extend(format.classes.iter().map(String::as_str));
+        }
+
+        write!(html, "<{} class=\"{}\"", tag, html_escape(&classes.join(" ")))?;
+        if let Some(format) = format.filter(|f| !f.styles.is_empty()) {
+            write!(html, " style=\"{}\"", html_escape(&format.styles.join("; ")))?;
+        }
+        if let Some(title) = format.and_then(|f| f.title.as_ref()).or(cell.formula.as_ref()) {
+            write!(html, " title=\"{}\"", html_escape(title))?;
+        }
+        write!(html, ">{}</{}>", html_escape(&cell.content), tag)?;
+    }
+    *html += "</tr>\n";
+    Ok(())
+}
+
 fn create_handlebars<'a>() -> Result<Handlebars<'a>, TemplateError> {
     let mut handlebars = Handlebars::new();
     handlebars.register_template_string("SHEET", include_str!("res/table.hbs"))?;
@@ -154,10 +199,10 @@ fn create_handlebars<'a>() -> Result<Handlebars<'a>, TemplateError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::calc::Table;
+    use crate::calc::{Calc, Table};
     use crate::doc::Document;
     use crate::show::Presentation;
-    use crate::framework::Printer;
+    use crate::framework::{Loader, Printer};
     use crate::{HtmlPrinter};
 
     #[test]
@@ -166,6 +211,17 @@ mod tests {
         render.print(Table::new(Vec::new())).unwrap();
     }
 
+    #[test]
+    fn renders_table_cells() {
+        let render = HtmlPrinter::new(false, None);
+        let table = Calc::from_string("Item,Price\n<b>,2\nTOTAL,=SUM(B2)".to_string()).load().unwrap();
+        let html = String::from_utf8(render.print(table).unwrap()).unwrap();
+
+        assert!(html.contains("<thead>\n<tr><th class=\"text\">Item</th><th class=\"text numeric\">Price</th></tr>"), "{}", html);
+        assert!(html.contains("<td class=\"text\">&lt;b&gt;</td>"), "{}", html);
+        assert!(html.contains("<td class=\"number numeric\" title=\"&#x3D;SUM(B2)\">2.00</td>"), "{}", html);
+    }
+
     #[test]
     fn can_render_document() {
         let render = HtmlPrinter::new(false, None);
@@ -5,15 +5,18 @@ mod doc;
 mod show;
 mod framework;
 mod html;
+mod odf;
 
 use std::error::Error;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use clap::{Parser, Subcommand, ValueHint};
-use crate::calc::{Calc, CsvPrinter};
+use crate::calc::{Calc, CsvPrinter, default_plugins, DotPrinter, FormulaMode, JsonPrinter, Locale, NdjsonPrinter, run_repl, TerminalPrinter, XlsxPrinter};
 use crate::doc::{Doc, MarkdownPrinter};
-use crate::framework::{Printer, Loader, print_to_file, print_to_web};
+use crate::framework::{Printer, Loader, print_to_file, print_to_stdout, print_to_web};
 use crate::html::HtmlPrinter;
+use crate::odf::OdfPrinter;
 use crate::show::{Slides};
 
 #[cfg(feature = "ui")]
@@ -36,20 +39,8 @@ struct Args {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Process CSV file
-    Calc {
-        #[clap(value_hint=ValueHint::FilePath)]
-        file: String,
-        /// Output location
-        #[clap(short, long, value_hint=ValueHint::FilePath)]
-        output_file: Option<String>,
-        /// CSS theme file to apply to tables
-        #[clap(short, long, value_hint=ValueHint::FilePath)]
-        theme: Option<String>,
-        /// Output file format
-        #[clap(short, long, arg_enum, default_value = "csv")]
-        format: CalcFormat
-    },
+    /// Process CSV or XLSX file
+    Calc(Box<CalcArgs>),
     /// Process markdown document
     Doc {
         #[clap(value_hint=ValueHint::FilePath)]
@@ -73,20 +64,133 @@ enum Commands {
         output_file: Option<String>,
         /// CSS theme file to apply to slides
         #[clap(short, long, value_hint=ValueHint::FilePath)]
-        theme: Option<String>
+        theme: Option<String>,
+        /// Output file format
+        #[clap(short, long, arg_enum, default_value = "html")]
+        format: ShowFormat
     },
 }
 
+#[derive(clap::Args)]
+struct CalcArgs {
+    #[clap(value_hint=ValueHint::FilePath)]
+    file: String,
+    /// Output location, or - for standard output
+    #[clap(short, long, value_hint=ValueHint::FilePath)]
+    output_file: Option<String>,
+    /// CSS theme file to apply to tables
+    #[clap(short, long, value_hint=ValueHint::FilePath)]
+    theme: Option<String>,
+    /// Output file format
+    #[clap(short, long, arg_enum, default_value = "csv")]
+    format: CalcFormat,
+    /// WebAssembly plugin file or directory of plugins
+    #[clap(long, multiple_occurrences = true, value_hint=ValueHint::AnyPath)]
+    plugin: Vec<String>,
+    /// Use exact decimal arithmetic instead of floating point
+    #[clap(long)]
+    decimal: bool,
+    /// Number and separator conventions, such as en, de or fr
+    #[clap(long, default_value = "en")]
+    locale: Locale,
+    /// Conditional formatting rules to apply to html tables
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    rules: Option<String>,
+    /// Widest column of the table format before truncating
+    #[clap(long, default_value = "40")]
+    max_width: usize,
+    /// Disable colors in the table format
+    #[clap(long)]
+    no_color: bool,
+    /// Worksheet to read from an XLSX file, the first one by default
+    #[clap(long)]
+    sheet: Option<String>,
+    /// Output formulas instead of their values
+    #[clap(long, conflicts_with = "annotate")]
+    show_formulas: bool,
+    /// Output values with each formula in a column next to them
+    #[clap(long)]
+    annotate: bool,
+    /// Query and edit the sheet interactively
+    #[clap(long)]
+    repl: bool,
+    /// Print how a cell is computed, with the value of every step
+    #[clap(long, value_name = "CELL")]
+    explain: Option<String>,
+    /// List every cell affected by a change to a cell
+    #[clap(long, value_name = "CELL")]
+    dependents: Option<String>,
+    /// Override the content of a cell, such as B2=0.07
+    #[clap(long, multiple_occurrences = true, value_name = "CELL=VALUE")]
+    set: Vec<String>,
+    /// Recompute the sheet for every value of a cell, such as B2=0.05..0.10:0.01
+    #[clap(long, value_name = "CELL=START..END:STEP", group = "collecting", requires = "collect")]
+    sweep: Option<String>,
+    /// Recompute the sheet N times with new random numbers and output statistics
+    #[clap(long, value_name = "N", group = "collecting", requires = "collect")]
+    simulate: Option<usize>,
+    /// Cell to output for every value of the sweep or simulation
+    #[clap(long, multiple_occurrences = true, value_name = "CELL", requires = "collecting")]
+    collect: Vec<String>,
+    /// Seed for random functions, to draw the same numbers on every run
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Find the input that makes a cell reach a value, such as F20=0
+    #[clap(long, value_name = "CELL=VALUE", requires = "by-changing")]
+    goal_seek: Option<String>,
+    /// Input cell changed by the goal seek
+    #[clap(long, value_name = "CELL", requires = "goal-seek")]
+    by_changing: Option<String>,
+    /// Report failing ASSERT cells, exiting with an error if any
+    #[clap(long)]
+    test: bool,
+    /// Column validation rules; invalid cells evaluate to #INVALID!
+    #[clap(long, value_hint=ValueHint::FilePath)]
+    schema: Option<String>,
+    /// Report cells breaking the schema, exiting with an error if any
+    #[clap(long, requires = "schema")]
+    validate: bool,
+    /// Sort rows below the header by columns, such as "C desc,A"
+    #[clap(long, value_name = "COLUMNS")]
+    sort_by: Option<String>,
+    /// Keep rows meeting a formula condition, such as "C>100"
+    #[clap(long, value_name = "CONDITION")]
+    filter: Option<String>,
+    /// Keep columns, in order, such as A,C,F
+    #[clap(long)]
+    columns: Option<String>,
+    /// Keep at most N rows below the header
+    #[clap(long, value_name = "N")]
+    limit: Option<usize>,
+    /// Output a summary of the sheet, such as rows=B,cols=C,values=sum(D)
+    #[clap(long, value_name = "SPEC", conflicts_with = "collecting")]
+    pivot: Option<String>
+}
+
 #[derive(clap::ArgEnum, Clone)]
 pub enum CalcFormat {
     Html,
     Csv,
+    Json,
+    Ndjson,
+    Markdown,
+    Table,
+    Xlsx,
+    Ods,
+    Dot,
 }
 
 #[derive(clap::ArgEnum, Clone)]
 pub enum DocFormat {
     Html,
     Md,
+    Odt,
+}
+
+#[derive(clap::ArgEnum, Clone)]
+pub enum ShowFormat {
+    Html,
+    Odp,
 }
 
 fn main() {
@@ -98,9 +202,54 @@ fn main() {
 
 fn process(mut args: Args) {
     let res = match &args.command {
-        Commands::Calc { file, theme, format, .. } => {
-            let calc = Calc::from_file(file.clone());
+        Commands::Calc(calc) => {
+            let CalcArgs { file, theme, format, plugin, decimal, locale, rules, max_width, no_color, sheet, show_formulas, annotate, repl, explain, dependents, set, sweep, simulate, collect, seed, goal_seek, by_changing, test, schema, validate, sort_by, filter, columns, limit, pivot, .. } = calc.as_ref();
+            let formulas = match (show_formulas, annotate) {
+                (true, _) => FormulaMode::Formulas,
+                (_, true) => FormulaMode::Annotated,
+                _ => FormulaMode::Values,
+            };
+            let calc = Calc::from_file(file.clone())
+                .with_plugins(default_plugins().map(|p| p.to_string_lossy().into_owned()).into_iter().chain(plugin.clone()).collect())
+                .with_decimal(*decimal)
+                .with_locale(*locale)
+                .with_rules(rules.clone())
+                .with_sheet(sheet.clone())
+                .with_formulas(formulas)
+                .with_overrides(set.clone())
+                .with_sweep(sweep.clone())
+                .with_simulation(*simulate)
+                .with_collect(collect.clone())
+                .with_seed(*seed)
+                .with_goal_seek(goal_seek.clone(), by_changing.clone())
+                .with_validation(schema.clone())
+                .with_sort_by(sort_by.clone())
+                .with_filter(filter.clone())
+                .with_columns(columns.clone())
+                .with_limit(*limit)
+                .with_pivot(pivot.clone())
+                .with_graph(matches!(format, CalcFormat::Dot));
             match format {
+                _ if *repl => {
+                    let stdin = std::io::stdin();
+                    run_repl(&calc, file, stdin.lock(), std::io::stdout())
+                },
+                _ if *test => calc.test().and_then(|report| {
+                    print!("{}", report);
+                    match report.failed() {
+                        0 => Ok(()),
+                        failed => Err(format!("{} of {} assertions failed", failed, report.assertions.len()).into()),
+                    }
+                }),
+                _ if *validate => calc.validate().and_then(|report| {
+                    print!("{}", report);
+                    match report.violations.len() {
+                        0 => Ok(()),
+                        invalid => Err(format!("{} invalid cells", invalid).into()),
+                    }
+                }),
+                _ if explain.is_some() => calc.explain(explain.as_deref().unwrap_or_default()).map(|tree| print!("{}", tree)),
+                _ if dependents.is_some() => calc.dependents(dependents.as_deref().unwrap_or_default()).map(|cells| print!("{}", cells)),
                 CalcFormat::Html => {
                     let printer = HtmlPrinter::new(args.watch, theme.clone());
                     run_command(&args, calc, printer)
@@ -112,6 +261,63 @@ fn process(mut args: Args) {
                     }
                     let printer = CsvPrinter::new();
                     run_command(&args, calc, printer)
+                },
+                CalcFormat::Json => {
+                    if args.watch {
+                        println!("WARNING: json format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = JsonPrinter::new();
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Ndjson => {
+                    if args.watch {
+                        println!("WARNING: ndjson format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = NdjsonPrinter::new();
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Markdown => {
+                    if args.watch {
+                        println!("WARNING: markdown format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = MarkdownPrinter::new();
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Table => {
+                    if args.watch {
+                        println!("WARNING: table format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let color = !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+                    let printer = TerminalPrinter::new(color, *max_width);
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Xlsx => {
+                    if args.watch {
+                        println!("WARNING: xlsx format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = XlsxPrinter::new();
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Ods => {
+                    if args.watch {
+                        println!("WARNING: ods format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = OdfPrinter::new();
+                    run_command(&args, calc, printer)
+                },
+                CalcFormat::Dot => {
+                    if args.watch {
+                        println!("WARNING: dot format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = DotPrinter::new();
+                    run_command(&args, calc, printer)
                 }
             }
         },
@@ -130,13 +336,34 @@ fn process(mut args: Args) {
                     let printer = MarkdownPrinter::new();
                     let doc = Doc::new(Path::new(&file));
                     run_command(&args, doc, printer)
+                },
+                DocFormat::Odt => {
+                    if args.watch {
+                        println!("WARNING: odt format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = OdfPrinter::new();
+                    let doc = Doc::new(Path::new(&file));
+                    run_command(&args, doc, printer)
                 }
             }
         },
-        Commands::Show { file, theme, .. } => {
-            let printer = HtmlPrinter::new(args.watch, theme.clone());
+        Commands::Show { file, theme, format, .. } => {
             let slides = Slides::new(Path::new(&file));
-            run_command(&args, slides, printer)
+            match format {
+                ShowFormat::Html => {
+                    let printer = HtmlPrinter::new(args.watch, theme.clone());
+                    run_command(&args, slides, printer)
+                },
+                ShowFormat::Odp => {
+                    if args.watch {
+                        println!("WARNING: odp format does not support watch mode");
+                        args.watch = false;
+                    }
+                    let printer = OdfPrinter::new();
+                    run_command(&args, slides, printer)
+                }
+            }
         },
     };
 
@@ -156,14 +383,20 @@ fn run_command<T, TPrinter: Printer<T>>(args: &Args, loader: impl Loader<Result=
         print_to_web(loader, printer, args.port, watch_paths)
     }else {
         let outfile = out_file(&args.command, <TPrinter>::extension());
-        print_to_file(loader, printer, &outfile)
+        if outfile == Path::new("-") {
+            print_to_stdout(loader, printer)
+        } else {
+            print_to_file(loader, printer, &outfile)
+        }
     }
 }
 
 fn out_file(command: &Commands, extension: &str) -> PathBuf {
     match command {
-        Commands::Calc { file, output_file, .. } => {
+        Commands::Calc(calc) => {
+            let CalcArgs { file, output_file, format, .. } = calc.as_ref();
             match output_file {
+                None if matches!(format, CalcFormat::Table) => PathBuf::from("-"),
                 None => Path::new(&file).with_extension(format!("out.{}", extension)),
                 Some(p) => Path::new(p).to_path_buf()
             }
@@ -187,11 +420,18 @@ fn watch_paths(command: &Commands) -> Vec<String>{
     let mut paths = Vec::new();
 
     match command {
-        Commands::Calc { file, theme, .. } => {
+        Commands::Calc(calc) => {
+            let CalcArgs { file, theme, rules, schema, .. } = calc.as_ref();
             paths.push(file.clone());
             if let Some(theme) = theme {
                 paths.push(theme.clone())
             }
+            if let Some(rules) = rules {
+                paths.push(rules.clone())
+            }
+            if let Some(schema) = schema {
+                paths.push(schema.clone())
+            }
         }
         Commands::Doc { file, theme, .. } => {
             paths.push(file.clone());
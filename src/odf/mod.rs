@@ -0,0 +1,230 @@
+mod package;
+mod text;
+
+use std::error::Error;
+use std::sync::LazyLock;
+use quick_xml::escape::escape;
+use regex::{Captures, Regex};
+use crate::calc::Table;
+use crate::calc::engine::{a1_formula, Cell, Value};
+use crate::doc::Document;
+use crate::framework::Printer;
+use crate::show::Presentation;
+use package::Package;
+use text::{TEXT_STYLES, TextWriter};
+
+const NAMESPACES: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+    xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+    xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+    xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+    xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" \
+    xmlns:presentation=\"urn:oasis:names:tc:opendocument:xmlns:presentation:1.0\" \
+    xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+    xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" \
+    xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+    xmlns:of=\"urn:oasis:names:tc:opendocument:xmlns:of:1.2\" \
+    office:version=\"1.3\"";
+
+const SLIDE_STYLES: &str = "<office:automatic-styles>\
+    <style:page-layout style:name=\"Slide\"><style:page-layout-properties fo:margin=\"0cm\" fo:page-width=\"28cm\" fo:page-height=\"15.75cm\" style:print-orientation=\"landscape\"/></style:page-layout>\
+    <style:style style:name=\"Page\" style:family=\"drawing-page\"/>\
+    </office:automatic-styles>\
+    <office:master-styles><style:master-page style:name=\"Default\" style:page-layout-name=\"Slide\" draw:style-name=\"Page\"/></office:master-styles>";
+
+/// Prints spreadsheets, documents and presentations as editable OpenDocument files.
+pub struct OdfPrinter;
+
+impl OdfPrinter {
+    pub fn new() -> Self {
+        OdfPrinter
+    }
+}
+
+impl Printer<Table> for OdfPrinter {
+    fn print(&self, table: Table) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut body = String::from("<table:table table:name=\"Sheet1\">");
+        for row in table.rows() {
+            body += "<table:table-row>";
+            let mut column = 0;
+            for cell in row {
+                if cell.position.column() > column {
+                    body += &format!("<table:table-cell table:number-columns-repeated=\"{}\"/>", cell.position.column() - column);
+                }
+                body += &table_cell(cell, &table);
+                column = cell.position.column() + 1;
+            }
+            body += "</table:table-row>";
+        }
+        body += "</table:table>";
+
+        let mut package = Package::new("application/vnd.oasis.opendocument.spreadsheet");
+        package.add("content.xml", "text/xml", content("", &format!("<office:spreadsheet>{}</office:spreadsheet>", body)));
+        package.finish()
+    }
+
+    fn extension() -> &'static str {
+        "ods"
+    }
+}
+
+impl Printer<Document> for OdfPrinter {
+    fn print(&self, document: Document) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut writer = TextWriter::new();
+        writer.write(document.elements()?);
+        let (text, pictures) = writer.finish();
+
+        let mut package = Package::new("application/vnd.oasis.opendocument.text");
+        package.add("content.xml", "text/xml", content(TEXT_STYLES, &format!("<office:text>{}</office:text>", text)));
+        for (path, media_type, bytes) in pictures {
+            package.add(&path, &media_type, bytes);
+        }
+        package.finish()
+    }
+
+    fn extension() -> &'static str {
+        "odt"
+    }
+}
+
+impl Printer<Presentation> for OdfPrinter {
+    fn print(&self, presentation: Presentation) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut pages = String::new();
+        let mut pictures = Vec::new();
+        for (index, slide) in presentation.slides()?.enumerate() {
+            let mut writer = TextWriter::with_pictures(pictures);
+            writer.write(slide.elements()?);
+            let (text, slide_pictures) = writer.finish();
+            pictures = slide_pictures;
+
+            pages += &format!(
+                "<draw:page draw:name=\"Slide{}\" draw:master-page-name=\"Default\">\
+                 <draw:frame svg:x=\"1.5cm\" svg:y=\"1cm\" svg:width=\"25cm\" svg:height=\"13.75cm\"><draw:text-box>{}</draw:text-box></draw:frame>\
+                 </draw:page>",
+                index + 1, text
+            );
+        }
+
+        let mut package = Package::new("application/vnd.oasis.opendocument.presentation");
+        package.add("content.xml", "text/xml", content(TEXT_STYLES, &format!("<office:presentation>{}</office:presentation>", pages)));
+        package.add("styles.xml", "text/xml", format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-styles {}>{}</office:document-styles>",
+            NAMESPACES, SLIDE_STYLES
+        ).into_bytes());
+        for (path, media_type, bytes) in pictures {
+            package.add(&path, &media_type, bytes);
+        }
+        package.finish()
+    }
+
+    fn extension() -> &'static str {
+        "odp"
+    }
+}
+
+fn content(styles: &str, body: &str) -> Vec<u8> {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-content {}>\
+         <office:automatic-styles>{}</office:automatic-styles><office:body>{}</office:body></office:document-content>",
+        NAMESPACES, styles, body
+    ).into_bytes()
+}
+
+fn table_cell(cell: &Cell, table: &Table) -> String {
+    let formula = match &cell.formula {
+        Some(formula) => format!(" table:formula=\"of:={}\"", escape(&open_formula(&a1_formula(&table.locale().canonical_formula(&formula[1..]), cell.position)))),
+        None => String::new(),
+    };
+    let value = match &cell.value {
+        Some(Value::Number(n)) => format!(" office:value-type=\"float\" office:value=\"{}\"", n),
+        Some(Value::Bool(b)) => format!(" office:value-type=\"boolean\" office:boolean-value=\"{}\"", b),
+        Some(Value::Text(_)) | Some(Value::Error(_)) | Some(Value::Quantity(..)) => " office:value-type=\"string\"".to_string(),
+        Some(Value::Empty) | None if formula.is_empty() => return "<table:table-cell/>".to_string(),
+        Some(Value::Empty) | None => String::new(),
+    };
+    format!("<table:table-cell{}{}><text:p>{}</text:p></table:table-cell>", formula, value, escape(&cell.content))
+}
+
+/// A reference or range, followed by `(` when it is a function name such as `LOG10`.
+static REFERENCES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\$?[A-Z]+\$?\d+)(:(\$?[A-Z]+\$?\d+))?(\(?)").unwrap());
+
+/// Rewrites a formula into OpenFormula, which brackets references and separates arguments with `;`.
+fn open_formula(formula: &str) -> String {
+    formula.split('"')
+        .enumerate()
+        .map(|(i, part)| if i % 2 == 1 {
+            part.to_string()
+        } else {
+            let part = part.replace(',', ";");
+            REFERENCES.replace_all(&part, |c: &Captures| match c.get(3) {
+                _ if !c[4].is_empty() => c[0].to_string(),
+                Some(end) => format!("[.{}:.{}]", &c[1], end.as_str()),
+                None => format!("[.{}]", &c[1]),
+            }).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+    use crate::calc::Calc;
+    use crate::doc::Document;
+    use crate::framework::{Loader, Printer};
+    use crate::odf::{open_formula, OdfPrinter};
+    use crate::show::Presentation;
+
+    fn entry(package: &[u8], name: &str) -> String {
+        let mut archive = ZipArchive::new(Cursor::new(package)).unwrap();
+        assert_eq!("mimetype", archive.by_index(0).unwrap().name());
+        let mut content = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn converts_formulas() {
+        assert_eq!("SUM([.A1:.$B$2];[.C3])&\"A1,B2\"", open_formula("SUM(A1:$B$2,C3)&\"A1,B2\""));
+        assert_eq!("LOG10([.A1])", open_formula("LOG10(A1)"));
+    }
+
+    #[test]
+    fn prints_spreadsheets() {
+        let table = Calc::from_string("Item,,Price\n<a>,,2\nTotal,,=SUM(R[-1]C)*2".to_string()).load().unwrap();
+        let package = OdfPrinter::new().print(table).unwrap();
+
+        assert_eq!("application/vnd.oasis.opendocument.spreadsheet", entry(&package, "mimetype"));
+        let content = entry(&package, "content.xml");
+        assert!(content.contains("<table:table-cell office:value-type=\"string\"><text:p>&lt;a&gt;</text:p></table:table-cell><table:table-cell/>"), "{}", content);
+        assert!(content.contains("<table:table-cell table:formula=\"of:=SUM([.C2])*2\" office:value-type=\"float\" office:value=\"4\"><text:p>4.00</text:p></table:table-cell>"), "{}", content);
+        assert!(entry(&package, "META-INF/manifest.xml").contains("manifest:full-path=\"content.xml\""));
+    }
+
+    #[test]
+    fn prints_documents() {
+        let document = Document::new("# Title\n\nSome *text* and `code`.\n\n- one\n- two\n\n```\na  b\n```\n\n<table><tr><th>Name</th></tr><tr><td>Ada &amp; Alan</td></tr></table>\n");
+        let package = OdfPrinter::new().print(document).unwrap();
+
+        assert_eq!("application/vnd.oasis.opendocument.text", entry(&package, "mimetype"));
+        let content = entry(&package, "content.xml");
+        assert!(content.contains("<text:h text:style-name=\"H1\" text:outline-level=\"1\">Title</text:h>"), "{}", content);
+        assert!(content.contains("<text:p>Some <text:span text:style-name=\"Emphasis\">text</text:span> and <text:span text:style-name=\"CodeSpan\">code</text:span>.</text:p>"), "{}", content);
+        assert!(content.contains("<text:list text:style-name=\"Bullets\"><text:list-item><text:p>one</text:p></text:list-item>"), "{}", content);
+        assert!(content.contains("<text:p text:style-name=\"Code\">a <text:s text:c=\"1\"/>b</text:p>"), "{}", content);
+        assert!(content.contains("<table:table><table:table-column table:number-columns-repeated=\"1\"/><table:table-row><table:table-cell office:value-type=\"string\"><text:p>Name</text:p></table:table-cell></table:table-row><table:table-row><table:table-cell office:value-type=\"string\"><text:p>Ada &amp; Alan</text:p></table:table-cell></table:table-row></table:table>"), "{}", content);
+    }
+
+    #[test]
+    fn prints_presentations() {
+        let presentation = Presentation::new("# One\n<!--slide-->\n# Two\n![dot](data:image/png;base64,iVBORw0KGgo=)");
+        let package = OdfPrinter::new().print(presentation).unwrap();
+
+        assert_eq!("application/vnd.oasis.opendocument.presentation", entry(&package, "mimetype"));
+        let content = entry(&package, "content.xml");
+        assert_eq!(2, content.matches("<draw:page ").count(), "{}", content);
+        assert!(content.contains("xlink:href=\"Pictures/image1.png\""), "{}", content);
+        assert!(entry(&package, "styles.xml").contains("style:master-page style:name=\"Default\""));
+        assert!(entry(&package, "META-INF/manifest.xml").contains("manifest:full-path=\"Pictures/image1.png\" manifest:media-type=\"image/png\""));
+    }
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// An OpenDocument zip package: the mimetype, a manifest and the files it lists.
+pub struct Package {
+    mimetype: &'static str,
+    files: Vec<(String, String, Vec<u8>)>,
+}
+
+impl Package {
+    pub fn new(mimetype: &'static str) -> Self {
+        Package { mimetype, files: Vec::new() }
+    }
+
+    pub fn add(&mut self, path: &str, media_type: &str, content: Vec<u8>) {
+        self.files.push((path.to_string(), media_type.to_string(), content));
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+        // The mimetype must come first and uncompressed so the format can be sniffed.
+        zip.start_file("mimetype", FileOptions::default().compression_method(CompressionMethod::Stored))?;
+        zip.write_all(self.mimetype.as_bytes())?;
+
+        let mut manifest = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.3\">\
+             <manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.3\" manifest:media-type=\"{}\"/>",
+            self.mimetype
+        );
+        for (path, media_type, content) in &self.files {
+            manifest += &format!("<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"{}\"/>", path, media_type);
+            zip.start_file(path, FileOptions::default())?;
+            zip.write_all(content)?;
+        }
+        manifest += "</manifest:manifest>";
+
+        zip.start_file("META-INF/manifest.xml", FileOptions::default())?;
+        zip.write_all(manifest.as_bytes())?;
+        Ok(zip.finish()?.into_inner())
+    }
+}
@@ -0,0 +1,279 @@
+use pulldown_cmark::{Event, Tag};
+use quick_xml::escape::{escape, unescape};
+use regex::Regex;
+
+/// Automatic styles used by the text written by [`TextWriter`].
+pub const TEXT_STYLES: &str = r##"<style:style style:name="H1" style:family="paragraph"><style:paragraph-properties fo:margin-top="0.4cm" fo:margin-bottom="0.2cm"/><style:text-properties fo:font-size="200%" fo:font-weight="bold"/></style:style>
+<style:style style:name="H2" style:family="paragraph"><style:paragraph-properties fo:margin-top="0.35cm" fo:margin-bottom="0.2cm"/><style:text-properties fo:font-size="150%" fo:font-weight="bold"/></style:style>
+<style:style style:name="H3" style:family="paragraph"><style:paragraph-properties fo:margin-top="0.3cm" fo:margin-bottom="0.2cm"/><style:text-properties fo:font-size="130%" fo:font-weight="bold"/></style:style>
+<style:style style:name="H4" style:family="paragraph"><style:text-properties fo:font-size="115%" fo:font-weight="bold"/></style:style>
+<style:style style:name="H5" style:family="paragraph"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="H6" style:family="paragraph"><style:text-properties fo:font-style="italic" fo:font-weight="bold"/></style:style>
+<style:style style:name="Code" style:family="paragraph"><style:paragraph-properties fo:background-color="#f4f4f4" fo:padding="0.1cm"/><style:text-properties fo:font-family="'Courier New'" style:font-family-generic="modern" style:font-pitch="fixed"/></style:style>
+<style:style style:name="Quote" style:family="paragraph"><style:paragraph-properties fo:margin-left="1cm" fo:border-left="0.06cm solid #cccccc" fo:padding-left="0.2cm"/><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="Rule" style:family="paragraph"><style:paragraph-properties fo:border-bottom="0.03cm solid #999999"/></style:style>
+<style:style style:name="Emphasis" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="Strong" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Strike" style:family="text"><style:text-properties style:text-line-through-style="solid"/></style:style>
+<style:style style:name="CodeSpan" style:family="text"><style:text-properties fo:font-family="'Courier New'" style:font-family-generic="modern" style:font-pitch="fixed"/></style:style>
+<text:list-style style:name="Bullets"><text:list-level-style-bullet text:level="1" text:bullet-char="•"><style:list-level-properties text:space-before="0.5cm" text:min-label-width="0.5cm"/></text:list-level-style-bullet><text:list-level-style-bullet text:level="2" text:bullet-char="◦"><style:list-level-properties text:space-before="1cm" text:min-label-width="0.5cm"/></text:list-level-style-bullet><text:list-level-style-bullet text:level="3" text:bullet-char="▪"><style:list-level-properties text:space-before="1.5cm" text:min-label-width="0.5cm"/></text:list-level-style-bullet></text:list-style>
+<text:list-style style:name="Numbers"><text:list-level-style-number text:level="1" style:num-format="1" style:num-suffix="."><style:list-level-properties text:space-before="0.5cm" text:min-label-width="0.5cm"/></text:list-level-style-number><text:list-level-style-number text:level="2" style:num-format="1" style:num-suffix="."><style:list-level-properties text:space-before="1cm" text:min-label-width="0.5cm"/></text:list-level-style-number><text:list-level-style-number text:level="3" style:num-format="1" style:num-suffix="."><style:list-level-properties text:space-before="1.5cm" text:min-label-width="0.5cm"/></text:list-level-style-number></text:list-style>"##;
+
+/// Writes markdown events as OpenDocument text, collecting embedded pictures along the way.
+pub struct TextWriter {
+    xml: String,
+    paragraph: Option<&'static str>,
+    code: bool,
+    quotes: usize,
+    image: bool,
+    pub pictures: Vec<(String, String, Vec<u8>)>,
+}
+
+impl TextWriter {
+    pub fn new() -> Self {
+        TextWriter { xml: String::new(), paragraph: None, code: false, quotes: 0, image: false, pictures: Vec::new() }
+    }
+
+    /// Continues numbering pictures after those of an earlier writer.
+    pub fn with_pictures(pictures: Vec<(String, String, Vec<u8>)>) -> Self {
+        TextWriter { pictures, ..TextWriter::new() }
+    }
+
+    pub fn write<'a>(&mut self, events: impl Iterator<Item=Event<'a>>) {
+        for event in events {
+            self.event(event);
+        }
+        self.close();
+    }
+
+    pub fn finish(self) -> (String, Vec<(String, String, Vec<u8>)>) {
+        (self.xml, self.pictures)
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => self.open(self.paragraph_start(), "</text:p>"),
+                Tag::Heading(level, ..) => {
+                    let level = level as usize;
+                    self.close();
+                    self.xml += &format!("<text:h text:style-name=\"H{}\" text:outline-level=\"{}\">", level, level);
+                    self.paragraph = Some("</text:h>");
+                },
+                Tag::BlockQuote => {
+                    self.close();
+                    self.quotes += 1;
+                },
+                Tag::CodeBlock(_) => {
+                    self.open("<text:p text:style-name=\"Code\">", "</text:p>");
+                    self.code = true;
+                },
+                Tag::List(start) => {
+                    self.close();
+                    let style = if start.is_some() { "Numbers" } else { "Bullets" };
+                    self.xml += &format!("<text:list text:style-name=\"{}\">", style);
+                },
+                Tag::Item => {
+                    self.close();
+                    self.xml += "<text:list-item>";
+                },
+                Tag::Emphasis => self.span("Emphasis"),
+                Tag::Strong => self.span("Strong"),
+                Tag::Strikethrough => self.span("Strike"),
+                Tag::Link(_, url, _) => {
+                    self.inline();
+                    self.xml += &format!("<text:a xlink:type=\"simple\" xlink:href=\"{}\">", escape(&url));
+                },
+                Tag::Image(_, url, _) => {
+                    self.inline();
+                    self.picture(&url);
+                    self.image = true;
+                },
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                Tag::Paragraph | Tag::Heading(..) => self.close(),
+                Tag::BlockQuote => {
+                    self.close();
+                    self.quotes -= 1;
+                },
+                Tag::CodeBlock(_) => {
+                    if self.xml.ends_with("<text:line-break/>") {
+                        self.xml.truncate(self.xml.len() - "<text:line-break/>".len());
+                    }
+                    self.code = false;
+                    self.close();
+                },
+                Tag::List(_) => {
+                    self.close();
+                    self.xml += "</text:list>";
+                },
+                Tag::Item => {
+                    self.close();
+                    self.xml += "</text:list-item>";
+                },
+                Tag::Emphasis | Tag::Strong | Tag::Strikethrough => self.xml += "</text:span>",
+                Tag::Link(..) => self.xml += "</text:a>",
+                Tag::Image(..) => self.image = false,
+                _ => {}
+            },
+            Event::Text(_) if self.image => {},
+            Event::Text(text) if self.code => {
+                self.inline();
+                self.xml += &preformatted(&text);
+            },
+            Event::Text(text) => {
+                self.inline();
+                self.xml += &escape(&text);
+            },
+            Event::Code(text) => {
+                self.inline();
+                self.xml += &format!("<text:span text:style-name=\"CodeSpan\">{}</text:span>", preformatted(&text));
+            },
+            Event::Html(html) => self.html(&html),
+            Event::SoftBreak => {
+                self.inline();
+                self.xml += " ";
+            },
+            Event::HardBreak => {
+                self.inline();
+                self.xml += "<text:line-break/>";
+            },
+            Event::Rule => {
+                self.close();
+                self.xml += "<text:p text:style-name=\"Rule\"/>";
+            },
+            _ => {}
+        }
+    }
+
+    /// Raw HTML has no ODF equivalent, so inline SVG becomes a picture, tables such as query
+    /// results keep their cells, highlighted code keeps its text and other markup is dropped.
+    fn html(&mut self, html: &str) {
+        let trimmed = html.trim_start();
+        if trimmed.starts_with("<svg") {
+            self.inline();
+            let path = self.add_picture("svg", "image/svg+xml", html.as_bytes().to_vec());
+            self.frame(&path);
+        } else if trimmed.starts_with("<table") {
+            self.close_block();
+            self.xml += &table(html);
+        } else if trimmed.starts_with("<pre") {
+            self.close();
+            self.open("<text:p text:style-name=\"Code\">", "</text:p>");
+            self.xml += &preformatted(strip_html(html).trim_end_matches('\n'));
+            self.close();
+        } else {
+            let text = strip_html(html);
+            if !text.trim().is_empty() {
+                self.inline();
+                self.xml += &escape(&text);
+            }
+        }
+    }
+
+    fn picture(&mut self, url: &str) {
+        let embedded = url.strip_prefix("data:image/")
+            .and_then(|data| data.split_once(";base64,"))
+            .and_then(|(extension, data)| base64::decode(data).ok().map(|bytes| (extension.to_string(), bytes)));
+        let path = match embedded {
+            Some((extension, bytes)) => {
+                let media_type = format!("image/{}", if extension == "jpg" { "jpeg" } else { &extension });
+                self.add_picture(&extension, &media_type, bytes)
+            },
+            None => url.to_string(),
+        };
+        self.frame(&path);
+    }
+
+    fn add_picture(&mut self, extension: &str, media_type: &str, bytes: Vec<u8>) -> String {
+        let path = format!("Pictures/image{}.{}", self.pictures.len() + 1, extension);
+        self.pictures.push((path.clone(), media_type.to_string(), bytes));
+        path
+    }
+
+    fn frame(&mut self, path: &str) {
+        self.xml += &format!(
+            "<draw:frame text:anchor-type=\"as-char\" svg:width=\"12cm\" style:rel-width=\"100%\" style:rel-height=\"scale\">\
+             <draw:image xlink:href=\"{}\" xlink:type=\"simple\" xlink:show=\"embed\" xlink:actuate=\"onLoad\"/></draw:frame>",
+            escape(path)
+        );
+    }
+
+    fn open(&mut self, start: &str, end: &'static str) {
+        self.close();
+        self.xml += start;
+        self.paragraph = Some(end);
+    }
+
+    fn paragraph_start(&self) -> &'static str {
+        if self.quotes > 0 { "<text:p text:style-name=\"Quote\">" } else { "<text:p>" }
+    }
+
+    /// Opens a paragraph for inline content that arrives outside of one, as in tight list items.
+    fn inline(&mut self) {
+        if self.paragraph.is_none() {
+            self.open(self.paragraph_start(), "</text:p>");
+        }
+    }
+
+    fn span(&mut self, style: &str) {
+        self.inline();
+        self.xml += &format!("<text:span text:style-name=\"{}\">", style);
+    }
+
+    fn close(&mut self) {
+        if let Some(end) = self.paragraph.take() {
+            self.xml += end;
+        }
+    }
+
+    /// Closes the open paragraph before a block that cannot sit inside one, dropping it
+    /// when it is still empty.
+    fn close_block(&mut self) {
+        let start = self.paragraph_start();
+        if self.paragraph == Some("</text:p>") && self.xml.ends_with(start) {
+            self.xml.truncate(self.xml.len() - start.len());
+            self.paragraph = None;
+        }
+        self.close();
+    }
+}
+
+/// Escapes text keeping its line breaks, tabs and runs of spaces.
+fn preformatted(text: &str) -> String {
+    let spaces = Regex::new(" {2,}").unwrap();
+    let lines: Vec<String> = text.split('\n')
+        .map(|line| {
+            let line = escape(line).replace('\t', "<text:tab/>");
+            spaces.replace_all(&line, |c: &regex::Captures| format!(" <text:s text:c=\"{}\"/>", c[0].len() - 1)).to_string()
+        })
+        .collect();
+    lines.join("<text:line-break/>")
+}
+
+/// An HTML table as an ODF one, with the text of each cell.
+fn table(html: &str) -> String {
+    let rows = Regex::new(r"(?s)<tr[^>]*>(.*?)</tr>").unwrap();
+    let cells = Regex::new(r"(?s)<t[hd][^>]*>(.*?)</t[hd]>").unwrap();
+    let rows: Vec<Vec<String>> = rows.captures_iter(html)
+        .map(|row| cells.captures_iter(&row[1]).map(|cell| strip_html(&cell[1])).collect())
+        .collect();
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut xml = format!("<table:table><table:table-column table:number-columns-repeated=\"{}\"/>", columns);
+    for row in rows {
+        xml += "<table:table-row>";
+        for cell in row {
+            xml += &format!("<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>", escape(&cell));
+        }
+        xml += "</table:table-row>";
+    }
+    xml + "</table:table>"
+}
+
+fn strip_html(html: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = tags.replace_all(html, "");
+    unescape(&text).map(|t| t.to_string()).unwrap_or_else(|_| text.to_string())
+}